@@ -0,0 +1 @@
+pub mod scy_gemini_api;
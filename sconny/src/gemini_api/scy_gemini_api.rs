@@ -0,0 +1,260 @@
+use std::env;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scy_api::ScyApiError;
+use crate::scy_backend::{BackendCapabilities, ChatRequest, ChatRole, LlmBackend};
+use crate::scy_http::{HttpClient, DEFAULT_TIMEOUT_SECS};
+use crate::scy_setting::{LlmService, SconnySetting};
+
+pub const DEFAULT_GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+pub const DEFAULT_GEMINI_MODEL: &str = "gemini-2.0-flash";
+
+const MISSING_KEY_HINT: &str = "GEMINI_API_KEY or SCONNY_GEMINI_API_KEY";
+
+pub struct GeminiApi {
+    pub base_url: String,     // e.g. https://generativelanguage.googleapis.com (로컬 stub 가능)
+    pub model: String,
+    http: HttpClient,
+}
+
+// -------------------- generateContent request/response --------------------
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest<'a> {
+    system_instruction: Content<'a>,
+    contents: Vec<Content<'a>>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'a str>,
+    parts: Vec<Part<'a>>,
+}
+
+#[derive(Serialize)]
+struct Part<'a> {
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    response_mime_type: &'static str,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<CandidateContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<CandidatePart>,
+}
+
+#[derive(Deserialize)]
+struct CandidatePart {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorEnvelope {
+    error: ErrorBody,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    code: Option<i64>,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    status: String,
+}
+
+impl GeminiApi {
+    pub fn from_setting(setting: &SconnySetting) -> Self {
+        let base_url = setting
+            .base_url(LlmService::Gemini)
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| DEFAULT_GEMINI_BASE_URL.to_string());
+
+        let model = setting
+            .model_for(LlmService::Gemini)
+            .or_else(|| env::var("SCONNY_GEMINI_MODEL").ok().filter(|s| !s.trim().is_empty()))
+            .unwrap_or_else(|| DEFAULT_GEMINI_MODEL.to_string());
+
+        let timeout_secs = setting
+            .provider(LlmService::Gemini)
+            .and_then(|p| p.timeout_sec)
+            .or_else(|| env::var("SCONNY_GEMINI_TIMEOUT_SECS").ok().and_then(|s| s.parse::<u64>().ok()))
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        Self { base_url, model, http: HttpClient::new(timeout_secs) }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/v1beta{}", self.base_url.trim_end_matches('/'), path)
+    }
+}
+
+impl LlmBackend for GeminiApi {
+    fn service(&self) -> LlmService {
+        LlmService::Gemini
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities { json_mode: true, streaming: false, list_models: true, requires_api_key: true }
+    }
+
+    /// models/{model}:generateContent 호출 (responseMimeType=application/json), 첫 candidate의 text만 리턴
+    fn chat(&self, req: &ChatRequest) -> Result<String, ScyApiError> {
+        let api_key = get_gemini_api_key().ok_or(ScyApiError::MissingApiKey(MISSING_KEY_HINT))?;
+        let url = self.url(&format!("/models/{}:generateContent", self.model.trim_start_matches("models/")));
+
+        let body_req = GenerateContentRequest {
+            system_instruction: Content { role: None, parts: vec![Part { text: &req.system }] },
+            contents: req
+                .messages
+                .iter()
+                .map(|m| Content {
+                    // Gemini는 assistant 대신 "model" role 사용
+                    role: Some(match m.role {
+                        ChatRole::User => "user",
+                        ChatRole::Assistant => "model",
+                    }),
+                    parts: vec![Part { text: &m.content }],
+                })
+                .collect(),
+            generation_config: GenerationConfig { response_mime_type: "application/json" },
+        };
+        let body = serde_json::to_string(&body_req)
+            .map_err(|e| ScyApiError::ParseFailed(format!("failed to encode request body: {}", e)))?;
+
+        let raw = self
+            .http
+            .post_json(&url, &[("x-goog-api-key", &api_key)], &body)
+            .map_err(map_error_payload)?;
+
+        extract_candidate_text(&raw)
+    }
+
+    fn list_models(&self) -> Result<Vec<String>, ScyApiError> {
+        let api_key = get_gemini_api_key().ok_or(ScyApiError::MissingApiKey(MISSING_KEY_HINT))?;
+        let raw = self
+            .http
+            .get(&self.url("/models"), &[("x-goog-api-key", &api_key)])
+            .map_err(map_error_payload)?;
+
+        let list: ModelList = serde_json::from_str(&raw)
+            .map_err(|e| ScyApiError::ParseFailed(format!("invalid /v1beta/models response: {}", e)))?;
+        let mut names: Vec<String> = list
+            .models
+            .into_iter()
+            .map(|m| m.name.trim_start_matches("models/").to_string())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn health_check(&self) -> Result<String, ScyApiError> {
+        let models = self.list_models()?;
+        Ok(format!("ok ({} models visible)", models.len()))
+    }
+}
+
+fn get_gemini_api_key() -> Option<String> {
+    // 우선순위: SCONNY_GEMINI_API_KEY -> GEMINI_API_KEY -> GOOGLE_API_KEY
+    env::var("SCONNY_GEMINI_API_KEY")
+        .ok()
+        .or_else(|| env::var("GEMINI_API_KEY").ok())
+        .or_else(|| env::var("GOOGLE_API_KEY").ok())
+        .filter(|s| !s.trim().is_empty())
+}
+
+/// 4xx/5xx 응답 본문이 {"error":{...}} 형식이면 ScyApiError::Api로 변환
+fn map_error_payload(e: ScyApiError) -> ScyApiError {
+    match e {
+        ScyApiError::Http { status, body } => parse_error_payload(&body).unwrap_or(ScyApiError::Http { status, body }),
+        other => other,
+    }
+}
+
+fn parse_error_payload(raw: &str) -> Option<ScyApiError> {
+    let env: ErrorEnvelope = serde_json::from_str(raw).ok()?;
+    Some(ScyApiError::Api {
+        code: env.error.code,
+        status: env.error.status,
+        message: env.error.message,
+    })
+}
+
+/// {"candidates":[{"content":{"parts":[{"text":"..."}]},"finishReason":"STOP"}]} 에서 text만 추출
+fn extract_candidate_text(raw: &str) -> Result<String, ScyApiError> {
+    if let Some(e) = parse_error_payload(raw) {
+        return Err(e);
+    }
+
+    let resp: GenerateContentResponse =
+        serde_json::from_str(raw).map_err(|e| ScyApiError::ParseFailed(format!("invalid generateContent response: {}", e)))?;
+
+    if let Some(reason) = resp.prompt_feedback.and_then(|f| f.block_reason) {
+        return Err(ScyApiError::Blocked(format!("prompt blocked ({})", reason)));
+    }
+
+    let cand = resp
+        .candidates
+        .into_iter()
+        .next()
+        .ok_or(ScyApiError::ParseFailed("response has no candidates".to_string()))?;
+
+    let text: String = cand
+        .content
+        .map(|c| c.parts.into_iter().filter_map(|p| p.text).collect())
+        .unwrap_or_default();
+
+    if text.trim().is_empty() {
+        return match cand.finish_reason.as_deref() {
+            Some("MAX_TOKENS") => Err(ScyApiError::Incomplete("MAX_TOKENS".to_string())),
+            Some(r) if r != "STOP" => Err(ScyApiError::Blocked(format!("generation stopped ({})", r))),
+            _ => Err(ScyApiError::ParseFailed("failed to extract candidate text".to_string())),
+        };
+    }
+
+    Ok(text.trim().to_string())
+}
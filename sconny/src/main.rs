@@ -1,32 +1,45 @@
 // main.rs
 mod scy_api;
+mod scy_backend;
+mod scy_http;
 mod scy_console;
 mod scy_prompt;
 mod scy_setting;
+mod scy_signal;
 // mod scy_gui; // 차후 추가
 mod ollama_api;
+mod gemini_api;
+mod openai_api;
 mod scy_executor;
+mod scy_analyzer;
+mod scy_policy;
+mod scy_shell;
+mod scy_output;
+mod scy_sandbox;
+mod scy_preview;
+mod scy_journal;
+mod scy_process;
+mod scy_schema;
+mod scy_session;
+mod scy_history;
+mod scy_tools;
+mod scy_listing;
+mod scy_git;
 
 
 use scy_api::{ScyApi, ScyApiError};
-use scy_console::{parse_console_request_from_args, run_repl_loop, ConsoleMode};
-use scy_prompt::build_prompt;
+use scy_backend::ChatMessage;
+use scy_console::{parse_console_request_from_args, run_repl_loop, ConsoleMode, ExplainStreamer, HistoryCmd, RunFlags};
+use scy_history::HistoryEntry;
+use scy_output::{say, Output};
+use scy_prompt::{build_prompt, build_reask_prompt, build_repair_prompt, Prompt};
+use scy_session::Session;
 use scy_setting::SconnySetting;
-use crate::ollama_api::scy_ollama_api::{OllamaApi, OllamaError};
+use std::io::{self, Write};
 
 
 fn main() {
-    // 1) setting load
-    let mut setting = SconnySetting::new();
-    if let Err(e) = setting.load_setting() {
-        eprintln!("Setting load error: {}", e);
-        return;
-    }
-
-    // 2) API client
-    let api = ScyApi::new();
-
-    // 3) parse console input (oneshot / repl)
+    // 1) parse console input (oneshot / repl / config)
     let req = match parse_console_request_from_args() {
         Ok(v) => v,
         Err(help_or_error) => {
@@ -35,21 +48,74 @@ fn main() {
         }
     };
 
-    // One-shot
-    if let Some(r) = req {
-        if r.mode == ConsoleMode::OneShot {
-            if let Err(msg) = process_request(&setting, &api, &r.text) {
-                eprintln!("{}", msg);
+    // 2) setting load: 설정 파일 레이어 → env → CLI
+    let mut setting = SconnySetting::new();
+    if let Err(e) = setting.load_setting(req.config_path.as_deref()) {
+        eprintln!("Setting load error: {}", e);
+        return;
+    }
+    for o in &req.overrides {
+        if let Err(e) = setting.apply_cli_override(&o.key, &o.value, &o.flag) {
+            eprintln!("{}", e);
+            return;
+        }
+    }
+
+    match &req.mode {
+        ConsoleMode::ConfigShow { origin } => {
+            print_config(&setting, *origin);
+            return;
+        }
+        ConsoleMode::History { cmd } => {
+            if !run_history(&setting, cmd, req.flags) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        ConsoleMode::Undo { id, list } => {
+            if !run_undo(id.as_deref(), *list, req.flags.yes) {
+                std::process::exit(1);
             }
             return;
         }
+        ConsoleMode::OneShot | ConsoleMode::Repl | ConsoleMode::Models => {}
     }
 
-    // REPL
-    let result = run_repl_loop(|line| {
-        if let Err(msg) = process_request(&setting, &api, line) {
+    // 3) API client
+    let api = ScyApi::new(&setting);
+
+    // Ctrl+C: 스트리밍 중이면 취소, 아니면 종료
+    if let Err(e) = scy_signal::install_handler() {
+        eprintln!("{}", e);
+    }
+
+    if req.mode == ConsoleMode::Models {
+        if let Err(msg) = handle_repl_command(&setting, &api, "models") {
             eprintln!("{}", msg);
         }
+        return;
+    }
+
+    // One-shot
+    if req.mode == ConsoleMode::OneShot {
+        if !process_request(&setting, &api, &req.text, req.flags, None) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // REPL: 세션 동안 이전 요청/계획/결과를 대화 기록으로 보냄
+    let mut session = Session::new(setting.history_tokens);
+    let result = run_repl_loop(|line| {
+        if let Some(cmd) = line.strip_prefix(':') {
+            if cmd.trim() == "reset" {
+                println!("Session history cleared ({} turn(s) forgotten).", session.len());
+                session.reset();
+                return Ok(());
+            }
+            return handle_repl_command(&setting, &api, cmd);
+        }
+        process_request(&setting, &api, line, req.flags, Some(&mut session));
         Ok(()) // 에러가 나도 REPL은 계속
     });
 
@@ -58,31 +124,451 @@ fn main() {
     }
 }
 
-fn process_request(setting: &SconnySetting, api: &ScyApi, user_text: &str) -> Result<(), String> {
+/// `sconny config show [--origin]`: 유효한 설정 값 (origin=true면 값을 정한 레이어도 표시)
+fn print_config(setting: &SconnySetting, origin: bool) {
+    println!("# config files (later layers override earlier ones)");
+    for f in &setting.config_files {
+        let state = if f.loaded { "loaded" } else { "not found" };
+        println!("#   {:<8} {} ({})", f.layer.name(), f.path.display(), state);
+    }
+    println!();
+
+    for (key, value) in setting.effective_values() {
+        if origin {
+            println!("{} = {}  # {}", key, value, setting.origin_of(&key));
+        } else {
+            println!("{} = {}", key, value);
+        }
+    }
+}
+
+/// `sconny undo [<plan-id>]` / `sconny undo --list`
+fn run_undo(id: Option<&str>, list: bool, assume_yes: bool) -> bool {
+    if list {
+        match scy_journal::list() {
+            Ok(all) if all.is_empty() => println!("No undo journals."),
+            Ok(all) => {
+                for j in all.iter().rev() {
+                    let state = if j.undone { "undone" } else { "" };
+                    println!("{:<18} {:>3} paths  {:<6}  {}", j.id, j.entries.len(), state, j.request);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                return false;
+            }
+        }
+        return true;
+    }
+
+    let mut journal = match scy_journal::find(id) {
+        Ok(j) => j,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    println!("Plan {}: {}", journal.id, journal.request);
+    println!("Directory: {}", journal.cwd.display());
+    for (i, c) in journal.cmd.iter().enumerate() {
+        println!("  {}. {}", i + 1, c);
+    }
+    if journal.entries.is_empty() {
+        println!("No paths were recorded for this plan; nothing to restore.");
+    }
+    for u in &journal.unknown {
+        println!("Step {}: {}; those changes cannot be undone.", u.step, u.reason);
+    }
+
+    // 나중 계획이 같은 경로를 바꿨으면 이 계획을 되돌릴 때 그 변경이 사라짐
+    let conflicts = match scy_journal::newer_conflicts(&journal) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    if !conflicts.is_empty() {
+        eprintln!("\nNewer plans that have not been undone touched the same paths:");
+        for (id, what) in &conflicts {
+            eprintln!("  {}  {}", id, what);
+        }
+        eprintln!("Restoring this plan would overwrite their changes. Undo them first (newest first).");
+        if assume_yes {
+            eprintln!("Refusing to undo with --yes; run without --yes to confirm.");
+            return false;
+        }
+    }
+
+    if !assume_yes {
+        print!("\nRestore {} path(s) to their state before this plan? [y/N]: ", journal.entries.len());
+        let _ = io::stdout().flush();
+        let mut s = String::new();
+        if io::stdin().read_line(&mut s).is_err() || !matches!(s.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("Cancelled.");
+            return true;
+        }
+    }
+
+    match scy_journal::undo(&mut journal) {
+        Ok(report) => {
+            for line in &report {
+                println!("  {}", line);
+            }
+            let failed = report.iter().any(|l| l.starts_with("FAILED"));
+            println!("Undo of plan {} {}.", journal.id, if failed { "finished with errors" } else { "complete" });
+            !failed
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            false
+        }
+    }
+}
+
+/// 요청 하나 처리. 성공 여부를 리턴 (에러는 Output으로 보고됨).
+/// session: REPL 이면 대화 기록을 보내고 이번 요청/결과를 추가
+fn process_request(
+    setting: &SconnySetting,
+    api: &ScyApi,
+    user_text: &str,
+    flags: RunFlags,
+    session: Option<&mut Session>,
+) -> bool {
     let user_text = user_text.trim();
     if user_text.is_empty() {
-        return Ok(());
+        return true;
+    }
+
+    let mut out = Output::new(flags.output, user_text);
+    let (status, ok) = match run_request(setting, api, user_text, flags, session.as_deref(), &mut out) {
+        Ok(status) => (status, status != "blocked"),
+        Err((status, msg)) => {
+            out.error(&msg);
+            (status, false)
+        }
+    };
+    if let Some(s) = session {
+        s.record(user_text, &out, status);
     }
+    let model = api.backend(setting.llm_service).map(|b| b.model().to_string()).unwrap_or_default();
+    record_history(setting, &out, status, setting.llm_service.name(), &model, None);
+    out.finish(status);
+    ok
+}
 
-    let prompt = build_prompt(setting, user_text).map_err(|e| format!("Prompt build error: {}", e))?;
+/// 요청 하나를 history 에 추가 (실패해도 요청 결과에는 영향 없음)
+fn record_history(setting: &SconnySetting, out: &Output, status: &str, service: &str, model: &str, rerun_of: Option<u64>) {
+    if !setting.history.enabled {
+        return;
+    }
+    let mut entry = HistoryEntry::from_output(out, status, service, model);
+    entry.rerun_of = rerun_of;
+    if let Err(e) = scy_history::append(entry, &setting.history) {
+        eprintln!("[history] not recorded: {}", e);
+    }
+}
+
+/// `sconny history list/show/search/rerun`
+fn run_history(setting: &SconnySetting, cmd: &HistoryCmd, flags: RunFlags) -> bool {
+    let result = match cmd {
+        HistoryCmd::List { limit } => scy_history::load_all().map(|all| {
+            let skip = limit.map(|n| all.len().saturating_sub(n)).unwrap_or(0);
+            print_history_list(&all[skip..]);
+        }),
+        HistoryCmd::Search { query } => scy_history::search(query).map(|found| print_history_list(&found)),
+        HistoryCmd::Show { id, json } => scy_history::find(id.as_deref()).map(|e| {
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&e).unwrap_or_default());
+            } else {
+                print_history_entry(&e);
+            }
+        }),
+        HistoryCmd::Rerun { id } => return scy_history::find(id.as_deref())
+            .map(|e| rerun_history(setting, &e, flags))
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                false
+            }),
+    };
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("{}", e);
+            false
+        }
+    }
+}
+
+fn print_history_list(entries: &[HistoryEntry]) {
+    if entries.is_empty() {
+        println!("No history entries.");
+        return;
+    }
+    println!("{:>5}  {:<23}  {:<9}  request", "id", "time", "status");
+    for e in entries {
+        let mut req = e.request.replace('\n', " ");
+        if req.chars().count() > 70 {
+            req = req.chars().take(67).collect::<String>() + "...";
+        }
+        println!("{:>5}  {:<23}  {:<9}  {}", e.id, e.time_text(), e.status, req);
+    }
+}
+
+fn print_history_entry(e: &HistoryEntry) {
+    println!("#{}  {}", e.id, e.time_text());
+    println!("Request:      {}", e.request);
+    println!("Directory:    {}", e.cwd.display());
+    println!("Model:        {} / {}", e.service, e.model);
+    println!("Status:       {}", e.status);
+    if let Some(c) = &e.confirmation {
+        println!("Confirmation: {}", c);
+    }
+    if let Some(id) = e.rerun_of {
+        println!("Rerun of:     #{}", id);
+    }
+    if let Some(j) = &e.journal {
+        println!("Undo:         sconny undo {}", j);
+    }
+
+    let cmds = e.commands();
+    if !cmds.is_empty() {
+        println!("\nPlan:");
+        if let Some(explain) = e.plan.as_ref().and_then(|p| p.get("explain")).and_then(|v| v.as_str()) {
+            println!("  {}", explain);
+        }
+        for (i, c) in cmds.iter().enumerate() {
+            println!("  {}. {}", i + 1, c);
+        }
+    }
+    if !e.steps.is_empty() {
+        println!("\nSteps:");
+        for s in &e.steps {
+            let code = s.exit_code.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string());
+            let mut tags = Vec::new();
+            if s.on_failure {
+                tags.push("on_failure");
+            }
+            if s.timed_out {
+                tags.push("timed out");
+            }
+            if s.cancelled {
+                tags.push("cancelled");
+            }
+            let tags = if tags.is_empty() { String::new() } else { format!(" ({})", tags.join(", ")) };
+            println!("  {:>2}. exit={:<4} {:>6}ms  {}{}", s.index, code, s.duration_ms, s.cmd, tags);
+        }
+    }
+    if !e.errors.is_empty() {
+        println!("\nErrors:");
+        for x in &e.errors {
+            println!("  {}", x.replace('\n', "\n  "));
+        }
+    }
+    for (i, raw) in e.llm_output.iter().enumerate() {
+        println!("\n--- LLM output ({}/{}) ---\n{}", i + 1, e.llm_output.len(), raw.trim());
+    }
+}
+
+/// 기록된 계획을 현재 디렉토리에서 다시 실행 (모델에 다시 묻지 않음, 검사/확인은 그대로)
+fn rerun_history(setting: &SconnySetting, entry: &HistoryEntry, flags: RunFlags) -> bool {
+    let Some(plan) = &entry.plan else {
+        eprintln!("History entry #{} has no plan to run.", entry.id);
+        return false;
+    };
+    if let Err(e) = scy_signal::install_handler() {
+        eprintln!("{}", e);
+    }
+
+    let mut out = Output::new(flags.output, &entry.request);
+    say!(out, "Re-running plan #{} ({}): {}", entry.id, entry.time_text(), entry.request);
+    if std::env::current_dir().ok().as_ref() != Some(&entry.cwd) {
+        say!(out, "Note: it was recorded in {}; running in the current directory.", entry.cwd.display());
+    }
+    let (status, ok) = match scy_executor::handle_plan_json(setting, &plan.to_string(), flags.yes, &mut out) {
+        Ok(outcome) => (outcome.status(), outcome != scy_executor::ExecOutcome::Blocked),
+        Err(e) => {
+            out.error(&format!("Execution error: {}", e));
+            ("failed", false)
+        }
+    };
+    record_history(setting, &out, status, &entry.service, &entry.model, Some(entry.id));
+    out.finish(status);
+    ok
+}
+
+/// Err: (status, message). status는 Output::finish 참고.
+fn run_request(
+    setting: &SconnySetting,
+    api: &ScyApi,
+    user_text: &str,
+    flags: RunFlags,
+    session: Option<&Session>,
+    out: &mut Output,
+) -> Result<&'static str, (&'static str, String)> {
+    let mut prompt = build_prompt(setting, user_text).map_err(|e| ("error", format!("Prompt build error: {}", e)))?;
+    let history = session.map(Session::history).unwrap_or_default();
+    if let Some(note) = session.and_then(Session::context_note) {
+        prompt.user = format!("{}\n\n{}", note, prompt.user);
+    }
 
     if debug_enabled() {
-        println!("===== SYSTEM PROMPT =====\n{}\n", prompt.system);
-        println!("===== USER PROMPT =====\n{}\n", prompt.user);
+        say!(out, "===== SYSTEM PROMPT =====\n{}\n", prompt.system);
+        say!(out, "===== USER PROMPT =====\n{}\n", prompt.user);
+        if !history.is_empty() {
+            say!(out, "===== HISTORY: {} message(s) =====\n", history.len());
+        }
     }
 
-    match api.generate_json(setting, &prompt.user, &prompt.system) {
-        Ok(json_text) => {
-            println!("=== LLM JSON ===");
-            println!("{}", json_text);
+    // --json / --output 에서는 stdout에 레코드 외에 아무것도 섞이지 않게 스트리밍 표시를 끔
+    let streaming = setting.stream
+        && !flags.json
+        && !out.is_machine()
+        && api
+            .backend(setting.llm_service)
+            .map(|b| b.capabilities().streaming)
+            .unwrap_or(false);
+
+    let mut explain_shown = false;
+    let result = if streaming {
+        let mut explain = ExplainStreamer::new();
+        let r = api.generate_json_stream(setting, &history, &prompt.user, &prompt.system, &mut |delta| explain.push(delta));
+        explain.finish();
+        explain_shown = explain.printed_any();
+        r
+    } else {
+        api.generate_json(setting, &history, &prompt.user, &prompt.system)
+    };
 
-            crate::scy_executor::handle_plan_json(setting, &json_text)
-                .map_err(|e| format!("Execution error: {}", e))?;
+    let json_text = result.map_err(|e| ("error", format_api_error(e)))?;
+    out.llm_output(&json_text);
+    let json_text = reask_if_malformed(setting, api, &history, &prompt, json_text, out)?;
 
-            Ok(())
+    if flags.json {
+        // 출력은 원문 그대로, history 에는 파싱한 계획도
+        if let Ok(plan) = scy_executor::parse_plan(&json_text) {
+            out.plan(&plan);
+        }
+        println!("{}", json_text.trim());
+        return Ok("ok");
+    }
+    if flags.explain_only {
+        let plan = scy_executor::parse_plan(&json_text).map_err(|e| ("error", format!("Execution error: {}", e)))?;
+        out.plan(&plan);
+        // 스트리밍으로 이미 출력했으면 끝
+        if !explain_shown {
+            say!(out, "Explain: {}", plan.explain.as_deref().unwrap_or("(no explanation)"));
         }
-        Err(e) => Err(format_api_error(e)),
+        return Ok("ok");
     }
+
+    say!(out, "=== LLM JSON ===");
+    say!(out, "{}", json_text);
+
+    execute_with_repair(setting, api, &history, &prompt, json_text, flags, out)
+}
+
+/// 계획 실행. 명령이 실패하면 policy.repair_attempts 번까지 실패 내용을 LLM 에 보내
+/// 고친 계획을 받아 다시 실행 (확인 절차는 처음과 같음).
+fn execute_with_repair(
+    setting: &SconnySetting,
+    api: &ScyApi,
+    history: &[ChatMessage],
+    request_prompt: &Prompt,
+    mut json_text: String,
+    flags: RunFlags,
+    out: &mut Output,
+) -> Result<&'static str, (&'static str, String)> {
+    let max = setting.policy.repair_attempts;
+    let mut attempt = 0;
+    loop {
+        let since = out.step_count();
+        let err = match scy_executor::handle_plan_json(setting, &json_text, flags.yes, out) {
+            Ok(outcome) => return Ok(outcome.status()),
+            Err(e) => format!("Execution error: {}", e),
+        };
+        // 실행한 명령이 실패한 경우만 (Ctrl+C 는 제외)
+        let failed = match out.failed_step(since) {
+            Some(s) if !s.cancelled && attempt < max => s.clone(),
+            _ => return Err(("failed", err)),
+        };
+        out.error(&err);
+        attempt += 1;
+        out.repair(attempt, failed.index);
+        say!(out, "\n=== REPAIR (attempt {}/{}) ===", attempt, max);
+        say!(out, "Sending the error of command {} back to the model...", failed.index);
+
+        let previous = scy_executor::parse_plan(&json_text).map(|p| p.cmd).unwrap_or_default();
+        let prompt = build_repair_prompt(request_prompt, &previous, &failed);
+        if debug_enabled() {
+            say!(out, "===== REPAIR PROMPT =====\n{}\n", prompt.user);
+        }
+        json_text = api
+            .generate_json(setting, history, &prompt.user, &prompt.system)
+            .map_err(|e| ("failed", format_api_error(e)))?;
+        out.llm_output(&json_text);
+        json_text = reask_if_malformed(setting, api, history, &prompt, json_text, out).map_err(|(_, e)| ("failed", e))?;
+
+        say!(out, "=== LLM JSON ===");
+        say!(out, "{}", json_text);
+    }
+}
+
+/// 응답이 계획 스키마에 맞지 않으면 (고칠 수 있는 건 scy_schema 가 고침)
+/// 에러와 스키마를 붙여서 한 번만 다시 요청. 그래도 틀리면 실행 단계에서 에러로 보고됨.
+fn reask_if_malformed(
+    setting: &SconnySetting,
+    api: &ScyApi,
+    history: &[ChatMessage],
+    prompt: &Prompt,
+    json_text: String,
+    out: &mut Output,
+) -> Result<String, (&'static str, String)> {
+    let err = match scy_executor::parse_plan(&json_text) {
+        Ok(_) => return Ok(json_text),
+        Err(e) => e,
+    };
+    say!(out, "{}", err);
+    say!(out, "Asking the model again with the plan schema...");
+    let retry = build_reask_prompt(prompt, &json_text, &err);
+    let text = api.generate_json(setting, history, &retry.user, &retry.system).map_err(|e| ("error", format_api_error(e)))?;
+    out.llm_output(&text);
+    Ok(text)
+}
+
+fn handle_repl_command(setting: &SconnySetting, api: &ScyApi, cmd: &str) -> Result<(), String> {
+    match cmd.trim() {
+        "models" => {
+            match api.backend(setting.llm_service).and_then(|b| b.list_models()) {
+                Ok(models) => {
+                    for m in models {
+                        println!("{}", m);
+                    }
+                }
+                Err(e) => eprintln!("{}", format_api_error(e)),
+            }
+        }
+        "health" => {
+            match api.backend(setting.llm_service) {
+                Ok(b) => {
+                    let caps = b.capabilities();
+                    println!("service: {:?}", b.service());
+                    println!("model: {}", b.model());
+                    println!(
+                        "capabilities: json_mode={} streaming={} list_models={} requires_api_key={}",
+                        caps.json_mode, caps.streaming, caps.list_models, caps.requires_api_key
+                    );
+                    match b.health_check() {
+                        Ok(status) => println!("health: {}", status),
+                        Err(e) => eprintln!("{}", format_api_error(e)),
+                    }
+                }
+                Err(e) => eprintln!("{}", format_api_error(e)),
+            }
+        }
+        other => eprintln!("Unknown command: :{} (try :help)", other),
+    }
+    Ok(())
 }
 
 fn debug_enabled() -> bool {
@@ -97,20 +583,139 @@ fn debug_enabled() -> bool {
 
 fn format_api_error(e: ScyApiError) -> String {
     match e {
-        ScyApiError::MissingApiKey => {
-            [
-                "API error: Missing API key.",
-                "Hint: set OPENAI_API_KEY or SCONNY_OPENAI_API_KEY environment variable.",
-            ]
-            .join("\n")
-        }
-        ScyApiError::CommandFailed { code, stdout, stderr } => {
-            format!(
-                "API error: curl/powershell command failed (code={:?}).\n--- stdout ---\n{}\n--- stderr ---\n{}",
-                code, stdout, stderr
-            )
+        ScyApiError::MissingApiKey(hint) => {
+            format!("API error: Missing API key.\nHint: set {} environment variable.", hint)
         }
+        ScyApiError::Http { status, body } => {
+            format!("API error: HTTP {}.\n--- body ---\n{}", status, body)
+        }
+        ScyApiError::Transport { url, message } => format!("API error: request to {} failed: {}", url, message),
+        ScyApiError::UnknownService(svc) => format!("API error: No backend registered for {:?}", svc),
+        ScyApiError::Api { code, status, message } => {
+            let mut tags = Vec::new();
+            if let Some(c) = code {
+                tags.push(format!("code={}", c));
+            }
+            if !status.is_empty() {
+                tags.push(format!("status={}", status));
+            }
+            format!("API error: service returned an error ({}): {}", tags.join(", "), message)
+        }
+        ScyApiError::Blocked(msg) => format!("API error: Response blocked: {}", msg),
+        ScyApiError::Refused(msg) => format!("API error: Model refused the request: {}", msg),
+        ScyApiError::Incomplete(reason) => format!("API error: Response incomplete (reason={})", reason),
+        ScyApiError::Cancelled => "Cancelled.".to_string(),
         ScyApiError::ParseFailed(msg) => format!("API error: Parse failed: {}", msg),
         ScyApiError::Io(err) => format!("API error: IO error: {}", err),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    use scy_output::OutputFormat;
+    use scy_setting::{LlmService, ProviderSetting};
+
+    /// 요청마다 plans 를 하나씩 Ollama 응답으로 돌려주는 로컬 서버. (base url, 받은 요청 본문들)
+    fn serve_plans(plans: &[&str]) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let bodies: Vec<String> = plans
+            .iter()
+            .map(|p| serde_json::json!({ "message": { "role": "assistant", "content": p }, "done": true }).to_string())
+            .collect();
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for body in bodies {
+                let (mut conn, _) = listener.accept().unwrap();
+                let mut req = Vec::new();
+                let mut buf = [0u8; 4096];
+                let body_start = loop {
+                    let n = conn.read(&mut buf).unwrap();
+                    req.extend_from_slice(&buf[..n]);
+                    if let Some(i) = req.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break i + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&req[..body_start]).to_lowercase();
+                let len: usize = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .map_or(0, |v| v.trim().parse().unwrap());
+                while req.len() < body_start + len {
+                    let n = conn.read(&mut buf).unwrap();
+                    req.extend_from_slice(&buf[..n]);
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                conn.write_all(response.as_bytes()).unwrap();
+                requests.push(String::from_utf8_lossy(&req[body_start..]).into_owned());
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn setting(url: &str, repair_attempts: u32) -> SconnySetting {
+        let mut s = SconnySetting::new();
+        s.llm_service = LlmService::Ollama;
+        s.providers.insert(LlmService::Ollama, ProviderSetting { base_url: Some(url.to_string()), model: Some("m".to_string()), timeout_sec: Some(5) });
+        s.policy.dry_run = false;
+        s.policy.require_confirmation = false;
+        s.policy.undo = false; // 테스트에서 저널을 남기지 않게
+        s.policy.repair_attempts = repair_attempts;
+        s
+    }
+
+    fn run(setting: &SconnySetting, first_plan: &str, out: &mut Output) -> Result<&'static str, (&'static str, String)> {
+        let prompt = Prompt { system: "sys".to_string(), user: "User request:\nlist it\n".to_string() };
+        let flags = RunFlags { yes: true, ..RunFlags::default() };
+        execute_with_repair(setting, &ScyApi::new(setting), &[], &prompt, first_plan.to_string(), flags, out)
+    }
+
+    #[test]
+    fn repair_sends_the_failure_and_runs_the_fixed_plan() {
+        let (url, server) = serve_plans(&[r#"{"cmd":["echo fixed"],"risk":"low"}"#]);
+        let setting = setting(&url, 2);
+        let mut out = Output::new(OutputFormat::Json, "list it");
+        let result = run(&setting, r#"{"cmd":["echo bad-flag >&2; exit 2"],"risk":"low"}"#, &mut out);
+        assert_eq!(result, Ok("ok"));
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("Command 1 failed (exit code 2)"), "{}", requests[0]);
+        assert!(requests[0].contains("bad-flag"));
+        assert_eq!(out.steps().iter().map(|s| s.exit_code).collect::<Vec<_>>(), [Some(2), Some(0)]);
+        assert_eq!(out.llm_outputs().len(), 1);
+    }
+
+    #[test]
+    fn repair_stops_after_the_attempt_limit() {
+        let (url, server) = serve_plans(&[r#"{"cmd":["exit 4"]}"#]);
+        let setting = setting(&url, 1);
+        let mut out = Output::new(OutputFormat::Json, "list it");
+        let (status, err) = run(&setting, r#"{"cmd":["exit 3"]}"#, &mut out).unwrap_err();
+        assert_eq!(status, "failed");
+        assert!(err.contains("1 of 1 command(s) failed"), "{}", err);
+        assert_eq!(server.join().unwrap().len(), 1);
+        assert_eq!(out.steps().iter().map(|s| s.exit_code).collect::<Vec<_>>(), [Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn no_repair_when_disabled() {
+        // 요청을 받으면 안 되므로 서버 없이 닫힌 포트
+        let setting = setting("http://127.0.0.1:9", 0);
+        let mut out = Output::new(OutputFormat::Json, "list it");
+        let (status, _) = run(&setting, r#"{"cmd":["false"]}"#, &mut out).unwrap_err();
+        assert_eq!(status, "failed");
+        assert_eq!(out.step_count(), 1);
+        assert!(out.llm_outputs().is_empty());
+    }
+}
@@ -1,151 +1,221 @@
-use std::env;
-use std::fs;
-use std::io;
-use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-#[derive(Debug)]
-pub enum OllamaError {
-    Io(io::Error),
-    CommandFailed { code: Option<i32>, stdout: String, stderr: String },
-    ParseFailed(&'static str),
-}
+use serde::{Deserialize, Serialize};
 
-impl From<io::Error> for OllamaError {
-    fn from(e: io::Error) -> Self { OllamaError::Io(e) }
-}
+use crate::scy_api::ScyApiError;
+use crate::scy_backend::{BackendCapabilities, ChatRequest, LlmBackend};
+use crate::scy_http::{HttpClient, DEFAULT_TIMEOUT_SECS};
+use crate::scy_setting::{LlmService, SconnySetting};
+
+pub const DEFAULT_OLLAMA_BASE_URL: &str = "http://127.0.0.1:11434";
+pub const DEFAULT_OLLAMA_MODEL: &str = "gemma3:1b";
 
 pub struct OllamaApi {
     pub base_url: String,     // e.g. http://127.0.0.1:11434
-    pub timeout_secs: u64,    // curl --max-time
+    pub model: String,
+    http: HttpClient,
+}
+
+// -------------------- /api/chat request/response --------------------
+
+#[derive(Serialize)]
+struct ChatBody<'a> {
+    model: &'a str,
+    stream: bool,
+    format: &'static str,
+    messages: Vec<Message<'a>>,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: Option<ResponseMessage>,
+    done_reason: Option<String>,
+}
+
+/// stream=true 일 때 NDJSON 한 줄
+#[derive(Deserialize)]
+struct ChatChunk {
+    message: Option<ResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+#[derive(Deserialize)]
+struct TagList {
+    #[serde(default)]
+    models: Vec<TagEntry>,
+}
+
+#[derive(Deserialize)]
+struct TagEntry {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct VersionInfo {
+    version: String,
 }
 
 impl OllamaApi {
-    pub fn new(base_url: String, timeout_secs: u64) -> Self {
-        Self { base_url, timeout_secs }
+    pub fn new(base_url: String, model: String, timeout_secs: u64) -> Self {
+        Self { base_url, model, http: HttpClient::new(timeout_secs) }
     }
 
-    /// /api/chat 호출 (stream=false), assistant message.content만 리턴
-    pub fn chat_once(&self, model: &str, system: &str, user: &str) -> Result<String, OllamaError> {
-        let url = format!("{}/api/chat", self.base_url.trim_end_matches('/'));
-
-        let body = format!(
-            "{{\
-\"model\":\"{}\",\
-\"stream\":false,\
-\"messages\":[\
-{{\"role\":\"system\",\"content\":\"{}\"}},\
-{{\"role\":\"user\",\"content\":\"{}\"}}\
-]\
-}}",
-            json_escape(model),
-            json_escape(system),
-            json_escape(user),
-        );
-
-        let tmp_path = write_temp_json("sconny_ollama_req", &body)?;
-        let raw = call_curl_post_json(&url, &tmp_path, self.timeout_secs)?;
-
-        let content = extract_message_content(&raw).ok_or(OllamaError::ParseFailed("failed to extract message.content"))?;
-        Ok(content.trim().to_string())
+    pub fn from_setting(setting: &SconnySetting) -> Self {
+        let base = setting
+            .base_url(LlmService::Ollama)
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| DEFAULT_OLLAMA_BASE_URL.to_string());
+
+        let model = setting
+            .model_for(LlmService::Ollama)
+            .unwrap_or_else(|| DEFAULT_OLLAMA_MODEL.to_string());
+
+        let timeout_secs = setting
+            .provider(LlmService::Ollama)
+            .and_then(|p| p.timeout_sec)
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        Self::new(base, model, timeout_secs)
     }
-}
 
-fn write_temp_json(prefix: &str, content: &str) -> Result<PathBuf, OllamaError> {
-    let mut p = env::temp_dir();
-    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
-    p.push(format!("{}_{}.json", prefix, now));
-    fs::write(&p, content.as_bytes())?;
-    Ok(p)
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    fn build_body(&self, req: &ChatRequest, stream: bool) -> Result<String, ScyApiError> {
+        let mut messages = vec![Message { role: "system", content: &req.system }];
+        messages.extend(req.messages.iter().map(|m| Message { role: m.role.as_str(), content: &m.content }));
+
+        let body_req = ChatBody { model: &self.model, stream, format: "json", messages };
+        serde_json::to_string(&body_req)
+            .map_err(|e| ScyApiError::ParseFailed(format!("failed to encode request body: {}", e)))
+    }
 }
 
-fn call_curl_post_json(url: &str, body_file: &Path, timeout_secs: u64) -> Result<String, OllamaError> {
-    let out = Command::new("curl")
-        .arg("-sS")
-        .arg("--fail-with-body")
-        .arg("--max-time")
-        .arg(timeout_secs.to_string())
-        .arg(url)
-        .arg("-H")
-        .arg("Content-Type: application/json")
-        .arg("--data-binary")
-        .arg(format!("@{}", body_file.display()))
-        .output()?;
-
-    if !out.status.success() {
-        return Err(OllamaError::CommandFailed {
-            code: out.status.code(),
-            stdout: String::from_utf8_lossy(&out.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&out.stderr).to_string(),
-        });
+impl LlmBackend for OllamaApi {
+    fn service(&self) -> LlmService {
+        LlmService::Ollama
     }
 
-    Ok(String::from_utf8_lossy(&out.stdout).to_string())
-}
+    fn model(&self) -> &str {
+        &self.model
+    }
 
-/// {"message":{"role":"assistant","content":"..."}...} 에서 content만 단순 추출
-fn extract_message_content(resp_json: &str) -> Option<String> {
-    let needle = "\"message\"";
-    let pos = resp_json.find(needle)?;
-    let after = &resp_json[pos..];
-
-    let key = "\"content\":\"";
-    let cpos = after.find(key)?;
-    let mut i = cpos + key.len();
-
-    let mut out = String::new();
-    let bytes = after.as_bytes();
-    while i < bytes.len() {
-        let c = bytes[i] as char;
-        if c == '"' {
-            return Some(out);
-        }
-        if c == '\\' {
-            i += 1;
-            if i >= bytes.len() { return None; }
-            let esc = bytes[i] as char;
-            match esc {
-                '"' => out.push('"'),
-                '\\' => out.push('\\'),
-                '/' => out.push('/'),
-                'b' => out.push('\u{0008}'),
-                'f' => out.push('\u{000C}'),
-                'n' => out.push('\n'),
-                'r' => out.push('\r'),
-                't' => out.push('\t'),
-                'u' => {
-                    if i + 4 >= bytes.len() { return None; }
-                    let hex = &after[i+1..i+5];
-                    if let Ok(v) = u16::from_str_radix(hex, 16) {
-                        if let Some(ch) = char::from_u32(v as u32) {
-                            out.push(ch);
-                        }
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities { json_mode: true, streaming: true, list_models: true, requires_api_key: false }
+    }
+
+    /// /api/chat 호출 (stream=false, format=json), assistant message.content만 리턴
+    fn chat(&self, req: &ChatRequest) -> Result<String, ScyApiError> {
+        let body = self.build_body(req, false)?;
+        let raw = self.http.post_json(&self.url("/api/chat"), &[], &body).map_err(map_error_payload)?;
+
+        extract_message_content(&raw)
+    }
+
+    /// /api/chat 호출 (stream=true), NDJSON 줄마다 message.content 조각을 on_delta로 전달
+    fn chat_stream(&self, req: &ChatRequest, on_delta: &mut dyn FnMut(&str)) -> Result<String, ScyApiError> {
+        let body = self.build_body(req, true)?;
+
+        let mut assembled = String::new();
+        let mut done_reason = None;
+
+        self.http
+            .post_json_lines(&self.url("/api/chat"), &[], &body, |line| {
+                if line.trim().is_empty() {
+                    return Ok(true);
+                }
+                let chunk: ChatChunk = serde_json::from_str(line)
+                    .map_err(|e| ScyApiError::ParseFailed(format!("invalid /api/chat stream chunk: {}", e)))?;
+                if let Some(err) = chunk.error {
+                    return Err(ScyApiError::Api { code: None, status: String::new(), message: err });
+                }
+                if let Some(m) = chunk.message {
+                    if !m.content.is_empty() {
+                        on_delta(&m.content);
+                        assembled.push_str(&m.content);
                     }
-                    i += 4;
                 }
-                _ => out.push(esc),
-            }
-        } else {
-            out.push(c);
+                if chunk.done {
+                    done_reason = chunk.done_reason;
+                    return Ok(false);
+                }
+                Ok(true)
+            })
+            .map_err(map_error_payload)?;
+
+        if done_reason.as_deref() == Some("length") {
+            return Err(ScyApiError::Incomplete("length".to_string()));
         }
-        i += 1;
+        if assembled.trim().is_empty() {
+            return Err(ScyApiError::ParseFailed("stream ended with empty message.content".to_string()));
+        }
+        Ok(assembled.trim().to_string())
+    }
+
+    fn list_models(&self) -> Result<Vec<String>, ScyApiError> {
+        let raw = self.http.get(&self.url("/api/tags"), &[]).map_err(map_error_payload)?;
+        let tags: TagList = serde_json::from_str(&raw)
+            .map_err(|e| ScyApiError::ParseFailed(format!("invalid /api/tags response: {}", e)))?;
+        let mut names: Vec<String> = tags.models.into_iter().map(|m| m.name).collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn health_check(&self) -> Result<String, ScyApiError> {
+        let raw = self.http.get(&self.url("/api/version"), &[]).map_err(map_error_payload)?;
+        let v: VersionInfo = serde_json::from_str(&raw)
+            .map_err(|e| ScyApiError::ParseFailed(format!("invalid /api/version response: {}", e)))?;
+        Ok(format!("ok (ollama {})", v.version))
     }
-    None
 }
 
-fn json_escape(s: &str) -> String {
-    let mut out = String::new();
-    for ch in s.chars() {
-        match ch {
-            '"' => out.push_str("\\\""),
-            '\\' => out.push_str("\\\\"),
-            '\n' => out.push_str("\\n"),
-            '\r' => out.push_str("\\r"),
-            '\t' => out.push_str("\\t"),
-            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
-            c => out.push(c),
-        }
+/// 4xx/5xx 응답 본문이 {"error":"..."} 형식이면 ScyApiError::Api로 변환
+fn map_error_payload(e: ScyApiError) -> ScyApiError {
+    match e {
+        ScyApiError::Http { status, body } => match serde_json::from_str::<ErrorBody>(&body) {
+            Ok(b) => ScyApiError::Api { code: Some(status as i64), status: String::new(), message: b.error },
+            Err(_) => ScyApiError::Http { status, body },
+        },
+        other => other,
+    }
+}
+
+/// {"message":{"role":"assistant","content":"..."},"done":true,...} 에서 content만 추출
+fn extract_message_content(raw: &str) -> Result<String, ScyApiError> {
+    if let Ok(b) = serde_json::from_str::<ErrorBody>(raw) {
+        return Err(ScyApiError::Api { code: None, status: String::new(), message: b.error });
+    }
+
+    let resp: ChatResponse = serde_json::from_str(raw)
+        .map_err(|e| ScyApiError::ParseFailed(format!("invalid /api/chat response: {}", e)))?;
+
+    // done_reason: "stop" | "length" | "load" ...
+    if resp.done_reason.as_deref() == Some("length") {
+        return Err(ScyApiError::Incomplete("length".to_string()));
+    }
+
+    let content = resp.message.map(|m| m.content).unwrap_or_default();
+    if content.trim().is_empty() {
+        return Err(ScyApiError::ParseFailed("response has empty message.content".to_string()));
     }
-    out
+    Ok(content.trim().to_string())
 }
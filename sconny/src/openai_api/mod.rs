@@ -0,0 +1 @@
+pub mod scy_openai_api;
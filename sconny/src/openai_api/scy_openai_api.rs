@@ -0,0 +1,371 @@
+use std::env;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scy_api::ScyApiError;
+use crate::scy_backend::{BackendCapabilities, ChatRequest, LlmBackend};
+use crate::scy_http::{HttpClient, DEFAULT_TIMEOUT_SECS};
+use crate::scy_setting::{LlmService, SconnySetting};
+
+pub const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com";
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4.1";
+
+const MISSING_KEY_HINT: &str = "OPENAI_API_KEY or SCONNY_OPENAI_API_KEY";
+
+pub struct OpenAiApi {
+    pub base_url: String,     // default: https://api.openai.com
+    pub model: String,        // default: gpt-4.1
+    pub store: bool,          // store=false 권장
+    http: HttpClient,
+}
+
+// -------------------- Responses API request/response --------------------
+
+#[derive(Serialize)]
+struct ResponsesRequest<'a> {
+    model: &'a str,
+    input: Vec<InputMessage<'a>>,
+    text: TextConfig,
+    store: bool,
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct InputMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct TextConfig {
+    format: TextFormat,
+}
+
+#[derive(Serialize)]
+struct TextFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Deserialize)]
+struct ResponsesResponse {
+    status: Option<String>, // "completed" | "incomplete" | "failed" | ...
+    error: Option<ResponseError>,
+    incomplete_details: Option<IncompleteDetails>,
+    #[serde(default)]
+    output: Vec<OutputItem>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    code: Option<String>,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct IncompleteDetails {
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OutputItem {
+    Message {
+        #[serde(default)]
+        content: Vec<ContentPart>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentPart {
+    OutputText { text: String },
+    Refusal { refusal: String },
+    #[serde(other)]
+    Other,
+}
+
+/// stream=true 일 때 SSE "data:" 줄마다 오는 이벤트
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum StreamEvent {
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta { delta: String },
+    #[serde(rename = "response.completed")]
+    Completed { response: ResponsesResponse },
+    #[serde(rename = "response.incomplete")]
+    Incomplete { response: ResponsesResponse },
+    #[serde(rename = "response.failed")]
+    Failed { response: ResponsesResponse },
+    #[serde(rename = "error")]
+    Error {
+        code: Option<String>,
+        #[serde(default)]
+        message: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// 4xx/5xx 응답: {"error":{"message":"...","type":"...","code":"..."}}
+#[derive(Deserialize)]
+struct ErrorEnvelope {
+    error: ErrorBody,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    message: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    code: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+impl OpenAiApi {
+    pub fn from_setting(setting: &SconnySetting) -> Self {
+        let base_url = setting
+            .base_url(LlmService::OpenAI)
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| DEFAULT_OPENAI_BASE_URL.to_string());
+        let model = setting
+            .model_for(LlmService::OpenAI)
+            .or_else(|| env::var("SCONNY_OPENAI_MODEL").ok().filter(|s| !s.trim().is_empty()))
+            .unwrap_or_else(|| DEFAULT_OPENAI_MODEL.to_string());
+        let timeout_secs = setting
+            .provider(LlmService::OpenAI)
+            .and_then(|p| p.timeout_sec)
+            .or_else(|| env::var("SCONNY_OPENAI_TIMEOUT_SECS").ok().and_then(|s| s.parse::<u64>().ok()))
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        Self { base_url, model, store: false, http: HttpClient::new(timeout_secs) }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    fn build_body(&self, req: &ChatRequest, stream: bool) -> Result<String, ScyApiError> {
+        let system = format!(
+            "You are a helpful assistant designed to output JSON only.\n\
+             Output MUST be a single JSON object. No markdown.\n\n\
+             {}",
+            req.system
+        );
+
+        let mut input = vec![InputMessage { role: "system", content: &system }];
+        input.extend(req.messages.iter().map(|m| InputMessage { role: m.role.as_str(), content: &m.content }));
+
+        let body_req = ResponsesRequest {
+            model: &self.model,
+            input,
+            text: TextConfig { format: TextFormat { kind: "json_object" } },
+            store: self.store,
+            stream,
+        };
+        serde_json::to_string(&body_req)
+            .map_err(|e| ScyApiError::ParseFailed(format!("failed to encode request body: {}", e)))
+    }
+}
+
+impl LlmBackend for OpenAiApi {
+    fn service(&self) -> LlmService {
+        LlmService::OpenAI
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities { json_mode: true, streaming: true, list_models: true, requires_api_key: true }
+    }
+
+    fn chat(&self, req: &ChatRequest) -> Result<String, ScyApiError> {
+        let api_key = get_openai_api_key().ok_or(ScyApiError::MissingApiKey(MISSING_KEY_HINT))?;
+        let body = self.build_body(req, false)?;
+
+        let auth = format!("Bearer {}", api_key);
+        let raw = self
+            .http
+            .post_json(&self.url("/v1/responses"), &[("Authorization", &auth)], &body)
+            .map_err(map_error_payload)?;
+
+        extract_output_text(&raw)
+    }
+
+    /// stream=true, SSE 이벤트 중 output_text.delta를 on_delta로 전달.
+    /// 최종 텍스트는 response.completed 의 응답 객체 기준 (없으면 delta 누적본).
+    fn chat_stream(&self, req: &ChatRequest, on_delta: &mut dyn FnMut(&str)) -> Result<String, ScyApiError> {
+        let api_key = get_openai_api_key().ok_or(ScyApiError::MissingApiKey(MISSING_KEY_HINT))?;
+        let body = self.build_body(req, true)?;
+
+        let auth = format!("Bearer {}", api_key);
+        let mut assembled = String::new();
+        let mut finished: Option<Result<String, ScyApiError>> = None;
+
+        self.http
+            .post_json_lines(
+                &self.url("/v1/responses"),
+                &[("Authorization", &auth), ("Accept", "text/event-stream")],
+                &body,
+                |line| {
+                    let data = match line.strip_prefix("data:") {
+                        Some(d) => d.trim(),
+                        None => return Ok(true), // event:/id:/빈 줄
+                    };
+                    if data == "[DONE]" {
+                        return Ok(false);
+                    }
+                    let ev: StreamEvent = match serde_json::from_str(data) {
+                        Ok(ev) => ev,
+                        Err(_) => return Ok(true),
+                    };
+                    match ev {
+                        StreamEvent::OutputTextDelta { delta } => {
+                            on_delta(&delta);
+                            assembled.push_str(&delta);
+                        }
+                        StreamEvent::Completed { response }
+                        | StreamEvent::Incomplete { response }
+                        | StreamEvent::Failed { response } => {
+                            finished = Some(response_output_text(response));
+                            return Ok(false);
+                        }
+                        StreamEvent::Error { code, message } => {
+                            finished = Some(Err(ScyApiError::Api {
+                                code: None,
+                                status: code.unwrap_or_default(),
+                                message,
+                            }));
+                            return Ok(false);
+                        }
+                        StreamEvent::Other => {}
+                    }
+                    Ok(true)
+                },
+            )
+            .map_err(map_error_payload)?;
+
+        match finished {
+            Some(Ok(text)) => Ok(text),
+            Some(Err(ScyApiError::ParseFailed(_))) | None if !assembled.trim().is_empty() => Ok(assembled),
+            Some(Err(e)) => Err(e),
+            None => Err(ScyApiError::ParseFailed("stream ended without output_text".to_string())),
+        }
+    }
+
+    fn list_models(&self) -> Result<Vec<String>, ScyApiError> {
+        let api_key = get_openai_api_key().ok_or(ScyApiError::MissingApiKey(MISSING_KEY_HINT))?;
+        let auth = format!("Bearer {}", api_key);
+        let raw = self
+            .http
+            .get(&self.url("/v1/models"), &[("Authorization", &auth)])
+            .map_err(map_error_payload)?;
+
+        let list: ModelList = serde_json::from_str(&raw)
+            .map_err(|e| ScyApiError::ParseFailed(format!("invalid /v1/models response: {}", e)))?;
+        let mut ids: Vec<String> = list.data.into_iter().map(|m| m.id).collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn health_check(&self) -> Result<String, ScyApiError> {
+        let models = self.list_models()?;
+        Ok(format!("ok ({} models visible)", models.len()))
+    }
+}
+
+fn get_openai_api_key() -> Option<String> {
+    // 우선순위: SCONNY_OPENAI_API_KEY -> OPENAI_API_KEY
+    env::var("SCONNY_OPENAI_API_KEY")
+        .ok()
+        .or_else(|| env::var("OPENAI_API_KEY").ok())
+        .filter(|s| !s.trim().is_empty())
+}
+
+/// 4xx/5xx 응답 본문이 {"error":{...}} 형식이면 ScyApiError::Api로 변환
+fn map_error_payload(e: ScyApiError) -> ScyApiError {
+    match e {
+        ScyApiError::Http { status, body } => match serde_json::from_str::<ErrorEnvelope>(&body) {
+            Ok(env) => ScyApiError::Api {
+                code: Some(status as i64),
+                status: env
+                    .error
+                    .code
+                    .map(|c| c.as_str().map(|s| s.to_string()).unwrap_or_else(|| c.to_string()))
+                    .or(env.error.kind)
+                    .unwrap_or_default(),
+                message: env.error.message,
+            },
+            Err(_) => ScyApiError::Http { status, body },
+        },
+        other => other,
+    }
+}
+
+fn extract_output_text(raw: &str) -> Result<String, ScyApiError> {
+    let resp: ResponsesResponse = serde_json::from_str(raw)
+        .map_err(|e| ScyApiError::ParseFailed(format!("invalid /v1/responses response: {}", e)))?;
+    response_output_text(resp)
+}
+
+/// output[].content[] 중 output_text를 이어붙여 리턴.
+/// refusal / incomplete / failed 응답은 각각 별도 에러로 보고.
+fn response_output_text(resp: ResponsesResponse) -> Result<String, ScyApiError> {
+    if let Some(err) = resp.error {
+        return Err(ScyApiError::Api {
+            code: None,
+            status: err.code.unwrap_or_else(|| "failed".to_string()),
+            message: err.message,
+        });
+    }
+
+    let mut text = String::new();
+    let mut refusal = None;
+    for item in resp.output {
+        if let OutputItem::Message { content } = item {
+            for part in content {
+                match part {
+                    ContentPart::OutputText { text: t } => text.push_str(&t),
+                    ContentPart::Refusal { refusal: r } => refusal = Some(r),
+                    ContentPart::Other => {}
+                }
+            }
+        }
+    }
+
+    if let Some(r) = refusal {
+        if text.trim().is_empty() {
+            return Err(ScyApiError::Refused(r));
+        }
+    }
+
+    if resp.status.as_deref() == Some("incomplete") {
+        let reason = resp
+            .incomplete_details
+            .and_then(|d| d.reason)
+            .unwrap_or_else(|| "unknown".to_string());
+        return Err(ScyApiError::Incomplete(reason));
+    }
+
+    if text.trim().is_empty() {
+        return Err(ScyApiError::ParseFailed("response has no output_text".to_string()));
+    }
+
+    Ok(text)
+}
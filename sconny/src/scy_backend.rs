@@ -0,0 +1,104 @@
+// scy_backend.rs
+use std::collections::HashMap;
+
+use crate::gemini_api::scy_gemini_api::GeminiApi;
+use crate::ollama_api::scy_ollama_api::OllamaApi;
+use crate::openai_api::scy_openai_api::OpenAiApi;
+use crate::scy_api::ScyApiError;
+use crate::scy_setting::{LlmService, SconnySetting};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+/// 백엔드에 전달되는 대화 요청. system은 별도로, 나머지는 시간순 메시지.
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub system: String,
+    pub messages: Vec<ChatMessage>,
+}
+
+impl ChatRequest {
+    /// 앞선 대화(history, REPL 세션) 뒤에 이번 user 메시지. history 가 비면 한 턴짜리 요청
+    pub fn with_history(system: &str, history: &[ChatMessage], user: &str) -> Self {
+        let mut messages = history.to_vec();
+        messages.push(ChatMessage { role: ChatRole::User, content: user.to_string() });
+        Self { system: system.to_string(), messages }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BackendCapabilities {
+    pub json_mode: bool,        // 서버 측 JSON 출력 강제 지원
+    pub streaming: bool,
+    pub list_models: bool,
+    pub requires_api_key: bool,
+}
+
+/// LLM 제공자 공통 인터페이스.
+/// 새 제공자는 이 trait을 구현하고 `BackendRegistry::with_defaults`에 등록하면 된다.
+pub trait LlmBackend {
+    fn service(&self) -> LlmService;
+    fn model(&self) -> &str;
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// 한 번의 대화 요청 → assistant 응답 텍스트(JSON 기대)
+    fn chat(&self, req: &ChatRequest) -> Result<String, ScyApiError>;
+
+    /// 토큰 단위로 on_delta를 호출하고, 끝나면 조립된 전체 텍스트를 리턴.
+    /// 기본 구현은 chat() 결과를 한 번에 넘긴다.
+    fn chat_stream(&self, req: &ChatRequest, on_delta: &mut dyn FnMut(&str)) -> Result<String, ScyApiError> {
+        let text = self.chat(req)?;
+        on_delta(&text);
+        Ok(text)
+    }
+
+    fn list_models(&self) -> Result<Vec<String>, ScyApiError>;
+
+    /// 연결/인증 확인. 성공 시 사람이 읽을 수 있는 짧은 상태 문자열.
+    fn health_check(&self) -> Result<String, ScyApiError>;
+}
+
+pub struct BackendRegistry {
+    backends: HashMap<LlmService, Box<dyn LlmBackend>>,
+}
+
+impl BackendRegistry {
+    pub fn new() -> Self {
+        Self { backends: HashMap::new() }
+    }
+
+    /// 내장 제공자 전부 등록
+    pub fn with_defaults(setting: &SconnySetting) -> Self {
+        let mut reg = Self::new();
+        reg.register(Box::new(OpenAiApi::from_setting(setting)));
+        reg.register(Box::new(OllamaApi::from_setting(setting)));
+        reg.register(Box::new(GeminiApi::from_setting(setting)));
+        reg
+    }
+
+    pub fn register(&mut self, backend: Box<dyn LlmBackend>) {
+        self.backends.insert(backend.service(), backend);
+    }
+
+    pub fn get(&self, service: LlmService) -> Option<&dyn LlmBackend> {
+        self.backends.get(&service).map(|b| b.as_ref())
+    }
+}
@@ -1,46 +1,299 @@
 // scy_console.rs
 use std::env;
 use std::io::{self, Write};
+use std::path::PathBuf;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::scy_output::OutputFormat;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ConsoleMode {
     OneShot,
     Repl,
+    ConfigShow { origin: bool },  // sconny config [show] [--origin]
+    History { cmd: HistoryCmd },
+    Undo { id: Option<String>, list: bool },
+    Models,
+}
+
+/// sconny history <subcommand>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryCmd {
+    List { limit: Option<usize> },        // None: 전부
+    Show { id: Option<String>, json: bool }, // id 가 없으면 가장 최근
+    Search { query: String },
+    Rerun { id: Option<String> },
+}
+
+/// 한 번의 실행에만 적용되는 동작 플래그 (설정 파일에는 없음)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunFlags {
+    pub yes: bool,           // --yes: y/N 확인 자동 승인 (HIGH 위험은 여전히 YES 입력 필요)
+    pub json: bool,          // --json: 계획 JSON만 출력, 실행 안 함
+    pub explain_only: bool,  // --explain-only: 설명만 출력, 실행 안 함
+    pub output: OutputFormat, // --output json|ndjson: stdout에는 구조화된 레코드만
+}
+
+/// CLI 레이어 설정 값. flag는 `config show --origin`에 표시됨.
+#[derive(Debug, Clone)]
+pub struct CliOverride {
+    pub key: String,
+    pub value: String,
+    pub flag: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct ConsoleRequest {
     pub mode: ConsoleMode,
     pub text: String,
+    pub overrides: Vec<CliOverride>,
+    pub config_path: Option<PathBuf>,  // --config <path>
+    pub flags: RunFlags,
+}
+
+pub fn parse_console_request_from_args() -> Result<ConsoleRequest, String> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    parse_args(&args)
 }
 
-pub fn parse_console_request_from_args() -> Result<Option<ConsoleRequest>, String> {
-    let args: Vec<String> = env::args().collect();
+fn parse_args(args: &[String]) -> Result<ConsoleRequest, String> {
+    parse_args_with(args, true)
+}
+
+/// subcommands: false 면 첫 단어가 undo/config/... 여도 요청문으로 봄
+fn parse_args_with(args: &[String], subcommands: bool) -> Result<ConsoleRequest, String> {
+    let mut req = ConsoleRequest {
+        mode: ConsoleMode::OneShot,
+        text: String::new(),
+        overrides: Vec::new(),
+        config_path: None,
+        flags: RunFlags::default(),
+    };
+    let mut repl = false;
+    let mut positional: Vec<String> = Vec::new();
+    let mut subcommand: Option<(String, Vec<String>)> = None;
+    let mut after_separator = false;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        if after_separator {
+            positional.push(arg.clone());
+            continue;
+        }
+        if arg == "--" {
+            after_separator = true;
+            continue;
+        }
+        // 서브커맨드 인자 (예: config --origin)는 서브커맨드 쪽에서 처리
+        if let Some((_, sub_args)) = subcommand.as_mut() {
+            sub_args.push(arg.clone());
+            continue;
+        }
+
+        // --flag=value 와 --flag value 모두 허용
+        let (name, inline) = match arg.split_once('=') {
+            Some((n, v)) if n.starts_with("--") => (n, Some(v.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |flag: &str| -> Result<String, String> {
+            match &inline {
+                Some(v) => Ok(v.clone()),
+                None => it.next().cloned().ok_or_else(|| format!("{} requires a value", flag)),
+            }
+        };
+
+        match name {
+            "-h" | "--help" => return Err(help_text()),
+            "-i" | "--repl" => repl = true,
+            "-m" | "--model" => req.overrides.push(cli("llm.model", value(name)?, name)),
+            "-s" | "--service" => req.overrides.push(cli("llm.service", value(name)?, name)),
+            "-t" | "--timeout" => req.overrides.push(cli("policy.timeout_sec", value(name)?, name)),
+            "-n" | "--dry-run" => req.overrides.push(cli("policy.dry_run", "true".to_string(), name)),
+            "-x" | "--execute" => req.overrides.push(cli("policy.dry_run", "false".to_string(), name)),
+            // 값은 --sandbox=<mode> 로만 (다음 인자는 요청문일 수 있음)
+            "--sandbox" => req.overrides.push(cli("policy.sandbox", inline.clone().unwrap_or_else(|| "auto".to_string()), name)),
+            "--continue-on-error" => req.overrides.push(cli("policy.on_error", "continue".to_string(), name)),
+            "--stop-on-error" => req.overrides.push(cli("policy.on_error", "stop".to_string(), name)),
+            // --repair 는 2번, 횟수는 --repair=<n> 으로
+            "--repair" => req.overrides.push(cli("policy.repair_attempts", inline.clone().unwrap_or_else(|| "2".to_string()), name)),
+            "--list-cwd" => req.overrides.push(cli("env.list_cwd", "true".to_string(), name)),
+            "--no-list-cwd" => req.overrides.push(cli("env.list_cwd", "false".to_string(), name)),
+            "--preview" => req.overrides.push(cli("policy.preview", "true".to_string(), name)),
+            "-y" | "--yes" => req.flags.yes = true,
+            "--json" => req.flags.json = true,
+            "--explain-only" => req.flags.explain_only = true,
+            "-o" | "--output" => {
+                let v = value(name)?;
+                req.flags.output = OutputFormat::parse(&v)
+                    .ok_or_else(|| format!("--output: expected human, json or ndjson, got '{}'", v))?;
+            }
+            "-c" | "--config" => req.config_path = Some(PathBuf::from(value(name)?)),
+            "--set" => {
+                let kv = value(name)?;
+                let (k, v) = kv.split_once('=').ok_or_else(|| format!("--set: expected key=value, got '{}'", kv))?;
+                req.overrides.push(cli(k.trim(), v.to_string(), "--set"));
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("Unknown option: {} (use -- before a request that starts with '-')", arg));
+            }
+            // 첫 단어가 서브커맨드면 (-- 앞에 있을 때만)
+            _ if subcommands && positional.is_empty() && is_subcommand(arg) => subcommand = Some((arg.clone(), Vec::new())),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if req.flags.json && req.flags.explain_only {
+        return Err("--json and --explain-only cannot be used together".to_string());
+    }
+    if req.flags.json && req.flags.output != OutputFormat::Human {
+        return Err("--json cannot be combined with --output (use --output json)".to_string());
+    }
 
-    // args[0] = executable name
-    if args.len() <= 1 {
-        // no arguments -> REPL mode
-        return Ok(None);
+    // 서브커맨드 (-- 뒤에 온 단어는 항상 요청문)
+    if let Some((name, rest)) = subcommand {
+        if repl {
+            return Err(format!("--repl cannot be combined with '{}'", name));
+        }
+        let parsed = match name.as_str() {
+            "config" => parse_config_args(&rest).map(|m| (m, false)),
+            "history" => parse_history_args(&rest).map(|(cmd, yes)| (ConsoleMode::History { cmd }, yes)),
+            "undo" => parse_undo_args(&rest),
+            _ => match rest.first() {
+                Some(extra) => Err(format!("Unexpected argument for models: {}", extra)),
+                None => Ok((ConsoleMode::Models, false)),
+            },
+        };
+        match parsed {
+            Ok((mode, yes)) => {
+                req.mode = mode;
+                req.flags.yes |= yes;
+                return Ok(req);
+            }
+            // "sconny undo the last pull" 처럼 문법에 맞지 않는 단어가 있으면 요청문
+            Err(_) if rest.iter().any(|a| !a.starts_with('-')) => return parse_args_with(args, false),
+            Err(e) => return Err(e),
+        }
     }
 
-    // Simple flags
-    if args.len() == 2 && (args[1] == "-h" || args[1] == "--help") {
-        return Err(help_text());
+    if repl {
+        if !positional.is_empty() {
+            return Err("--repl does not take a request".to_string());
+        }
+        req.mode = ConsoleMode::Repl;
+        return Ok(req);
     }
-    if args.len() == 2 && (args[1] == "--repl" || args[1] == "-i") {
-        return Ok(None);
+
+    // 요청문이 없으면 REPL
+    if positional.is_empty() {
+        if after_separator {
+            return Err("Empty request. Try: sconny \"zip a.txt b.txt c/\"".to_string());
+        }
+        req.mode = ConsoleMode::Repl;
+        return Ok(req);
     }
 
     // One-shot: join all remaining args as the request text
-    let text = args[1..].join(" ").trim().to_string();
-    if text.is_empty() {
+    req.text = positional.join(" ").trim().to_string();
+    if req.text.is_empty() {
         return Err("Empty request. Try: sconny \"zip a.txt b.txt c/\"".to_string());
     }
+    Ok(req)
+}
 
-    Ok(Some(ConsoleRequest {
-        mode: ConsoleMode::OneShot,
-        text,
-    }))
+fn is_subcommand(word: &str) -> bool {
+    matches!(word, "config" | "history" | "models" | "undo")
+}
+
+fn cli(key: &str, value: String, flag: &str) -> CliOverride {
+    CliOverride { key: key.to_string(), value, flag: flag.to_string() }
+}
+
+fn parse_config_args(args: &[String]) -> Result<ConsoleMode, String> {
+    let mut origin = false;
+    for (i, a) in args.iter().enumerate() {
+        match a.as_str() {
+            "show" if i == 0 => {}
+            "--origin" => origin = true,
+            other => return Err(format!("Unknown config argument: {}\nUsage: sconny config show [--origin]", other)),
+        }
+    }
+    Ok(ConsoleMode::ConfigShow { origin })
+}
+
+/// (mode, --yes)
+fn parse_undo_args(args: &[String]) -> Result<(ConsoleMode, bool), String> {
+    let usage = "Usage: sconny undo [--yes] [<plan-id>] | sconny undo --list";
+    let mut id = None;
+    let mut list = false;
+    let mut yes = false;
+    for a in args {
+        match a.as_str() {
+            "--list" | "-l" | "list" => list = true,
+            "--yes" | "-y" => yes = true,
+            other if other.starts_with('-') => return Err(format!("Unknown undo argument: {}\n{}", other, usage)),
+            other if id.is_none() && is_plan_id(other) => id = Some(other.to_string()),
+            other => return Err(format!("Unexpected argument for undo: {}\n{}", other, usage)),
+        }
+    }
+    if list && id.is_some() {
+        return Err(usage.to_string());
+    }
+    Ok((ConsoleMode::Undo { id, list }, yes))
+}
+
+/// 저널 id 모양 (utc_stamp: "20261017-073007", 같은 초면 "-2" ...)
+fn is_plan_id(s: &str) -> bool {
+    s.len() >= 15 && s.starts_with(|c: char| c.is_ascii_digit()) && s.chars().all(|c| c.is_ascii_digit() || c == '-')
+}
+
+/// (subcommand, --yes)
+fn parse_history_args(args: &[String]) -> Result<(HistoryCmd, bool), String> {
+    let usage = concat!(
+        "Usage: sconny history [list] [-n <count> | --all]\n",
+        "       sconny history show [--json] [<id>]\n",
+        "       sconny history search <text>\n",
+        "       sconny [OPTIONS] history rerun [--yes] [<id>]"
+    );
+    let (sub, rest) = match args.split_first() {
+        Some((s, rest)) if !s.starts_with('-') => (s.as_str(), rest),
+        _ => ("list", args),
+    };
+    let mut yes = false;
+    let mut json = false;
+    let mut limit = Some(20);
+    let mut words: Vec<String> = Vec::new();
+    let mut it = rest.iter();
+    while let Some(a) = it.next() {
+        match a.as_str() {
+            "-y" | "--yes" if sub == "rerun" => yes = true,
+            "--json" if sub == "show" => json = true,
+            "--all" | "-a" if sub == "list" => limit = None,
+            "-n" if sub == "list" => {
+                let v = it.next().ok_or_else(|| format!("-n requires a value\n{}", usage))?;
+                limit = Some(v.parse().map_err(|_| format!("invalid count: {}\n{}", v, usage))?);
+            }
+            other if other.starts_with('-') && sub != "search" => {
+                return Err(format!("Unknown history argument: {}\n{}", other, usage))
+            }
+            other => words.push(other.to_string()),
+        }
+    }
+
+    let is_history_id = |s: &str| s == "last" || s.trim_start_matches('#').parse::<u64>().is_ok();
+    let one_id = |words: Vec<String>| -> Result<Option<String>, String> {
+        match words.len() {
+            0 => Ok(None),
+            1 if is_history_id(&words[0]) => Ok(words.into_iter().next()),
+            _ => Err(format!("Expected one history id\n{}", usage)),
+        }
+    };
+    let cmd = match sub {
+        "list" | "ls" if words.is_empty() => HistoryCmd::List { limit },
+        "show" => HistoryCmd::Show { id: one_id(words)?, json },
+        "rerun" => HistoryCmd::Rerun { id: one_id(words)? },
+        "search" if !words.is_empty() => HistoryCmd::Search { query: words.join(" ") },
+        _ => return Err(usage.to_string()),
+    };
+    Ok((cmd, yes))
 }
 
 pub fn run_repl_loop<F>(mut on_request: F) -> Result<(), String>
@@ -88,16 +341,54 @@ fn help_text() -> String {
         "Sconny - Smart Console Assistant (input capture MVP)",
         "",
         "USAGE:",
-        "  sconny \"<natural language request>\"",
-        "  sconny --repl",
-        "  sconny                (same as --repl)",
+        "  sconny [OPTIONS] [--] \"<natural language request>\"",
+        "  sconny [OPTIONS] --repl",
+        "  sconny [OPTIONS]                  (same as --repl)",
+        "  sconny config show [--origin]     show effective config (and which layer set each value)",
+        "  sconny history [list] [-n <n>]    list past requests (newest last)",
+        "  sconny history show [<id>]        request, model output, plan, confirmation and exit codes",
+        "  sconny history search <text>      find past requests by request text, command or directory",
+        "  sconny history rerun [<id>]       run a recorded plan again in the current directory",
+        "  sconny models                     list models of the configured service",
+        "  sconny undo [<plan-id>]           restore files backed up before a plan ran (default: latest)",
+        "  sconny undo --list                list undo journals",
+        "",
+        "OPTIONS:",
+        "  -s, --service <name>     openai | gemini | ollama",
+        "  -m, --model <name>       model to use",
+        "  -n, --dry-run            show the plan only",
+        "  -x, --execute            run the plan (after confirmation)",
+        "      --sandbox[=<mode>]   run in a sandbox (auto|bwrap|unshare): read-only root, no network,",
+        "                           writes go to a copy of the current directory",
+        "      --stop-on-error      skip the remaining commands after a failure (default)",
+        "      --continue-on-error  keep running the remaining commands after a failure",
+        "      --repair[=<n>]       when a command fails, send the error back to the model and",
+        "                           run its corrected plan (up to n times, default 2)",
+        "      --preview            run the plan in a sandbox first, show changed files and diffs,",
+        "                           then ask whether to apply it to the real directory",
+        "      --list-cwd           include a short listing of the current directory in the prompt",
+        "                           (names, types, sizes; .gitignore'd and sensitive names left out)",
+        "  -y, --yes                answer y/N confirmations (HIGH risk still asks for YES)",
+        "  -t, --timeout <sec>      per-command timeout",
+        "  -c, --config <path>      extra config file (applied after project config)",
+        "      --json               print the plan JSON only, do not execute",
+        "      --explain-only       print the explanation only, do not execute",
+        "  -o, --output <format>    human | json | ndjson (records on stdout, messages on stderr)",
+        "      --set <key>=<value>  override any config value, e.g. policy.dry_run=false",
+        "  -i, --repl               interactive mode",
+        "  -h, --help               show help",
+        "  --                       everything after is the request text",
         "",
         "EXAMPLES:",
         "  sconny \"지금 이 디렉토리에 있는 a.txt, b.txt, c/ 들을 압축해줘\"",
+        "  sconny --service ollama --model gemma3:1b --execute -- \"show disk usage\"",
         "  sconny --repl",
         "",
         "REPL COMMANDS:",
         "  :help   show help",
+        "  :models list models",
+        "  :health check backend",
+        "  :reset  forget the conversation so far",
         "  :q      quit",
         "",
     ]
@@ -108,8 +399,217 @@ fn repl_help_text() -> String {
     [
         "REPL commands:",
         "  :help   show this help",
+        "  :models list models of the configured service",
+        "  :health check backend connectivity",
+        "  :reset  forget the conversation history of this session",
         "  :q      quit",
         "  exit    quit",
     ]
     .join("\n")
 }
+
+/// 스트리밍 중인 LLM JSON 조각에서 "explain" 문자열 값만 골라 즉시 출력한다.
+/// 완성된 JSON은 별도로 조립되어 CommandPlan으로 파싱되므로, 여기서는 표시만 담당.
+pub struct ExplainStreamer {
+    buf: String,
+    pos: usize,           // buf에서 아직 처리하지 않은 위치
+    in_value: bool,       // "explain": "  <- 이 안쪽을 읽는 중
+    done: bool,
+    printed_any: bool,
+}
+
+impl ExplainStreamer {
+    pub fn new() -> Self {
+        Self { buf: String::new(), pos: 0, in_value: false, done: false, printed_any: false }
+    }
+
+    pub fn push(&mut self, delta: &str) {
+        let started = self.in_value;
+        let Some(out) = self.decode(delta) else { return };
+        if !started {
+            print!("Explain: ");
+        }
+        print!("{}", out);
+        if self.done {
+            println!();
+        }
+        let _ = io::stdout().flush();
+    }
+
+    /// delta 를 붙이고 explain 값 중 새로 읽을 수 있게 된 부분을 리턴 (값이 아직 시작되지 않았으면 None)
+    fn decode(&mut self, delta: &str) -> Option<String> {
+        if self.done {
+            return None;
+        }
+        self.buf.push_str(delta);
+
+        if !self.in_value {
+            self.pos = find_explain_value_start(&self.buf)?;
+            self.in_value = true;
+            self.printed_any = true;
+        }
+
+        let mut out = String::new();
+        let rest = &self.buf[self.pos..];
+        let bytes = rest.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'"' => {
+                    self.done = true;
+                    i += 1;
+                    break;
+                }
+                b'\\' => {
+                    // 이스케이프가 청크 경계에서 잘렸으면 다음 delta를 기다림
+                    let esc = match bytes.get(i + 1) {
+                        Some(e) => *e,
+                        None => break,
+                    };
+                    if esc == b'u' {
+                        let hi = match hex4(rest, i + 2) {
+                            Some(v) => v,
+                            None => break,
+                        };
+                        if (0xD800..0xDC00).contains(&hi) {
+                            // surrogate pair: \uD83D\uDE00
+                            if bytes.len() < i + 8 {
+                                break;
+                            }
+                            let lo = if &bytes[i + 6..i + 8] == b"\\u" {
+                                match hex4(rest, i + 8) {
+                                    None if bytes.len() < i + 12 => break,
+                                    lo => lo,
+                                }
+                            } else {
+                                None
+                            };
+                            match lo {
+                                Some(lo) if (0xDC00..0xE000).contains(&lo) => {
+                                    let cp = 0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00);
+                                    out.push(char::from_u32(cp).unwrap_or(char::REPLACEMENT_CHARACTER));
+                                    i += 12;
+                                }
+                                // 짝이 없는 high surrogate: U+FFFD 를 내고 뒤 이스케이프는 따로 처리
+                                _ => {
+                                    out.push(char::REPLACEMENT_CHARACTER);
+                                    i += 6;
+                                }
+                            }
+                        } else {
+                            out.push(char::from_u32(hi).unwrap_or(char::REPLACEMENT_CHARACTER));
+                            i += 6;
+                        }
+                        continue;
+                    }
+                    // 잘못된 이스케이프(\é 등)는 문자 하나를 통째로
+                    let ch = rest[i + 1..].chars().next().unwrap_or('?');
+                    match ch {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' | 'b' | 'f' => {}
+                        other => out.push(other),
+                    }
+                    i += 1 + ch.len_utf8();
+                }
+                _ => {
+                    let ch = rest[i..].chars().next().unwrap_or('?');
+                    out.push(ch);
+                    i += ch.len_utf8();
+                }
+            }
+        }
+        self.pos += i;
+        Some(out)
+    }
+
+    /// explain 값을 이미 화면에 출력했는지
+    pub fn printed_any(&self) -> bool {
+        self.printed_any
+    }
+
+    /// 스트림이 끝났는데 explain 문자열이 닫히지 않았으면 줄바꿈만 정리
+    pub fn finish(&mut self) {
+        if self.printed_any && !self.done {
+            println!();
+        }
+        self.done = true;
+    }
+}
+
+fn find_explain_value_start(buf: &str) -> Option<usize> {
+    let key = "\"explain\"";
+    let kpos = buf.find(key)?;
+    let after = &buf[kpos + key.len()..];
+    let trimmed = after.trim_start();
+    let colon_rest = trimmed.strip_prefix(':')?.trim_start();
+    colon_rest.strip_prefix('"')?;
+    Some(buf.len() - colon_rest.len() + 1)
+}
+
+fn hex4(s: &str, at: usize) -> Option<u32> {
+    s.get(at..at + 4).and_then(|h| u32::from_str_radix(h, 16).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 조각들을 차례로 넣고 나온 explain 텍스트
+    fn stream(chunks: &[&str]) -> String {
+        let mut s = ExplainStreamer::new();
+        chunks.iter().filter_map(|c| s.decode(c)).collect()
+    }
+
+    fn parse(args: &[&str]) -> Result<ConsoleRequest, String> {
+        parse_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn subcommand_words_fall_back_to_request() {
+        let r = parse(&["undo", "the", "last", "pull", "-x"]).unwrap();
+        assert!(matches!(r.mode, ConsoleMode::OneShot));
+        assert_eq!(r.text, "undo the last pull");
+        assert_eq!(r.overrides[0].value, "false");
+
+        assert_eq!(parse(&["history", "of", "this", "branch"]).unwrap().text, "history of this branch");
+        assert_eq!(parse(&["models", "in", "ollama"]).unwrap().text, "models in ollama");
+        assert_eq!(parse(&["config", "git", "user.name"]).unwrap().text, "config git user.name");
+    }
+
+    #[test]
+    fn subcommands_matching_grammar() {
+        let r = parse(&["undo", "20261017-073007", "--yes"]).unwrap();
+        assert!(matches!(r.mode, ConsoleMode::Undo { id: Some(ref id), list: false } if id == "20261017-073007"));
+        assert!(r.flags.yes);
+        assert!(matches!(parse(&["undo", "list"]).unwrap().mode, ConsoleMode::Undo { id: None, list: true }));
+        assert!(matches!(parse(&["history", "show", "#12"]).unwrap().mode, ConsoleMode::History { .. }));
+        assert!(matches!(parse(&["history", "search", "tar", "backup"]).unwrap().mode, ConsoleMode::History { .. }));
+        assert!(matches!(parse(&["config", "show", "--origin"]).unwrap().mode, ConsoleMode::ConfigShow { origin: true }));
+        // 옵션만 틀리면 요청문이 아니라 에러
+        assert!(parse(&["undo", "--bogus"]).is_err());
+    }
+
+    #[test]
+    fn explain_across_chunks() {
+        assert_eq!(stream(&[r#"{"cmd":["ls"],"expl"#, r#"ain": "lists "#, r#"files\nhere", "risk":"low"}"#]), "lists files\nhere");
+    }
+
+    #[test]
+    fn invalid_escape_before_non_ascii() {
+        assert_eq!(stream(&[r#"{"explain": "\é and \한글""#, "}"]), "é and 한글");
+    }
+
+    #[test]
+    fn escape_split_at_chunk_boundary() {
+        assert_eq!(stream(&[r#"{"explain": "a\"#, r#"u00e9 \"#, r#"uD83D"#, r#"\uDE00!"}"#]), "a\u{e9} \u{1F600}!");
+    }
+
+    #[test]
+    fn unpaired_surrogates_become_replacement_chars() {
+        assert_eq!(stream(&[r#"{"explain": "smile \uD83D\u0041 done"}"#]), "smile \u{FFFD}A done");
+        assert_eq!(stream(&[r#"{"explain": "x\uD83D😀"}"#]), "x\u{FFFD}\u{1F600}");
+        assert_eq!(stream(&[r#"{"explain": "\uD83Dz"}"#]), "\u{FFFD}z");
+        assert_eq!(stream(&[r#"{"explain": "\uDE00"}"#]), "\u{FFFD}");
+    }
+}
@@ -1,10 +1,18 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
-use std::process::Command;
+use std::time::Duration;
 
-use crate::scy_setting::SconnySetting;
+use crate::scy_analyzer::{self, Finding, Risk};
+use crate::scy_journal::{BackupLimits, Journal};
+use crate::scy_output::{say, Output, StepRecord, SummaryRow};
+use crate::scy_policy;
+use crate::scy_preview;
+use crate::scy_process::{self, Ended, Stream};
+use crate::scy_sandbox::{Sandbox, SandboxMode};
+use crate::scy_schema;
+use crate::scy_setting::{ConfigOrigin, OnError, SconnySetting};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CommandPlan {
     pub cmd: Vec<String>,
     pub explain: Option<String>,
@@ -12,129 +20,513 @@ pub struct CommandPlan {
     pub risk: Option<String>, // "low" | "medium" | "high"
     pub assumptions: Option<Vec<String>>,
     pub notes: Option<Vec<String>>,
+    // step별 타임아웃(초), cmd 와 같은 순서. null 이면 policy.timeout_sec
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_sec: Option<Vec<Option<u64>>>,
+    // 명령이 실패하면 실행할 정리/복구 명령, cmd 와 같은 순서. null 이면 없음
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<Vec<Option<String>>>,
+    // "stop" | "continue". policy.on_error 를 설정하지 않았을 때만 사용
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_error: Option<String>,
+    // 스키마에 맞추려고 고친 것 (scy_schema::conform)
+    #[serde(skip)]
+    pub fixes: Vec<String>,
 }
 
-pub fn handle_plan_json(setting: &SconnySetting, json_text: &str) -> Result<(), String> {
+impl CommandPlan {
+    /// index(0부터)번 명령의 on_failure 핸들러
+    pub fn handler(&self, index: usize) -> Option<&str> {
+        self.on_failure.as_ref()?.get(index)?.as_deref().map(str::trim).filter(|h| !h.is_empty())
+    }
+
+    fn handlers(&self) -> impl Iterator<Item = (usize, &str)> {
+        (0..self.cmd.len()).filter_map(|i| self.handler(i).map(|h| (i, h)))
+    }
+}
+
+pub fn parse_plan(json_text: &str) -> Result<CommandPlan, String> {
     let normalized = normalize_llm_json(json_text)?;
-    let plan: CommandPlan = serde_json::from_str(&normalized)
-        .map_err(|e| format!("Failed to parse LLM JSON: {}", e))?;
+    let value: serde_json::Value =
+        serde_json::from_str(&normalized).map_err(|e| format!("Failed to parse LLM JSON: {}", e))?;
+    let (value, fixes) = scy_schema::conform(value)
+        .map_err(|errors| format!("LLM JSON does not match the plan schema: {}", errors.join("; ")))?;
+    let mut plan: CommandPlan =
+        serde_json::from_value(value).map_err(|e| format!("Failed to parse LLM JSON: {}", e))?;
+    plan.fixes = fixes;
+    Ok(plan)
+}
 
+/// handle_plan_json 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecOutcome {
+    DryRun,
+    Declined,   // 확인 단계에서 거절
+    Blocked,    // allow/deny 정책에 걸림
+    Sandboxed,  // 샌드박스에서만 실행 (실제 디렉토리는 그대로)
+    Completed,
+}
 
-    print_plan(&plan);
+impl ExecOutcome {
+    pub fn status(&self) -> &'static str {
+        match self {
+            ExecOutcome::DryRun => "dry_run",
+            ExecOutcome::Declined => "declined",
+            ExecOutcome::Blocked => "blocked",
+            ExecOutcome::Sandboxed => "sandboxed",
+            ExecOutcome::Completed => "ok",
+        }
+    }
+}
+
+/// assume_yes: --yes (y/N 확인 자동 승인)
+pub fn handle_plan_json(setting: &SconnySetting, json_text: &str, assume_yes: bool, out: &mut Output) -> Result<ExecOutcome, String> {
+    let mut plan = parse_plan(json_text)?;
+
+    // 로컬 분석 결과가 모델의 risk보다 높으면 올림 (on_failure 핸들러 포함)
+    let mut findings = scy_analyzer::analyze_plan(&plan.cmd);
+    for (i, h) in plan.handlers() {
+        for mut f in scy_analyzer::analyze_plan(&[h.to_string()]) {
+            f.step = i + 1;
+            f.message = format!("{} (on_failure)", f.message);
+            findings.push(f);
+        }
+    }
+    let model_risk = plan.risk.as_deref().and_then(Risk::parse);
+    let local_risk = scy_analyzer::max_risk(&findings);
+    let escalated = local_risk.is_some_and(|r| model_risk.is_none_or(|m| r > m));
+    if let Some(r) = local_risk.filter(|_| escalated) {
+        plan.risk = Some(r.as_str().to_string());
+    }
+
+    out.plan(&plan);
+    out.analysis(&findings);
+    print_plan(&plan, out);
+    print_findings(&findings, model_risk, escalated, out);
+
+    // allow/deny 규칙은 dry-run이어도 알려줌
+    let handler_check = plan.handlers().try_for_each(|(i, h)| {
+        scy_policy::check_plan(&setting.policy.rules, &[h.to_string()]).map_err(|mut v| {
+            v.step = i + 1;
+            v.command = format!("on_failure: {}", v.command);
+            v
+        })
+    });
+    if let Err(v) = scy_policy::check_plan(&setting.policy.rules, &plan.cmd).and(handler_check) {
+        out.error(&format!("Blocked by policy: {}", v));
+        return Ok(ExecOutcome::Blocked);
+    }
 
     if plan.cmd.is_empty() {
         return Err("LLM returned empty cmd list. Aborting.".to_string());
     }
 
+    // confirmation 정책
+    // 분석기가 HIGH로 판단하면 설정과 무관하게 확인
+    let must_confirm = setting.policy.require_confirmation
+        || plan.needs_confirmation.unwrap_or(false)
+        || local_risk == Some(Risk::High);
+    let risk = plan.risk.as_deref().unwrap_or("low");
+
+    if setting.policy.preview {
+        return run_preview(setting, &plan, risk, assume_yes, out);
+    }
+
+    // 샌드박스는 실제 디렉토리를 건드리지 않으므로 dry_run이어도 실행
+    let sandboxed = setting.policy.sandbox != SandboxMode::Off;
+
     // dry_run이면 절대 실행 안 함
+    if setting.policy.dry_run && !sandboxed {
+        say!(out, "\n[dry_run=true] Not executing commands.");
+        return Ok(ExecOutcome::DryRun);
+    }
+
+    if !must_confirm {
+        out.confirmation("not_required");
+    }
+    if must_confirm && !ask_confirmation("Execute these commands?", risk, assume_yes, out)? {
+        say!(out, "Cancelled.");
+        return Ok(ExecOutcome::Declined);
+    }
+
+    let sandbox = if sandboxed {
+        let sb = Sandbox::prepare(setting.policy.sandbox, setting.policy.sandbox_max_mb * 1024 * 1024)
+            .map_err(|e| format!("Sandbox error: {}", e))?;
+        say!(out, "\n[sandbox: {}] read-only root, no network, changes go to a copy of the working directory.", sb.backend_name());
+        Some(sb)
+    } else {
+        None
+    };
+
+    let result = match &sandbox {
+        Some(sb) => run_steps(setting, &plan, Some(sb), None, out),
+        None => run_for_real(setting, &plan, out),
+    };
+
+    if let Some(sb) = &sandbox {
+        print_sandbox_changes(sb, out);
+    }
+    result?;
+
+    Ok(if sandboxed { ExecOutcome::Sandboxed } else { ExecOutcome::Completed })
+}
+
+/// 복사본에서 먼저 실행해 변경을 보여주고, 실제 디렉토리에 적용할지는 항상 물어봄.
+/// HIGH 면 복사본에서 실행하기 전에도 확인 (샌드박스에서도 네트워크 외의 부작용은 막지 못할 수 있음)
+fn run_preview(setting: &SconnySetting, plan: &CommandPlan, risk: &str, assume_yes: bool, out: &mut Output) -> Result<ExecOutcome, String> {
+    if Risk::parse(risk) == Some(Risk::High) && !ask_confirmation("Run this plan in the preview sandbox?", risk, assume_yes, out)? {
+        say!(out, "Cancelled.");
+        return Ok(ExecOutcome::Declined);
+    }
+
+    // 미리보기는 항상 샌드박스 안에서 (policy.sandbox 가 off 면 auto)
+    let mode = match setting.policy.sandbox {
+        SandboxMode::Off => SandboxMode::Auto,
+        m => m,
+    };
+    let sb = Sandbox::prepare(mode, setting.policy.sandbox_max_mb * 1024 * 1024)
+        .map_err(|e| format!("Preview needs a sandbox: {}", e))?;
+    say!(out, "\n[preview: {}] running the plan against a copy of the working directory...", sb.backend_name());
+
+    let result = run_steps(setting, plan, Some(&sb), None, out);
+    let changes = scy_preview::describe(&sb)?;
+    drop(sb);
+    out.changes(&changes);
+    scy_preview::print_changes(&changes, out);
+    result.map_err(|e| format!("{}\nPreview failed; nothing was applied to the real directory.", e))?;
+
     if setting.policy.dry_run {
-        println!("\n[dry_run=true] Not executing commands.");
-        return Ok(());
+        say!(out, "\n[dry_run=true] Not applying to the real directory.");
+        return Ok(ExecOutcome::DryRun);
+    }
+    if !ask_confirmation("Apply this plan to the real directory?", risk, assume_yes, out)? {
+        say!(out, "Cancelled.");
+        return Ok(ExecOutcome::Declined);
     }
 
-    // confirmation 정책
-    let must_confirm = setting.policy.require_confirmation
-        || plan.needs_confirmation.unwrap_or(false);
+    run_for_real(setting, plan, out)?;
+    Ok(ExecOutcome::Completed)
+}
 
-    if must_confirm {
-        if !ask_confirmation(plan.risk.as_deref().unwrap_or("low"))? {
-            println!("Cancelled.");
-            return Ok(());
+/// 실제 디렉토리에서 실행. policy.undo 면 step마다 건드릴 경로를 먼저 백업.
+fn run_for_real(setting: &SconnySetting, plan: &CommandPlan, out: &mut Output) -> Result<(), String> {
+    if !setting.policy.undo {
+        return run_steps(setting, plan, None, None, out);
+    }
+    let limits = BackupLimits {
+        max_total_bytes: setting.policy.backup_max_mb * 1024 * 1024,
+        max_file_bytes: setting.policy.backup_max_file_mb * 1024 * 1024,
+    };
+    let mut journal = match Journal::begin(out.request(), &plan.cmd, limits) {
+        Ok(j) => j,
+        Err(e) => {
+            say!(out, "\n[undo] journal disabled for this plan: {}", e);
+            return run_steps(setting, plan, None, None, out);
         }
+    };
+
+    let result = run_steps(setting, plan, None, Some(&mut journal), out);
+    let _ = journal.save();
+    out.journal(&journal.id);
+    if journal.entries.is_empty() {
+        say!(out, "\n[undo] plan {}: no file changes could be predicted; nothing to undo.", journal.id);
+        return result;
     }
+    let skipped = journal.skipped();
+    say!(
+        out,
+        "\n[undo] plan {}: {} path(s) recorded{}. Revert with: sconny undo {}",
+        journal.id,
+        journal.backed_up(),
+        if skipped > 0 { format!(", {} not backed up", skipped) } else { String::new() },
+        journal.id
+    );
+    result
+}
+
+/// 명령을 순서대로 실행. 실패하면 on_failure 핸들러를 실행하고 on_error 에 따라 멈추거나 계속.
+/// 끝나면 요약 표를 보여주고, 하나라도 실패했으면 Err.
+fn run_steps(
+    setting: &SconnySetting,
+    plan: &CommandPlan,
+    sandbox: Option<&Sandbox>,
+    mut journal: Option<&mut Journal>,
+    out: &mut Output,
+) -> Result<(), String> {
+    // 사용자가 정하지 않았으면 계획의 on_error
+    let on_error = match (setting.origin_of("policy.on_error"), plan.on_error.as_deref().and_then(OnError::parse)) {
+        (ConfigOrigin::Default, Some(v)) => v,
+        _ => setting.policy.on_error,
+    };
+
+    let mut rows: Vec<SummaryRow> = Vec::new();
+    let mut failed: Vec<usize> = Vec::new();
+    let mut stop = false;
 
-    // Linux 기준: sh -lc 로 실행
-    // timeout은 coreutils 'timeout'이 있으면 적용
     for (i, c) in plan.cmd.iter().enumerate() {
-        println!("\n--- Running ({}/{}) ---\n{}", i + 1, plan.cmd.len(), c);
-        run_shell_command_with_timeout(c, setting.policy.timeout_sec)?;
+        if stop {
+            rows.push(SummaryRow { index: i + 1, cmd: c.clone(), status: "skipped", exit_code: None, duration_ms: None, on_failure: None });
+            continue;
+        }
+        say!(out, "\n--- Running ({}/{}) ---\n{}", i + 1, plan.cmd.len(), c);
+        let (status, mut row) = run_one(setting, plan, i, c, false, sandbox, journal.as_deref_mut(), out);
+        if status == "ok" {
+            rows.push(row);
+            continue;
+        }
+
+        failed.push(i + 1);
+        // Ctrl+C 는 핸들러도 실행하지 않고 바로 멈춤
+        if status == "cancelled" {
+            stop = true;
+        } else {
+            if let Some(h) = plan.handler(i) {
+                say!(out, "\n--- on_failure ({}) ---\n{}", i + 1, h);
+                let (hs, _) = run_one(setting, plan, i, h, true, sandbox, journal.as_deref_mut(), out);
+                row.on_failure = Some(hs);
+                stop = hs == "cancelled";
+            }
+            stop |= on_error == OnError::Stop;
+        }
+        rows.push(row);
+    }
+
+    if plan.cmd.len() > 1 || !failed.is_empty() {
+        print_summary(&rows, out);
+    }
+    out.summary(&rows);
+
+    if failed.is_empty() {
+        return Ok(());
+    }
+    let skipped = rows.iter().filter(|r| r.status == "skipped").count();
+    let list = failed.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
+    let mut msg = format!("{} of {} command(s) failed (step {})", failed.len(), plan.cmd.len(), list);
+    if skipped > 0 {
+        msg.push_str(&format!(", {} skipped", skipped));
+    }
+    let ran_ok = rows.iter().any(|r| r.status == "ok");
+    if ran_ok && sandbox.is_none() {
+        msg.push_str("; the plan was partially applied");
     }
+    Err(format!("{}.", msg))
+}
+
+/// 명령(또는 on_failure 핸들러) 하나 실행. (status, 요약 줄)
+#[allow(clippy::too_many_arguments)]
+fn run_one(
+    setting: &SconnySetting,
+    plan: &CommandPlan,
+    i: usize,
+    cmd: &str,
+    handler: bool,
+    sandbox: Option<&Sandbox>,
+    journal: Option<&mut Journal>,
+    out: &mut Output,
+) -> (&'static str, SummaryRow) {
+    let mut row = SummaryRow { index: i + 1, cmd: cmd.to_string(), status: "error", exit_code: None, duration_ms: None, on_failure: None };
+    let what = if handler { format!("on_failure of command {}", i + 1) } else { format!("Command {}", i + 1) };
 
-    Ok(())
+    if let Some(j) = journal {
+        for w in j.before_step(i + 1, cmd) {
+            say!(out, "[undo] not backed up: {}", w);
+        }
+    }
+    let timeout_sec = step_timeout(setting, plan, i);
+    let mut step = match run_shell_command_with_timeout(i + 1, cmd, timeout_sec, setting, sandbox, out) {
+        Ok(s) => s,
+        Err(e) => {
+            out.error(&format!("{} could not be started: {}", what, e));
+            return ("error", row);
+        }
+    };
+    step.sandboxed = sandbox.is_some();
+    step.on_failure = handler;
+
+    let status = if step.cancelled {
+        "cancelled"
+    } else if step.timed_out {
+        "timed_out"
+    } else if step.exit_code == Some(0) {
+        "ok"
+    } else {
+        "failed"
+    };
+    row.status = status;
+    row.exit_code = step.exit_code;
+    row.duration_ms = Some(step.duration_ms);
+
+    // 출력은 이미 흘려보냈으므로 에러에는 stderr 마지막 부분만
+    let mut msg = match status {
+        "cancelled" => format!("{} cancelled (Ctrl+C); remaining commands were not run.", what),
+        "timed_out" => format!("{} timed out after {}s.", what, timeout_sec),
+        _ => format!("{} failed (code={:?}).", what, step.exit_code),
+    };
+    if status == "failed" && !out.is_machine() && !step.stderr.is_empty() {
+        msg.push_str(&format!("\n--- stderr (last lines) ---\n{}", step.stderr.trim_end()));
+    }
+    out.step(step);
+    if status != "ok" {
+        out.error(&msg);
+    }
+    (status, row)
 }
 
-fn print_plan(plan: &CommandPlan) {
-    println!("\n=== PLAN ===");
+fn print_summary(rows: &[SummaryRow], out: &Output) {
+    let dur = |ms: Option<u128>| ms.map(|m| format!("{:.2}s", m as f64 / 1000.0)).unwrap_or_else(|| "-".to_string());
+    let code = |c: Option<i32>| c.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string());
+
+    say!(out, "\n=== SUMMARY ===");
+    say!(out, "  {:>2}  {:<10} {:>4}  {:>8}  command", "#", "status", "exit", "time");
+    for r in rows {
+        let mut cmd = r.cmd.replace('\n', " ");
+        if cmd.chars().count() > 60 {
+            cmd = cmd.chars().take(57).collect::<String>() + "...";
+        }
+        say!(out, "  {:>2}  {:<10} {:>4}  {:>8}  {}", r.index, r.status, code(r.exit_code), dur(r.duration_ms), cmd);
+        if let Some(h) = r.on_failure {
+            say!(out, "      on_failure: {}", h);
+        }
+    }
+}
+
+fn print_sandbox_changes(sb: &Sandbox, out: &Output) {
+    match sb.changes() {
+        Ok(changes) if changes.is_empty() => say!(out, "\n[sandbox] No filesystem changes in the working directory."),
+        Ok(changes) => {
+            say!(out, "\n[sandbox] Changes (NOT applied to the real directory):");
+            for c in changes {
+                say!(out, "  {}", c);
+            }
+        }
+        Err(e) => say!(out, "\n[sandbox] Could not compare working directory: {}", e),
+    }
+}
+
+fn print_plan(plan: &CommandPlan, out: &Output) {
+    say!(out, "\n=== PLAN ===");
     if let Some(explain) = &plan.explain {
-        println!("Explain: {}", explain);
+        say!(out, "Explain: {}", explain);
     }
-    println!("Risk: {}", plan.risk.as_deref().unwrap_or("unknown"));
-    println!("Needs confirmation (plan): {}", plan.needs_confirmation.unwrap_or(false));
-    println!("\nCommands:");
+    say!(out, "Risk: {}", plan.risk.as_deref().unwrap_or("unknown"));
+    say!(out, "Needs confirmation (plan): {}", plan.needs_confirmation.unwrap_or(false));
+    say!(out, "\nCommands:");
     for (i, c) in plan.cmd.iter().enumerate() {
-        println!("  {}. {}", i + 1, c);
+        say!(out, "  {}. {}", i + 1, c);
+        if let Some(h) = plan.handler(i) {
+            say!(out, "     on failure: {}", h);
+        }
+    }
+    if let Some(e) = &plan.on_error {
+        say!(out, "On error (plan): {}", e);
+    }
+    if !plan.fixes.is_empty() {
+        say!(out, "\nFixed in LLM output:");
+        for x in &plan.fixes { say!(out, "  - {}", x); }
     }
     if let Some(a) = &plan.assumptions {
         if !a.is_empty() {
-            println!("\nAssumptions:");
-            for x in a { println!("  - {}", x); }
+            say!(out, "\nAssumptions:");
+            for x in a { say!(out, "  - {}", x); }
         }
     }
     if let Some(n) = &plan.notes {
         if !n.is_empty() {
-            println!("\nNotes:");
-            for x in n { println!("  - {}", x); }
+            say!(out, "\nNotes:");
+            for x in n { say!(out, "  - {}", x); }
         }
     }
 }
 
-fn ask_confirmation(risk: &str) -> Result<bool, String> {
-    let risk_lc = risk.trim().to_lowercase();
-    if risk_lc == "high" {
-        print!("\nRisk is HIGH. Type YES to execute: ");
-        io::stdout().flush().map_err(|e| e.to_string())?;
+fn print_findings(findings: &[Finding], model_risk: Option<Risk>, escalated: bool, out: &Output) {
+    if findings.is_empty() {
+        return;
+    }
+    say!(out, "\nSafety check:");
+    for f in findings {
+        say!(out, "  [{}] step {}: {} ({})", f.risk, f.step, f.message, f.rule);
+    }
+    if escalated {
+        let from = model_risk.map(|r| r.as_str()).unwrap_or("unknown");
+        let to = scy_analyzer::max_risk(findings).map(|r| r.as_str()).unwrap_or("unknown");
+        say!(out, "  Risk raised from {} to {} by local analysis.", from, to);
+    }
+}
+
+/// 프롬프트는 사람용 텍스트와 같은 곳(Human: stdout, 그 외: stderr)에 출력
+fn prompt(out: &Output, text: &str) -> Result<(), String> {
+    if out.is_machine() {
+        eprint!("{}", text);
+        io::stderr().flush().map_err(|e| e.to_string())
+    } else {
+        print!("{}", text);
+        io::stdout().flush().map_err(|e| e.to_string())
+    }
+}
+
+/// question: y/N 로 물을 질문 (예: "Execute these commands?")
+/// 결과는 out.confirmation 에도 기록 (history)
+fn ask_confirmation(question: &str, risk: &str, assume_yes: bool, out: &mut Output) -> Result<bool, String> {
+    let ok = if Risk::parse(risk) == Some(Risk::High) {
+        // --yes 여도 HIGH는 직접 입력받음
+        prompt(out, &format!("\n{}\nRisk is HIGH. Type YES to execute: ", question))?;
         let mut s = String::new();
         io::stdin().read_line(&mut s).map_err(|e| e.to_string())?;
-        return Ok(s.trim() == "YES");
-    }
-
-    print!("\nExecute these commands? [y/N]: ");
-    io::stdout().flush().map_err(|e| e.to_string())?;
-    let mut s = String::new();
-    io::stdin().read_line(&mut s).map_err(|e| e.to_string())?;
-    let v = s.trim().to_lowercase();
-    Ok(v == "y" || v == "yes")
-}
-
-fn run_shell_command_with_timeout(cmd: &str, timeout_sec: u64) -> Result<(), String> {
-    // timeout 커맨드가 있으면: timeout 15s sh -lc "<cmd>"
-    // 없으면: sh -lc "<cmd>"
-    let use_timeout = Command::new("sh")
-        .arg("-lc")
-        .arg("command -v timeout >/dev/null 2>&1")
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false);
-
-    let mut c = if use_timeout {
-        let t = format!("{}s", timeout_sec);
-        let mut x = Command::new("timeout");
-        x.arg(t).arg("sh").arg("-lc").arg(cmd);
-        x
+        s.trim() == "YES"
+    } else if assume_yes {
+        say!(out, "\n{} [y/N]: y (--yes)", question);
+        out.confirmation("auto_yes");
+        return Ok(true);
     } else {
-        let mut x = Command::new("sh");
-        x.arg("-lc").arg(cmd);
-        x
+        prompt(out, &format!("\n{} [y/N]: ", question))?;
+        let mut s = String::new();
+        io::stdin().read_line(&mut s).map_err(|e| e.to_string())?;
+        let v = s.trim().to_lowercase();
+        v == "y" || v == "yes"
     };
+    out.confirmation(if ok { "yes" } else { "no" });
+    Ok(ok)
+}
 
-    let out = c.output().map_err(|e| e.to_string())?;
-
-    if !out.status.success() {
-        return Err(format!(
-            "Command failed (code={:?}).\n--- stdout ---\n{}\n--- stderr ---\n{}",
-            out.status.code(),
-            String::from_utf8_lossy(&out.stdout),
-            String::from_utf8_lossy(&out.stderr),
-        ));
+/// 출력은 줄 단위로 바로 보여주고 (Human: 그대로, ndjson: output 레코드, json: stderr),
+/// StepRecord 에는 마지막 policy.tail_lines 줄만 남김
+fn run_shell_command_with_timeout(
+    index: usize,
+    cmd: &str,
+    timeout_sec: u64,
+    setting: &SconnySetting,
+    sandbox: Option<&Sandbox>,
+    out: &Output,
+) -> Result<StepRecord, String> {
+    // sh -lc "<cmd>", 샌드박스면 앞에 bwrap/unshare 인자가 붙음
+    // 타임아웃은 프로세스 그룹 단위로 직접 처리 (0 이면 제한 없음)
+    let mut argv = vec!["sh".to_string(), "-lc".to_string(), cmd.to_string()];
+    if let Some(sb) = sandbox {
+        argv = sb.wrap(&argv);
     }
+    let timeout = (timeout_sec > 0).then(|| Duration::from_secs(timeout_sec));
 
-    if !out.stdout.is_empty() {
-        print!("{}", String::from_utf8_lossy(&out.stdout));
-    }
-    if !out.stderr.is_empty() {
-        eprint!("{}", String::from_utf8_lossy(&out.stderr));
+    let prefix = if setting.policy.step_prefix { format!("[{}] ", index) } else { String::new() };
+    let mut on_line = |stream: Stream, line: &str| out.output_line(index, stream, &prefix, line);
+    let done = scy_process::run_streaming(&argv, timeout, setting.policy.tail_lines, &mut on_line).map_err(|e| e.to_string())?;
+
+    let mut step = StepRecord::new(index, cmd, done.exit_code, done.stdout.text(), done.stderr.text(), done.duration);
+    step.truncated = done.stdout.dropped > 0 || done.stderr.dropped > 0;
+    step.timed_out = done.ended == Ended::TimedOut;
+    step.cancelled = done.ended == Ended::Cancelled;
+    Ok(step)
+}
+
+/// 계획에 step별 timeout_sec 이 있으면 그 값 (policy.max_timeout_sec 까지), 없으면 policy.timeout_sec.
+/// 계획의 0 은 "제한 없음"이 아니라 max_timeout_sec 으로 본다.
+fn step_timeout(setting: &SconnySetting, plan: &CommandPlan, index: usize) -> u64 {
+    let from_plan = plan.timeout_sec.as_ref().and_then(|v| v.get(index)).copied().flatten();
+    match from_plan {
+        Some(0) => setting.policy.max_timeout_sec,
+        Some(t) if setting.policy.max_timeout_sec > 0 => t.min(setting.policy.max_timeout_sec),
+        Some(t) => t,
+        None => setting.policy.timeout_sec,
     }
-    Ok(())
 }
 
 fn normalize_llm_json(text: &str) -> Result<String, String> {
@@ -181,3 +573,85 @@ fn normalize_llm_json(text: &str) -> Result<String, String> {
 
     Ok(s[start..=end].trim().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scy_output::OutputFormat;
+    use crate::scy_setting::ConfigLayer;
+    use std::path::PathBuf;
+
+    fn plan(json: &str) -> CommandPlan {
+        parse_plan(json).unwrap()
+    }
+
+    /// (실행한 명령, on_failure 핸들러인지)
+    fn ran(out: &Output) -> Vec<(String, bool)> {
+        out.steps().iter().map(|s| (s.cmd.clone(), s.on_failure)).collect()
+    }
+
+    #[test]
+    fn stop_skips_remaining_steps() {
+        let setting = SconnySetting::new();
+        let mut out = Output::new(OutputFormat::Json, "t");
+        let err = run_steps(&setting, &plan(r#"{"cmd":["true","false","echo never"]}"#), None, None, &mut out).unwrap_err();
+        assert_eq!(err, "1 of 3 command(s) failed (step 2), 1 skipped; the plan was partially applied.");
+        assert_eq!(ran(&out), [("true".to_string(), false), ("false".to_string(), false)]);
+        assert_eq!(out.failed_step(0).map(|s| s.index), Some(2));
+    }
+
+    #[test]
+    fn continue_runs_every_step() {
+        let mut setting = SconnySetting::new();
+        setting.policy.on_error = OnError::Continue;
+        let mut out = Output::new(OutputFormat::Json, "t");
+        let err = run_steps(&setting, &plan(r#"{"cmd":["false","exit 3","true"]}"#), None, None, &mut out).unwrap_err();
+        assert_eq!(err, "2 of 3 command(s) failed (step 1, 2); the plan was partially applied.");
+        assert_eq!(out.steps().iter().map(|s| s.exit_code).collect::<Vec<_>>(), [Some(1), Some(3), Some(0)]);
+
+        assert!(run_steps(&setting, &plan(r#"{"cmd":["true","true"]}"#), None, None, &mut out).is_ok());
+    }
+
+    #[test]
+    fn plan_on_error_applies_only_when_user_did_not_set_it() {
+        let p = plan(r#"{"cmd":["false","true"],"on_error":"continue"}"#);
+        let mut setting = SconnySetting::new();
+        let mut out = Output::new(OutputFormat::Json, "t");
+        let _ = run_steps(&setting, &p, None, None, &mut out);
+        assert_eq!(out.step_count(), 2);
+
+        // 사용자가 stop 을 정했으면 계획의 continue 는 무시
+        setting.origins.insert("policy.on_error".to_string(), ConfigOrigin::File(ConfigLayer::User, PathBuf::from("c.toml")));
+        let mut out = Output::new(OutputFormat::Json, "t");
+        let _ = run_steps(&setting, &p, None, None, &mut out);
+        assert_eq!(out.step_count(), 1);
+    }
+
+    #[test]
+    fn on_failure_handler_runs_only_for_failed_step() {
+        let setting = SconnySetting::new();
+        let mut out = Output::new(OutputFormat::Json, "t");
+        let p = plan(r#"{"cmd":["true","false","true"],"on_failure":["echo a",null,null]}"#);
+        assert!(run_steps(&setting, &p, None, None, &mut out).is_err());
+        assert_eq!(ran(&out), [("true".to_string(), false), ("false".to_string(), false)]);
+
+        let mut out = Output::new(OutputFormat::Json, "t");
+        let p = plan(r#"{"cmd":["false","true"],"on_failure":["echo cleanup"],"on_error":"continue"}"#);
+        let err = run_steps(&setting, &p, None, None, &mut out).unwrap_err();
+        assert_eq!(
+            ran(&out),
+            [("false".to_string(), false), ("echo cleanup".to_string(), true), ("true".to_string(), false)]
+        );
+        assert_eq!(out.steps()[1].index, 1);
+        // 핸들러는 실패한 step 으로 세지 않음
+        assert!(err.starts_with("1 of 2 command(s) failed (step 1)"));
+    }
+
+    #[test]
+    fn only_a_successful_step_makes_a_partial_apply() {
+        let setting = SconnySetting::new();
+        let mut out = Output::new(OutputFormat::Json, "t");
+        let err = run_steps(&setting, &plan(r#"{"cmd":["false","true"]}"#), None, None, &mut out).unwrap_err();
+        assert_eq!(err, "1 of 2 command(s) failed (step 1), 1 skipped.");
+    }
+}
@@ -0,0 +1,275 @@
+// scy_git.rs
+// CWD 가 git 작업 트리 안이면 브랜치, upstream, 변경 상태, stash 수, 최근 커밋 제목을 조사해서
+// 프롬프트에 넣는다 ("마지막 커밋 3개 합쳐줘", "방금 pull 되돌려줘" 같은 요청용).
+// REPL 에서 요청마다 상태가 바뀌므로 캐시하지 않고 프롬프트를 만들 때마다 조사한다.
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const GIT_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_SUBJECT_CHARS: usize = 100;
+
+#[derive(Debug, Clone, Default)]
+pub struct GitInfo {
+    pub root: PathBuf,
+    pub branch: Option<String>, // None: detached HEAD
+    pub head: Option<String>,   // 짧은 커밋 id (커밋이 없으면 None)
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub staged: usize,
+    pub modified: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+    pub stashes: usize,
+    pub in_progress: Option<&'static str>, // rebase | merge | cherry-pick | revert | bisect
+    pub recent: Vec<String>,               // "abc1234 subject", 최신부터
+}
+
+impl GitInfo {
+    pub fn is_dirty(&self) -> bool {
+        self.staged + self.modified + self.untracked + self.conflicted > 0
+    }
+
+    /// 프롬프트용 몇 줄
+    pub fn prompt_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("- Git repository: {}", self.root.display())];
+        let head = self.head.as_deref().unwrap_or("no commits yet");
+        let mut branch = match &self.branch {
+            Some(b) => format!("  - Branch: {} ({})", b, head),
+            None => format!("  - Branch: detached HEAD at {}", head),
+        };
+        match &self.upstream {
+            Some(u) => branch.push_str(&format!(", upstream {} (ahead {}, behind {})", u, self.ahead, self.behind)),
+            None if self.branch.is_some() => branch.push_str(", no upstream"),
+            None => {}
+        }
+        lines.push(branch);
+
+        if self.is_dirty() {
+            let mut parts = Vec::new();
+            for (n, what) in [
+                (self.conflicted, "conflicted"),
+                (self.staged, "staged"),
+                (self.modified, "modified"),
+                (self.untracked, "untracked"),
+            ] {
+                if n > 0 {
+                    parts.push(format!("{} {}", n, what));
+                }
+            }
+            lines.push(format!("  - Working tree: dirty ({})", parts.join(", ")));
+        } else {
+            lines.push("  - Working tree: clean".to_string());
+        }
+        if self.stashes > 0 {
+            let unit = if self.stashes == 1 { "entry" } else { "entries" };
+            lines.push(format!("  - Stash: {} {}", self.stashes, unit));
+        }
+        if let Some(op) = self.in_progress {
+            lines.push(format!("  - In progress: {} (not finished)", op));
+        }
+        if !self.recent.is_empty() {
+            lines.push("  - Recent commits (newest first):".to_string());
+            lines.extend(self.recent.iter().map(|c| format!("    {}", c)));
+        }
+        lines
+    }
+}
+
+/// cwd 가 git 작업 트리 안이면 조사 결과. git 이 없거나 저장소가 아니면 None.
+/// recent: 최근 커밋 제목 수 (0 이면 넣지 않음)
+pub fn detect(cwd: &Path, recent: usize) -> Option<GitInfo> {
+    let top = git(cwd, &["rev-parse", "--show-toplevel", "--absolute-git-dir"])?;
+    let mut it = top.lines();
+    let root = PathBuf::from(it.next()?.trim());
+    let git_dir = PathBuf::from(it.next()?.trim());
+
+    let mut info = GitInfo { root, ..GitInfo::default() };
+
+    if let Some(status) = git(cwd, &["status", "--porcelain=v2", "--branch"]) {
+        parse_status(&status, &mut info);
+    }
+
+    info.stashes = git(cwd, &["rev-list", "--walk-reflogs", "--count", "refs/stash"])
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0);
+
+    info.in_progress = [
+        ("rebase-merge", "rebase"),
+        ("rebase-apply", "rebase"),
+        ("MERGE_HEAD", "merge"),
+        ("CHERRY_PICK_HEAD", "cherry-pick"),
+        ("REVERT_HEAD", "revert"),
+        ("BISECT_LOG", "bisect"),
+    ]
+    .iter()
+    .find(|(f, _)| git_dir.join(f).exists())
+    .map(|(_, op)| *op);
+
+    if recent > 0 && info.head.is_some() {
+        let n = format!("-{}", recent);
+        if let Some(log) = git(cwd, &["log", &n, "--no-decorate", "--format=%h %s"]) {
+            info.recent = log
+                .lines()
+                .map(|l| {
+                    let l = l.trim_end();
+                    if l.chars().count() > MAX_SUBJECT_CHARS {
+                        l.chars().take(MAX_SUBJECT_CHARS - 3).collect::<String>() + "..."
+                    } else {
+                        l.to_string()
+                    }
+                })
+                .collect();
+        }
+    }
+    Some(info)
+}
+
+/// --porcelain=v2 --branch: "# branch.*" 헤더 + 파일별 한 줄
+fn parse_status(status: &str, info: &mut GitInfo) {
+    for line in status.lines() {
+        if let Some(h) = line.strip_prefix("# branch.") {
+            let (key, value) = h.split_once(' ').unwrap_or((h, ""));
+            match key {
+                "oid" if value != "(initial)" => info.head = Some(value.chars().take(7).collect()),
+                "head" if value != "(detached)" => info.branch = Some(value.to_string()),
+                "upstream" => info.upstream = Some(value.to_string()),
+                "ab" => {
+                    for n in value.split_whitespace() {
+                        if let Some(a) = n.strip_prefix('+') {
+                            info.ahead = a.parse().unwrap_or(0);
+                        } else if let Some(b) = n.strip_prefix('-') {
+                            info.behind = b.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match line.split(' ').next().unwrap_or_default() {
+            "1" | "2" => {
+                // "1 XY ...": X = index, Y = 작업 트리
+                let xy: Vec<char> = line.chars().skip(2).take(2).collect();
+                if xy.first().is_some_and(|c| *c != '.') {
+                    info.staged += 1;
+                }
+                if xy.get(1).is_some_and(|c| *c != '.') {
+                    info.modified += 1;
+                }
+            }
+            "u" => info.conflicted += 1,
+            "?" => info.untracked += 1,
+            _ => {}
+        }
+    }
+}
+
+/// git 실행. 실패하거나 GIT_TIMEOUT 을 넘으면 None
+fn git(cwd: &Path, args: &[&str]) -> Option<String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .args(args)
+        .env("GIT_OPTIONAL_LOCKS", "0") // status 가 index.lock 을 잡지 않게
+        .env("LC_ALL", "C")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // 출력이 파이프 버퍼보다 클 수 있으므로 따로 읽음
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        buf
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(s)) => break s,
+            Ok(None) if started.elapsed() < GIT_TIMEOUT => thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    let out = reader.join().ok()?;
+    status.success().then(|| String::from_utf8_lossy(&out).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(status: &str) -> GitInfo {
+        let mut info = GitInfo { root: PathBuf::from("/w"), ..GitInfo::default() };
+        parse_status(status, &mut info);
+        info
+    }
+
+    #[test]
+    fn porcelain_v2_branch_headers() {
+        let info = parse(concat!(
+            "# branch.oid 3f2a9c1d0e5b7a8c9d0e1f2a3b4c5d6e7f8a9b0c\n",
+            "# branch.head main\n",
+            "# branch.upstream origin/main\n",
+            "# branch.ab +1 -2\n",
+        ));
+        assert_eq!(info.head.as_deref(), Some("3f2a9c1"));
+        assert_eq!(info.branch.as_deref(), Some("main"));
+        assert_eq!(info.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((info.ahead, info.behind), (1, 2));
+        assert!(!info.is_dirty());
+
+        let info = parse("# branch.oid (initial)\n# branch.head (detached)\n");
+        assert_eq!((info.head, info.branch), (None, None));
+    }
+
+    #[test]
+    fn porcelain_v2_file_lines() {
+        let info = parse(concat!(
+            "# branch.head dev\n",
+            "1 M. N... 100644 100644 100644 a a src/staged.rs\n",
+            "1 .M N... 100644 100644 100644 a a src/edited.rs\n",
+            "1 MM N... 100644 100644 100644 a a src/both.rs\n",
+            "2 R. N... 100644 100644 100644 a a R100 new.rs\told.rs\n",
+            "u UU N... 100644 100644 100644 100644 a a a conflict.rs\n",
+            "? notes.txt\n",
+            "? tmp/\n",
+            "! ignored.o\n",
+        ));
+        assert_eq!((info.staged, info.modified, info.conflicted, info.untracked), (3, 2, 1, 2));
+    }
+
+    #[test]
+    fn prompt_lines_summarize_state() {
+        let mut info = parse("# branch.oid 3f2a9c1d\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +1 -2\n? a\n");
+        info.stashes = 1;
+        info.in_progress = Some("rebase");
+        info.recent = vec!["3f2a9c1 Fix it".to_string()];
+        assert_eq!(
+            info.prompt_lines(),
+            [
+                "- Git repository: /w",
+                "  - Branch: main (3f2a9c1), upstream origin/main (ahead 1, behind 2)",
+                "  - Working tree: dirty (1 untracked)",
+                "  - Stash: 1 entry",
+                "  - In progress: rebase (not finished)",
+                "  - Recent commits (newest first):",
+                "    3f2a9c1 Fix it",
+            ]
+        );
+
+        let info = parse("# branch.oid (initial)\n# branch.head (detached)\n");
+        assert_eq!(info.prompt_lines()[1..], ["  - Branch: detached HEAD at no commits yet", "  - Working tree: clean"]);
+    }
+}
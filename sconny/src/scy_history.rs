@@ -0,0 +1,275 @@
+// scy_history.rs
+// 요청마다 요청문, 모델 응답, 계획, 확인 결과, step별 종료 코드를 기록하고
+// `sconny history list/show/search/rerun` 으로 다시 본다.
+// 저장 위치: $XDG_DATA_HOME/sconny/history.jsonl (한 줄에 한 건, 오래된 것부터)
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::scy_journal::utc_stamp;
+use crate::scy_output::Output;
+use crate::scy_setting::{user_data_dir, HistorySetting};
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryStep {
+    pub index: usize,
+    pub cmd: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u128,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub on_failure: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub time: u64, // unix seconds
+    pub cwd: PathBuf,
+    pub request: String,
+    pub service: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub llm_output: Vec<String>, // 모델 응답 원문 (재요청/repair 포함)
+    #[serde(default)]
+    pub plan: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation: Option<String>, // yes | no | auto_yes | not_required
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<HistoryStep>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal: Option<String>, // undo 저널 id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerun_of: Option<u64>,
+}
+
+impl HistoryEntry {
+    /// 요청 하나가 끝난 뒤의 Output 으로 기록 생성 (id 는 append 에서 정함)
+    pub fn from_output(out: &Output, status: &str, service: &str, model: &str) -> Self {
+        HistoryEntry {
+            id: 0,
+            time: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            cwd: env::current_dir().unwrap_or_default(),
+            request: out.request().to_string(),
+            service: service.to_string(),
+            model: model.to_string(),
+            llm_output: out.llm_outputs().to_vec(),
+            plan: out.plan_value().cloned(),
+            confirmation: out.confirmation_decision().map(str::to_string),
+            status: status.to_string(),
+            steps: out
+                .steps()
+                .iter()
+                .map(|s| HistoryStep {
+                    index: s.index,
+                    cmd: s.cmd.clone(),
+                    exit_code: s.exit_code,
+                    duration_ms: s.duration_ms,
+                    timed_out: s.timed_out,
+                    cancelled: s.cancelled,
+                    on_failure: s.on_failure,
+                })
+                .collect(),
+            journal: out.journal_id().map(str::to_string),
+            errors: out.errors().to_vec(),
+            rerun_of: None,
+        }
+    }
+
+    /// 계획의 명령 목록
+    pub fn commands(&self) -> Vec<String> {
+        self.plan
+            .as_ref()
+            .and_then(|p| p.get("cmd"))
+            .and_then(Value::as_array)
+            .map(|a| a.iter().filter_map(Value::as_str).map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    /// "2026-10-17 07:55:59 UTC"
+    pub fn time_text(&self) -> String {
+        let s = utc_stamp(self.time);
+        format!("{}-{}-{} {}:{}:{} UTC", &s[0..4], &s[4..6], &s[6..8], &s[9..11], &s[11..13], &s[13..15])
+    }
+}
+
+/// 기록 추가. 새 id 리턴. max_entries 를 넘으면 오래된 것부터 삭제.
+pub fn append(entry: HistoryEntry, limits: &HistorySetting) -> Result<u64, String> {
+    let path = history_path().ok_or("cannot locate data directory (set XDG_DATA_HOME or HOME)")?;
+    append_at(&path, entry, limits)
+}
+
+fn append_at(path: &Path, mut entry: HistoryEntry, limits: &HistorySetting) -> Result<u64, String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    }
+    let mut all = load_from(path)?;
+    entry.id = all.last().map(|e| e.id + 1).unwrap_or(1);
+    let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+
+    // 10% 넘게 초과했을 때만 다시 씀
+    let max = limits.max_entries.max(1);
+    if all.len() + 1 > max + max / 10 {
+        let id = entry.id;
+        all.push(entry);
+        let keep = &all[all.len() - max..];
+        let mut text = String::new();
+        for e in keep {
+            text.push_str(&serde_json::to_string(e).map_err(|e| e.to_string())?);
+            text.push('\n');
+        }
+        fs::write(path, text).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        return Ok(id);
+    }
+
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+    writeln!(f, "{}", line).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    Ok(entry.id)
+}
+
+/// 전체 기록 (오래된 것부터). 읽지 못한 줄은 건너뜀.
+pub fn load_all() -> Result<Vec<HistoryEntry>, String> {
+    match history_path() {
+        Some(path) => load_from(&path),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn load_from(path: &Path) -> Result<Vec<HistoryEntry>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+    };
+    Ok(text.lines().filter_map(|l| serde_json::from_str(l).ok()).collect())
+}
+
+/// id 가 없거나 "last" 면 가장 최근 기록
+pub fn find(id: Option<&str>) -> Result<HistoryEntry, String> {
+    find_in(load_all()?, id)
+}
+
+fn find_in(all: Vec<HistoryEntry>, id: Option<&str>) -> Result<HistoryEntry, String> {
+    match id {
+        None | Some("last") => all.into_iter().last().ok_or_else(|| "No history recorded yet.".to_string()),
+        Some(id) => {
+            let n: u64 = id.trim_start_matches('#').parse().map_err(|_| format!("Invalid history id '{}'", id))?;
+            all.into_iter().find(|e| e.id == n).ok_or_else(|| format!("No history entry with id {}", n))
+        }
+    }
+}
+
+/// 요청문, 명령, CWD 에서 대소문자 무시하고 모든 단어를 포함하는 기록
+pub fn search(query: &str) -> Result<Vec<HistoryEntry>, String> {
+    Ok(search_in(load_all()?, query))
+}
+
+fn search_in(all: Vec<HistoryEntry>, query: &str) -> Vec<HistoryEntry> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    all.into_iter()
+        .filter(|e| {
+            let hay = format!("{}\n{}\n{}", e.request, e.commands().join("\n"), e.cwd.display()).to_lowercase();
+            words.iter().all(|w| hay.contains(w.as_str()))
+        })
+        .collect()
+}
+
+fn history_path() -> Option<PathBuf> {
+    user_data_dir().map(|d| d.join("sconny").join(HISTORY_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(request: &str, cmd: &[&str]) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            time: 0,
+            cwd: PathBuf::from("/home/u/Project"),
+            request: request.to_string(),
+            service: "ollama".to_string(),
+            model: "m".to_string(),
+            llm_output: Vec::new(),
+            plan: Some(json!({ "cmd": cmd })),
+            confirmation: None,
+            status: "ok".to_string(),
+            steps: Vec::new(),
+            journal: None,
+            errors: Vec::new(),
+            rerun_of: None,
+        }
+    }
+
+    fn temp_file() -> PathBuf {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+        env::temp_dir().join(format!("sconny-history-test-{}-{}", std::process::id(), nanos)).join(HISTORY_FILE)
+    }
+
+    #[test]
+    fn append_assigns_ids_and_truncates() {
+        let path = temp_file();
+        let limits = HistorySetting { enabled: true, max_entries: 10 };
+        for i in 1..=11 {
+            assert_eq!(append_at(&path, entry(&format!("r{}", i), &["true"]), &limits).unwrap(), i);
+        }
+        // 10% 여유까지는 그대로 추가
+        assert_eq!(load_from(&path).unwrap().len(), 11);
+
+        // 넘으면 최근 max_entries 건만 남기고, id 는 계속 증가
+        assert_eq!(append_at(&path, entry("r12", &["true"]), &limits).unwrap(), 12);
+        let all = load_from(&path).unwrap();
+        assert_eq!(all.iter().map(|e| e.id).collect::<Vec<_>>(), (3..=12).collect::<Vec<_>>());
+        assert_eq!(append_at(&path, entry("r13", &["true"]), &limits).unwrap(), 13);
+
+        // 깨진 줄은 건너뜀
+        let mut f = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(f, "{{not json").unwrap();
+        assert_eq!(load_from(&path).unwrap().len(), 11);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn find_by_id_or_last() {
+        let all = || {
+            let mut v = vec![entry("first", &["ls"]), entry("second", &["pwd"])];
+            v[0].id = 1;
+            v[1].id = 2;
+            v
+        };
+        assert_eq!(find_in(all(), None).unwrap().request, "second");
+        assert_eq!(find_in(all(), Some("last")).unwrap().request, "second");
+        assert_eq!(find_in(all(), Some("#1")).unwrap().request, "first");
+        assert_eq!(find_in(all(), Some("3")).unwrap_err(), "No history entry with id 3");
+        assert_eq!(find_in(all(), Some("x")).unwrap_err(), "Invalid history id 'x'");
+        assert_eq!(find_in(Vec::new(), None).unwrap_err(), "No history recorded yet.");
+    }
+
+    #[test]
+    fn search_matches_all_words_case_insensitively() {
+        let all = || vec![entry("Compress logs", &["tar czf logs.tgz logs"]), entry("list files", &["ls -la"])];
+        let requests = |q: &str| search_in(all(), q).into_iter().map(|e| e.request).collect::<Vec<_>>();
+        assert_eq!(requests("compress TGZ"), ["Compress logs"]);
+        assert_eq!(requests("ls -la"), ["list files"]);
+        assert_eq!(requests("project"), ["Compress logs", "list files"]); // CWD
+        assert!(requests("compress ls").is_empty());
+    }
+}
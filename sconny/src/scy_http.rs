@@ -0,0 +1,343 @@
+// scy_http.rs
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+use crate::scy_api::ScyApiError;
+use crate::scy_signal;
+
+/// 제공자 설정에 timeout_sec 이 없을 때 쓰는 HTTP 타임아웃 (policy.timeout_sec 은 명령용이라 따로 둠)
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+const MAX_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 모든 백엔드가 공유하는 in-process HTTP(S) 클라이언트.
+/// - 연결/전체 타임아웃 (0 이면 전체 타임아웃 없음)
+/// - HTTP_PROXY / HTTPS_PROXY / ALL_PROXY / NO_PROXY (대소문자 모두)
+/// - 2xx가 아니면 ScyApiError::Http { status, body }
+/// - 요청 본문은 메모리의 문자열을 그대로 보냄 (프롬프트를 임시 파일에 쓰지 않음)
+/// - 스트리밍 응답은 줄 단위로 읽으며 Ctrl+C 취소를 확인
+pub struct HttpClient {
+    timeout: Option<Duration>,
+    proxy: ProxyConfig,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProxyConfig {
+    http: Option<String>,
+    https: Option<String>,
+    no_proxy: Vec<String>,
+}
+
+impl HttpClient {
+    pub fn new(timeout_secs: u64) -> Self {
+        Self {
+            timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs)),
+            proxy: ProxyConfig::from_env(),
+        }
+    }
+
+    pub fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<String, ScyApiError> {
+        let mut req = self.agent_for(url, false)?.get(url);
+        for (k, v) in headers {
+            req = req.set(k, v);
+        }
+        read_response(url, req.call())
+    }
+
+    pub fn post_json(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<String, ScyApiError> {
+        let mut req = self.agent_for(url, false)?.post(url).set("Content-Type", "application/json");
+        for (k, v) in headers {
+            req = req.set(k, v);
+        }
+        read_response(url, req.send_string(body))
+    }
+
+    /// 스트리밍 POST. 전체 타임아웃 대신 "청크 사이 대기 시간"에 timeout을 적용한다.
+    /// 응답 본문의 각 줄(개행 제외)을 on_line에 넘기고, false를 리턴하면 읽기를 멈춘다.
+    pub fn post_json_lines<F>(&self, url: &str, headers: &[(&str, &str)], body: &str, mut on_line: F) -> Result<(), ScyApiError>
+    where
+        F: FnMut(&str) -> Result<bool, ScyApiError>,
+    {
+        let mut req = self.agent_for(url, true)?.post(url).set("Content-Type", "application/json");
+        for (k, v) in headers {
+            req = req.set(k, v);
+        }
+
+        let resp = match req.send_string(body) {
+            Ok(r) => r,
+            Err(e) => return read_response(url, Err(e)).map(|_| ()),
+        };
+
+        let reader = BufReader::new(resp.into_reader());
+        for line in reader.lines() {
+            if scy_signal::is_cancelled() {
+                return Err(ScyApiError::Cancelled);
+            }
+            let line = line.map_err(|e| ScyApiError::Transport {
+                url: url.to_string(),
+                message: format!("stream read failed: {}", e),
+            })?;
+            if !on_line(line.trim_end_matches('\r'))? {
+                break;
+            }
+        }
+
+        if scy_signal::is_cancelled() {
+            return Err(ScyApiError::Cancelled);
+        }
+        Ok(())
+    }
+
+    fn agent_for(&self, url: &str, streaming: bool) -> Result<ureq::Agent, ScyApiError> {
+        let connect = self.timeout.map_or(MAX_CONNECT_TIMEOUT, |t| t.min(MAX_CONNECT_TIMEOUT));
+        let mut builder = ureq::AgentBuilder::new().timeout_connect(connect);
+        builder = match self.timeout {
+            Some(t) if streaming => builder.timeout_read(t),
+            Some(t) => builder.timeout(t),
+            None => builder,
+        };
+
+        if let Some(p) = self.proxy.proxy_for(url) {
+            let proxy = ureq::Proxy::new(p).map_err(|e| ScyApiError::Transport {
+                url: url.to_string(),
+                message: format!("invalid proxy '{}': {}", p, e),
+            })?;
+            builder = builder.proxy(proxy);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+fn read_response(url: &str, res: Result<ureq::Response, ureq::Error>) -> Result<String, ScyApiError> {
+    match res {
+        Ok(resp) => resp.into_string().map_err(ScyApiError::Io),
+        Err(ureq::Error::Status(status, resp)) => Err(ScyApiError::Http {
+            status,
+            body: resp.into_string().unwrap_or_default(),
+        }),
+        Err(ureq::Error::Transport(t)) => Err(ScyApiError::Transport {
+            url: url.to_string(),
+            message: {
+                let mut m = t.kind().to_string();
+                if let Some(detail) = t.message() {
+                    m.push_str(&format!(": {}", detail));
+                }
+                if let Some(src) = std::error::Error::source(&t) {
+                    m.push_str(&format!(" ({})", src));
+                }
+                m
+            },
+        }),
+    }
+}
+
+impl ProxyConfig {
+    fn from_env() -> Self {
+        let all = env_any(&["ALL_PROXY", "all_proxy"]);
+        Self {
+            http: env_any(&["HTTP_PROXY", "http_proxy"]).or_else(|| all.clone()),
+            https: env_any(&["HTTPS_PROXY", "https_proxy"]).or(all),
+            no_proxy: env_any(&["NO_PROXY", "no_proxy"])
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| s.trim().to_lowercase())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn proxy_for(&self, url: &str) -> Option<&str> {
+        let (scheme, rest) = url.split_once("://")?;
+        let hostport = rest.split(['/', '?', '#']).next().unwrap_or("");
+        let hostport = hostport.rsplit('@').next().unwrap_or(hostport).to_lowercase();
+        let host = match hostport.strip_prefix('[') {
+            Some(v6) => v6.split(']').next().unwrap_or("").to_string(),
+            None => hostport.split(':').next().unwrap_or("").to_string(),
+        };
+
+        if self.bypass(&host, &hostport) {
+            return None;
+        }
+
+        match scheme.to_lowercase().as_str() {
+            "https" => self.https.as_deref(),
+            "http" => self.http.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// NO_PROXY 규칙: "*", 정확히 일치하는 host(또는 host:port), ".example.com"/"example.com" 도메인 접미사
+    fn bypass(&self, host: &str, hostport: &str) -> bool {
+        self.no_proxy.iter().any(|rule| {
+            if rule == "*" || rule == host || rule == hostport {
+                return true;
+            }
+            let domain = rule.trim_start_matches("*.").trim_start_matches('.');
+            host.ends_with(&format!(".{}", domain))
+        })
+    }
+}
+
+fn env_any(keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|k| env::var(k).ok())
+        .map(|v| v.trim().to_string())
+        .find(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use crate::ollama_api::scy_ollama_api::OllamaApi;
+    use crate::scy_backend::{ChatRequest, LlmBackend};
+
+    /// 요청 하나에 정해진 응답을 돌려주는 로컬 서버. (base url, 받은 요청 본문)
+    fn serve(status: &str, content_type: &str, body: &str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        );
+        let handle = thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut req = Vec::new();
+            let mut buf = [0u8; 4096];
+            // 헤더 끝까지 읽고 Content-Length 만큼 본문
+            let body_start = loop {
+                let n = conn.read(&mut buf).unwrap();
+                req.extend_from_slice(&buf[..n]);
+                if let Some(i) = req.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break i + 4;
+                }
+            };
+            let head = String::from_utf8_lossy(&req[..body_start]).to_lowercase();
+            let len: usize = head
+                .lines()
+                .find_map(|l| l.strip_prefix("content-length:"))
+                .map_or(0, |v| v.trim().parse().unwrap());
+            while req.len() < body_start + len {
+                let n = conn.read(&mut buf).unwrap();
+                req.extend_from_slice(&buf[..n]);
+            }
+            conn.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&req[body_start..]).into_owned()
+        });
+        (url, handle)
+    }
+
+    fn client(timeout_secs: u64) -> HttpClient {
+        // 환경의 프록시 설정과 상관없이 로컬 서버로
+        HttpClient { proxy: ProxyConfig::default(), ..HttpClient::new(timeout_secs) }
+    }
+
+    fn lines(http: &HttpClient, url: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        http.post_json_lines(url, &[], "{}", |l| {
+            out.push(l.to_string());
+            Ok(true)
+        })
+        .unwrap();
+        out
+    }
+
+    #[test]
+    fn zero_timeout_means_no_limit() {
+        assert_eq!(HttpClient::new(0).timeout, None);
+        assert_eq!(HttpClient::new(5).timeout, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn json_response() {
+        let (url, server) = serve("200 OK", "application/json", r#"{"ok":true}"#);
+        let got = client(0).post_json(&url, &[], r#"{"q":1}"#).unwrap();
+        assert_eq!(got, r#"{"ok":true}"#);
+        assert_eq!(server.join().unwrap(), r#"{"q":1}"#);
+    }
+
+    /// TMPDIR 을 빈 디렉토리로 둔 자식 프로세스에서 요청을 보내고, 그 디렉토리에 아무것도 생기지 않았는지 확인
+    #[test]
+    fn body_is_not_written_to_temp_files() {
+        let name = "scy_http::tests::body_is_not_written_to_temp_files";
+        if env::var_os("SCONNY_TEST_CHILD").is_some() {
+            let (url, server) = serve("200 OK", "application/json", "{}");
+            client(5).post_json(&url, &[], r#"{"prompt":"secret"}"#).unwrap();
+            assert_eq!(server.join().unwrap(), r#"{"prompt":"secret"}"#);
+            return;
+        }
+
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().subsec_nanos();
+        let tmp = env::temp_dir().join(format!("sconny-http-test-{}-{}", std::process::id(), nanos));
+        std::fs::create_dir_all(&tmp).unwrap();
+        let status = std::process::Command::new(env::current_exe().unwrap())
+            .args([name, "--exact", "--test-threads=1"])
+            .env("SCONNY_TEST_CHILD", "1")
+            .env("TMPDIR", &tmp)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        let left: Vec<_> = std::fs::read_dir(&tmp).unwrap().map(|e| e.unwrap().file_name()).collect();
+        let _ = std::fs::remove_dir_all(&tmp);
+        assert!(status.success());
+        assert!(left.is_empty(), "temp files created: {:?}", left);
+    }
+
+    #[test]
+    fn sse_lines() {
+        let body = "event: delta\r\ndata: {\"a\":1}\r\n\r\ndata: [DONE]\r\n\r\n";
+        let (url, _) = serve("200 OK", "text/event-stream", body);
+        assert_eq!(lines(&client(5), &url), ["event: delta", "data: {\"a\":1}", "", "data: [DONE]", ""]);
+    }
+
+    #[test]
+    fn ndjson_stream_through_ollama() {
+        let body = concat!(
+            "{\"message\":{\"content\":\"{\\\"a\\\"\"},\"done\":false}\n",
+            "{\"message\":{\"content\":\":1}\"},\"done\":false}\n",
+            "{\"message\":{\"content\":\"\"},\"done\":true,\"done_reason\":\"stop\"}\n",
+        );
+        let (url, server) = serve("200 OK", "application/x-ndjson", body);
+        let api = OllamaApi::new(url, "m".to_string(), 5);
+        let mut deltas = Vec::new();
+        let req = ChatRequest::with_history("sys", &[], "hi");
+        let text = api.chat_stream(&req, &mut |d| deltas.push(d.to_string())).unwrap();
+        assert_eq!(text, r#"{"a":1}"#);
+        assert_eq!(deltas, [r#"{"a""#, ":1}"]);
+        assert!(server.join().unwrap().contains(r#""stream":true"#));
+    }
+
+    #[test]
+    fn error_status_keeps_body() {
+        let (url, _) = serve("503 Service Unavailable", "text/plain", "overloaded");
+        match client(5).post_json(&url, &[], "{}") {
+            Err(ScyApiError::Http { status, body }) => assert_eq!((status, body.as_str()), (503, "overloaded")),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // 스트리밍 요청도 같은 에러
+        let (url, _) = serve("401 Unauthorized", "application/json", r#"{"error":"bad key"}"#);
+        let err = client(5).post_json_lines(&url, &[], "{}", |_| Ok(true)).unwrap_err();
+        assert!(matches!(err, ScyApiError::Http { status: 401, .. }));
+    }
+
+    #[test]
+    fn ollama_error_body_becomes_api_error() {
+        let (url, _) = serve("404 Not Found", "application/json", r#"{"error":"model 'm' not found"}"#);
+        let api = OllamaApi::new(url, "m".to_string(), 5);
+        let err = api.chat(&ChatRequest::with_history("sys", &[], "hi")).unwrap_err();
+        match err {
+            ScyApiError::Api { code, message, .. } => assert_eq!((code, message.as_str()), (Some(404), "model 'm' not found")),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
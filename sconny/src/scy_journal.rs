@@ -0,0 +1,1041 @@
+// scy_journal.rs
+// 실행한 계획마다 건드릴 경로를 미리 백업해두고 `sconny undo [plan-id]` 로 되돌린다.
+// 저장 위치: $XDG_DATA_HOME/sconny/journal/<plan-id>/ (journal.json + files/)
+// 어떤 경로를 건드릴지는 명령을 파싱해서 추정하므로 (리다이렉션, rm, mv, cp, sed -i ...)
+// 추정하지 못한 변경은 되돌릴 수 없다.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::scy_analyzer::{find_exec_commands, inner_script, program_name, unwrap_wrappers};
+use crate::scy_policy::{glob_match, resolve};
+use crate::scy_sandbox::{copy_tree, tree_size};
+use crate::scy_setting::user_data_dir;
+use crate::scy_shell::{self, Script};
+
+const JOURNAL_FILE: &str = "journal.json";
+const KEEP_JOURNALS: usize = 50; // 이보다 오래된 저널은 새 계획을 실행할 때 삭제
+
+/// 백업 크기 제한 (ExecPolicy 의 backup_max_mb / backup_max_file_mb)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BackupLimits {
+    pub max_total_bytes: u64,
+    pub max_file_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryAction {
+    Backup,  // 실행 전 내용을 files/ 에 복사해둠 → undo 시 복원
+    Created, // 실행 전에는 없던 경로 → undo 시 삭제
+    Skipped, // 제한을 넘거나 복사 실패로 백업 못 함 → undo 불가
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub step: usize, // 1부터
+    pub path: PathBuf,
+    pub action: EntryAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<BackupKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<String>, // 저널 디렉토리 기준 상대 경로
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>, // Skipped 사유
+}
+
+/// 건드릴 경로를 다 알 수 없었던 step (파싱 실패, 모르는 프로그램). 그 변경은 되돌릴 수 없음
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnknownStep {
+    pub step: usize,
+    #[serde(alias = "error")]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Journal {
+    pub id: String,
+    pub created: u64, // unix seconds
+    pub request: String,
+    pub cwd: PathBuf,
+    pub cmd: Vec<String>,
+    pub entries: Vec<JournalEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<UnknownStep>,
+    #[serde(default)]
+    pub undone: bool,
+    #[serde(skip)]
+    dir: PathBuf,
+    #[serde(skip)]
+    used_bytes: u64,
+    #[serde(skip)]
+    limits: BackupLimits,
+}
+
+impl Journal {
+    /// 새 저널 디렉토리 생성. 오래된 저널은 정리.
+    pub fn begin(request: &str, cmds: &[String], limits: BackupLimits) -> Result<Journal, String> {
+        let root = journal_root().ok_or("cannot locate data directory (set XDG_DATA_HOME or HOME)")?;
+        let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+        // 같은 초에 여러 번 실행하면 -2, -3 ...
+        let base = utc_stamp(created);
+        let mut id = base.clone();
+        let mut n = 1;
+        while root.join(&id).exists() {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+        let dir = root.join(&id);
+        fs::create_dir_all(dir.join("files")).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        prune(&root, &id);
+
+        let journal = Journal {
+            id,
+            created,
+            request: request.to_string(),
+            cwd: env::current_dir().unwrap_or_default(),
+            cmd: cmds.to_vec(),
+            entries: Vec::new(),
+            unknown: Vec::new(),
+            undone: false,
+            dir,
+            used_bytes: 0,
+            limits,
+        };
+        journal.save()?;
+        Ok(journal)
+    }
+
+    /// step 실행 직전에 호출. 건드릴 것으로 보이는 경로를 백업하고, 백업하지 못한 경로의 경고를 리턴.
+    pub fn before_step(&mut self, step: usize, cmd: &str) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let touched = touched_paths(cmd, &self.cwd).unwrap_or_else(|e| Touched {
+            untracked: vec![format!("command could not be parsed: {}", e)],
+            ..Touched::default()
+        });
+        for reason in touched.untracked {
+            warnings.push(format!("{}; those changes cannot be undone", reason));
+            self.unknown.push(UnknownStep { step, reason });
+        }
+        for path in touched.paths {
+            if self.covers(&path) {
+                continue;
+            }
+            let entry = self.record(step, &path);
+            if let (EntryAction::Skipped, Some(reason)) = (entry.action, &entry.reason) {
+                warnings.push(format!("{}: {}", path.display(), reason));
+            }
+            self.entries.push(entry);
+        }
+        let _ = self.save();
+        warnings
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let p = self.dir.join(JOURNAL_FILE);
+        fs::write(&p, text).map_err(|e| format!("cannot write {}: {}", p.display(), e))
+    }
+
+    pub fn backed_up(&self) -> usize {
+        self.entries.iter().filter(|e| e.action != EntryAction::Skipped).count()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.entries.iter().filter(|e| e.action == EntryAction::Skipped).count()
+    }
+
+    /// 이미 기록한 경로이거나 그 상위 디렉토리를 통째로 백업/생성 기록했으면 true
+    fn covers(&self, path: &Path) -> bool {
+        self.entries.iter().any(|e| {
+            e.path == path || (e.action != EntryAction::Skipped && e.kind != Some(BackupKind::File) && path.starts_with(&e.path))
+        })
+    }
+
+    fn record(&mut self, step: usize, path: &Path) -> JournalEntry {
+        let mut entry = JournalEntry {
+            step,
+            path: path.to_path_buf(),
+            action: EntryAction::Created,
+            kind: None,
+            backup: None,
+            link_target: None,
+            reason: None,
+        };
+
+        let Ok(meta) = fs::symlink_metadata(path) else {
+            // 없던 경로: 가장 위쪽의 없는 상위 디렉토리까지 올라가서 기록 (mkdir -p a/b/c → a)
+            let mut top = path.to_path_buf();
+            while let Some(parent) = top.parent() {
+                if parent.as_os_str().is_empty() || fs::symlink_metadata(parent).is_ok() {
+                    break;
+                }
+                top = parent.to_path_buf();
+            }
+            entry.path = top;
+            return entry;
+        };
+
+        let limits = self.limits;
+        let skip = |mut e: JournalEntry, reason: String| {
+            e.action = EntryAction::Skipped;
+            e.reason = Some(reason);
+            e
+        };
+
+        let backup_rel = format!("files/{}", self.entries.len() + 1);
+        let backup = self.dir.join(&backup_rel);
+        entry.action = EntryAction::Backup;
+
+        if meta.file_type().is_symlink() {
+            entry.kind = Some(BackupKind::Symlink);
+            match fs::read_link(path) {
+                Ok(t) => entry.link_target = Some(t),
+                Err(e) => return skip(entry, format!("cannot read link: {}", e)),
+            }
+            return entry;
+        }
+
+        let remaining = limits.max_total_bytes.saturating_sub(self.used_bytes);
+        if meta.is_dir() {
+            entry.kind = Some(BackupKind::Dir);
+            let size = match tree_size(path, remaining) {
+                Ok(s) => s,
+                Err(e) => return skip(entry, format!("cannot scan directory: {}", e)),
+            };
+            if size > remaining {
+                return skip(entry, format!("directory exceeds backup limit ({} MB left)", remaining / (1024 * 1024)));
+            }
+            if let Err(e) = fs::create_dir_all(&backup).and_then(|_| copy_tree(path, &backup)) {
+                let _ = fs::remove_dir_all(&backup);
+                return skip(entry, format!("backup failed: {}", e));
+            }
+            self.used_bytes += size;
+        } else {
+            entry.kind = Some(BackupKind::File);
+            let size = meta.len();
+            if size > limits.max_file_bytes {
+                return skip(entry, format!("file is larger than policy.backup_max_file_mb ({} MB)", limits.max_file_bytes / (1024 * 1024)));
+            }
+            if size > remaining {
+                return skip(entry, "policy.backup_max_mb reached for this plan".to_string());
+            }
+            if let Err(e) = fs::copy(path, &backup) {
+                return skip(entry, format!("backup failed: {}", e));
+            }
+            self.used_bytes += size;
+        }
+        entry.backup = Some(backup_rel);
+        entry
+    }
+}
+
+/// 저널 목록 (오래된 것부터)
+pub fn list() -> Result<Vec<Journal>, String> {
+    let Some(root) = journal_root() else { return Ok(Vec::new()) };
+    let Ok(rd) = fs::read_dir(&root) else { return Ok(Vec::new()) };
+    let mut out: Vec<Journal> = rd
+        .filter_map(|e| e.ok())
+        .filter_map(|e| load(&e.path()).ok())
+        .collect();
+    out.sort_by(|a, b| (a.created, &a.id).cmp(&(b.created, &b.id)));
+    Ok(out)
+}
+
+/// id 가 없으면 아직 되돌리지 않은 가장 최근 저널
+pub fn find(id: Option<&str>) -> Result<Journal, String> {
+    let all = list()?;
+    match id {
+        Some(id) => all.into_iter().find(|j| j.id == id).ok_or_else(|| format!("No undo journal with id '{}'", id)),
+        None => all.into_iter().rev().find(|j| !j.undone).ok_or_else(|| "Nothing to undo.".to_string()),
+    }
+}
+
+/// 이 저널보다 나중에 실행했고 아직 되돌리지 않은 저널 중 같은 경로(또는 상위/하위 경로)를 건드린 것.
+/// 이 저널을 먼저 되돌리면 그 계획들의 변경을 덮어쓰게 된다. (저널 id, 설명) 목록, 최신부터.
+pub fn newer_conflicts(journal: &Journal) -> Result<Vec<(String, String)>, String> {
+    Ok(conflicts_in(journal, &list()?))
+}
+
+/// all: list() 순서 (오래된 것부터)
+fn conflicts_in(journal: &Journal, all: &[Journal]) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    for newer in all.iter().rev() {
+        if (newer.created, &newer.id) <= (journal.created, &journal.id) {
+            break;
+        }
+        if newer.undone {
+            continue;
+        }
+        let shared: Vec<&Path> = newer
+            .entries
+            .iter()
+            .map(|e| e.path.as_path())
+            .filter(|p| {
+                // 복원하지 않는 Skipped 항목은 덮어쓸 일이 없음
+                journal
+                    .entries
+                    .iter()
+                    .any(|e| e.action != EntryAction::Skipped && (p.starts_with(&e.path) || e.path.starts_with(p)))
+            })
+            .collect();
+        if let Some(first) = shared.first() {
+            let more = if shared.len() > 1 { format!(" and {} more", shared.len() - 1) } else { String::new() };
+            out.push((newer.id.clone(), format!("{}{}", first.display(), more)));
+        } else if !newer.unknown.is_empty() && !journal.entries.is_empty() {
+            out.push((newer.id.clone(), "unknown paths (some changes were not tracked)".to_string()));
+        }
+    }
+    out
+}
+
+/// 뒤에서부터 복원. 결과 메시지 목록 리턴 (실패한 항목도 포함).
+pub fn undo(journal: &mut Journal) -> Result<Vec<String>, String> {
+    if journal.undone {
+        return Err(format!("Plan {} was already undone.", journal.id));
+    }
+    let mut report = Vec::new();
+    for e in journal.entries.iter().rev() {
+        let p = &e.path;
+        let r = match e.action {
+            EntryAction::Created => match remove_path(p) {
+                Ok(true) => format!("removed  {}", p.display()),
+                Ok(false) => format!("absent   {}", p.display()),
+                Err(err) => format!("FAILED   {} (remove: {})", p.display(), err),
+            },
+            EntryAction::Backup => match restore(journal, e) {
+                Ok(()) => format!("restored {}", p.display()),
+                Err(err) => format!("FAILED   {} (restore: {})", p.display(), err),
+            },
+            EntryAction::Skipped => {
+                format!("skipped  {} (not backed up: {})", p.display(), e.reason.as_deref().unwrap_or("unknown"))
+            }
+        };
+        report.push(r);
+    }
+    for u in &journal.unknown {
+        report.push(format!("unknown  step {} ({})", u.step, u.reason));
+    }
+    journal.undone = true;
+    journal.save()?;
+    Ok(report)
+}
+
+fn restore(journal: &Journal, e: &JournalEntry) -> std::io::Result<()> {
+    remove_path(&e.path)?;
+    if let Some(parent) = e.path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let backup = e.backup.as_ref().map(|b| journal.dir.join(b));
+    match (e.kind, backup) {
+        (Some(BackupKind::Symlink), _) => {
+            #[cfg(unix)]
+            if let Some(t) = &e.link_target {
+                std::os::unix::fs::symlink(t, &e.path)?;
+            }
+            Ok(())
+        }
+        (Some(BackupKind::Dir), Some(b)) => {
+            fs::create_dir_all(&e.path)?;
+            copy_tree(&b, &e.path)?;
+            fs::set_permissions(&e.path, fs::metadata(&b)?.permissions())
+        }
+        (_, Some(b)) => fs::copy(&b, &e.path).map(|_| ()),
+        (_, None) => Ok(()),
+    }
+}
+
+/// 있으면 지우고 true
+fn remove_path(p: &Path) -> std::io::Result<bool> {
+    match fs::symlink_metadata(p) {
+        Err(_) => Ok(false),
+        Ok(m) if m.is_dir() => fs::remove_dir_all(p).map(|_| true),
+        Ok(_) => fs::remove_file(p).map(|_| true),
+    }
+}
+
+fn load(dir: &Path) -> Result<Journal, String> {
+    let text = fs::read_to_string(dir.join(JOURNAL_FILE)).map_err(|e| e.to_string())?;
+    let mut j: Journal = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    j.dir = dir.to_path_buf();
+    Ok(j)
+}
+
+fn journal_root() -> Option<PathBuf> {
+    user_data_dir().map(|d| d.join("sconny").join("journal"))
+}
+
+fn prune(root: &Path, keep_id: &str) {
+    let Ok(rd) = fs::read_dir(root) else { return };
+    let mut ids: Vec<String> = rd
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().join(JOURNAL_FILE).is_file())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|id| id != keep_id)
+        .collect();
+    ids.sort();
+    let excess = (ids.len() + 1).saturating_sub(KEEP_JOURNALS);
+    for id in ids.into_iter().take(excess) {
+        let _ = fs::remove_dir_all(root.join(id));
+    }
+}
+
+/// unix 초 → "YYYYMMDD-HHMMSS" (UTC)
+pub fn utc_stamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // days → 년/월/일 (proleptic Gregorian)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", y, m, d, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+// -------------------- 건드릴 경로 추정 --------------------
+
+// 파일을 바꾸지 않는 프로그램. 여기 없고 아래에서 따로 다루지도 않는 프로그램은 "추적하지 못한 변경"으로 기록
+const READ_ONLY: &[&str] = &[
+    "ls", "cat", "echo", "printf", "pwd", "cd", "export", "set", "unset", "true", "false", "test", "[", "[[", ":",
+    "grep", "egrep", "fgrep", "rg", "head", "tail", "wc", "du", "df", "stat", "file", "which", "type", "command",
+    "date", "whoami", "id", "uname", "hostname", "uptime", "free", "ps", "env", "printenv", "uniq", "cut", "tr",
+    "diff", "cmp", "md5sum", "sha1sum", "sha256sum", "tree", "jq", "basename", "dirname", "realpath", "readlink",
+    "sleep", "seq", "column", "nl", "less", "more", "zcat", "zipinfo", "xxd", "awk", "wait", "history", "man",
+];
+
+// 압축 프로그램과 붙이는 확장자 (un* 는 풀기)
+const COMPRESSORS: [(&str, &str, &str); 7] = [
+    ("gzip", "gunzip", ".gz"),
+    ("bzip2", "bunzip2", ".bz2"),
+    ("xz", "unxz", ".xz"),
+    ("lzma", "unlzma", ".lzma"),
+    ("zstd", "unzstd", ".zst"),
+    ("lz4", "unlz4", ".lz4"),
+    ("compress", "uncompress", ".Z"),
+];
+
+const MAX_ARCHIVE_MEMBERS: usize = 10_000;
+const MAX_NESTING: usize = 4; // sh -c "..." 를 따라가는 깊이
+
+/// 명령 하나가 바꿀 것으로 보이는 경로와, 경로를 알 수 없는 변경의 설명
+#[derive(Debug, Default)]
+pub struct Touched {
+    pub paths: Vec<PathBuf>,    // 절대 경로, 글롭은 현재 파일 기준으로 펼침
+    pub untracked: Vec<String>, // 예: "'make' may change files that are not backed up"
+}
+
+impl Touched {
+    fn add(&mut self, p: &str, base: &Path) {
+        for x in expand(p, base) {
+            push_unique(&mut self.paths, x);
+        }
+    }
+
+    fn note(&mut self, reason: String) {
+        if !self.untracked.contains(&reason) {
+            self.untracked.push(reason);
+        }
+    }
+}
+
+/// 명령 하나가 쓰거나 지울 것으로 보이는 경로.
+/// 파싱하지 못하면 에러 (빈 목록이면 "건드리는 경로 없음"으로 오해하므로).
+pub fn touched_paths(cmd: &str, cwd: &Path) -> Result<Touched, String> {
+    let script = scy_shell::parse(cmd)?;
+    let mut t = Touched::default();
+    collect(&script, &mut cwd.to_path_buf(), &mut t, 0);
+    Ok(t)
+}
+
+fn collect(script: &Script, base: &mut PathBuf, t: &mut Touched, depth: usize) {
+    script.visit_simple(&mut |sc| {
+        // > file, >> file, &> file (2>&1 같은 fd 복제는 제외)
+        for r in &sc.redirects {
+            if r.op.contains('>') && !r.op.ends_with('&') {
+                t.add(&r.target.text, base);
+            }
+        }
+
+        let raw = sc.argv();
+        let argv = unwrap_wrappers(&raw);
+        let Some(first) = argv.first() else { return };
+        let args = &argv[1..];
+        let prog = program_name(first);
+        let ops = operands(args, value_options(prog));
+
+        if let Some(src) = inner_script(prog, args) {
+            match scy_shell::parse(&src) {
+                Ok(inner) if depth < MAX_NESTING => collect(&inner, &mut base.clone(), t, depth + 1),
+                _ => t.note(format!("the {} script could not be analyzed", prog)),
+            }
+            return;
+        }
+
+        match prog {
+            "cd" => {
+                let to = ops.first().copied().unwrap_or("~");
+                *base = resolve(to, base);
+            }
+            "rm" | "unlink" | "rmdir" | "shred" | "truncate" | "touch" | "tee" | "mkdir" => {
+                for p in ops {
+                    t.add(p, base);
+                }
+            }
+            "chmod" | "chown" | "chgrp" => {
+                // 첫 피연산자는 모드/소유자
+                for p in ops.iter().skip(1) {
+                    t.add(p, base);
+                }
+            }
+            "mv" | "cp" | "install" | "ln" | "rsync" => {
+                if ops.len() < 2 {
+                    return;
+                }
+                let (srcs, dest) = ops.split_at(ops.len() - 1);
+                let dest = dest[0];
+                if prog == "mv" {
+                    for s in srcs {
+                        t.add(s, base);
+                    }
+                }
+                let dest_abs = resolve(dest, base);
+                if dest_abs.is_dir() {
+                    for s in srcs {
+                        for src in expand(s, base) {
+                            if let Some(name) = src.file_name() {
+                                push_unique(&mut t.paths, dest_abs.join(name));
+                            }
+                        }
+                    }
+                } else {
+                    t.add(dest, base);
+                }
+            }
+            "sed" | "perl" => {
+                if args.iter().any(|a| a.starts_with("-i") || a.starts_with("--in-place") || (a.starts_with("-p") && a.contains('i'))) {
+                    // -e/-f 가 없으면 첫 피연산자가 스크립트
+                    let has_script_opt = args.iter().any(|a| *a == "-e" || *a == "-f" || a.starts_with("--expression"));
+                    for p in ops.iter().skip(usize::from(!has_script_opt)) {
+                        t.add(p, base);
+                    }
+                } else if prog == "perl" {
+                    t.note("'perl' may change files that are not backed up".to_string());
+                }
+            }
+            "dd" => {
+                if let Some(of) = args.iter().find_map(|a| a.strip_prefix("of=")) {
+                    t.add(of, base);
+                }
+            }
+            "sort" => {
+                if let Some(o) = option_value(args, &["-o", "--output"]) {
+                    t.add(o, base);
+                }
+            }
+            "curl" => {
+                if let Some(o) = option_value(args, &["-o", "--output"]) {
+                    t.add(o, base);
+                } else if args.iter().any(|a| matches!(*a, "-O" | "--remote-name" | "--remote-name-all")) {
+                    for url in args.iter().filter(|a| a.contains("://")) {
+                        t.add(url_file_name(url), base);
+                    }
+                }
+            }
+            "wget" => match option_value(args, &["-O", "--output-document"]) {
+                Some("-") => {}
+                Some(o) => t.add(o, base),
+                None => {
+                    for url in args.iter().filter(|a| a.contains("://")) {
+                        t.add(url_file_name(url), base);
+                    }
+                }
+            },
+            "tar" => tar_paths(args, base, t),
+            "unzip" => unzip_paths(args, base, t),
+            "zip" => {
+                let mut files = ops.iter();
+                if let Some(archive) = files.next() {
+                    let has_ext = Path::new(archive).extension().is_some();
+                    t.add(&if has_ext { archive.to_string() } else { format!("{}.zip", archive) }, base);
+                }
+                // -m: 넣은 파일을 지움
+                if args.iter().any(|a| *a == "-m" || *a == "--move") {
+                    for f in files {
+                        t.add(f, base);
+                    }
+                }
+            }
+            "find" => {
+                let roots: Vec<&str> = args.iter().copied().take_while(|a| !a.starts_with(['-', '(', '!'])).collect();
+                let writes = args.contains(&"-delete")
+                    || find_exec_commands(args).iter().any(|exec| {
+                        let p = exec.first().map(|p| program_name(p)).unwrap_or_default();
+                        !READ_ONLY.contains(&p)
+                    });
+                // 무엇이 지워질지는 실행해봐야 알 수 있으므로 시작 디렉토리를 통째로
+                if writes {
+                    if roots.is_empty() {
+                        t.add(".", base);
+                    }
+                    for r in roots {
+                        t.add(r, base);
+                    }
+                }
+            }
+            "git" => git_paths(args, base, t),
+            _ => {
+                if let Some((suffix, decompress)) = compressor(prog) {
+                    compress_paths(args, suffix, decompress, base, t);
+                } else if !READ_ONLY.contains(&prog) {
+                    t.note(format!("'{}' may change files that are not backed up", prog));
+                }
+            }
+        }
+    });
+}
+
+fn option_value<'a>(args: &[&'a str], names: &[&str]) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(i, a)| {
+        if names.contains(a) {
+            return args.get(i + 1).copied();
+        }
+        names.iter().find_map(|n| a.strip_prefix(n).and_then(|v| v.strip_prefix('=')))
+    })
+}
+
+fn url_file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().filter(|n| !n.is_empty()).unwrap_or("index.html")
+}
+
+fn compressor(prog: &str) -> Option<(&'static str, bool)> {
+    COMPRESSORS.iter().find_map(|(c, d, suffix)| {
+        if prog == *c {
+            Some((*suffix, false))
+        } else if prog == *d {
+            Some((*suffix, true))
+        } else {
+            None
+        }
+    })
+}
+
+/// gzip a → a.gz (a 는 지워짐), gzip -d a.gz → a. -c/-l/-t 면 파일을 바꾸지 않음
+fn compress_paths(args: &[&str], suffix: &str, mut decompress: bool, base: &Path, t: &mut Touched) {
+    let mut files = Vec::new();
+    let mut only_operands = false;
+    for a in args {
+        if only_operands || !a.starts_with('-') || *a == "-" {
+            files.push(*a);
+        } else if *a == "--" {
+            only_operands = true;
+        } else if matches!(*a, "--stdout" | "--to-stdout" | "--list" | "--test") {
+            return;
+        } else if matches!(*a, "--decompress" | "--uncompress") {
+            decompress = true;
+        } else if !a.starts_with("--") {
+            let flags = &a[1..];
+            if flags.contains(['c', 'l', 't']) {
+                return;
+            }
+            decompress |= flags.contains('d');
+        }
+    }
+    for f in files.into_iter().filter(|f| *f != "-") {
+        t.add(f, base);
+        let out = if decompress {
+            match f.strip_suffix(suffix) {
+                Some(stem) => stem.to_string(),
+                None if suffix == ".gz" && f.ends_with(".tgz") => format!("{}.tar", &f[..f.len() - 4]),
+                None => continue,
+            }
+        } else {
+            format!("{}{}", f, suffix)
+        };
+        t.add(&out, base);
+    }
+}
+
+/// tar -c/-r/-u: 아카이브 파일, tar -x: 아카이브에 든 최상위 경로들 (tar -tf 로 확인)
+fn tar_paths(args: &[&str], base: &Path, t: &mut Touched) {
+    // 첫 인자는 "czf" 처럼 - 없이 쓸 수 있음
+    let mut flags = String::new();
+    let mut archive = None;
+    let mut dir = None;
+    let mut i = 0;
+    while i < args.len() {
+        let a = args[i];
+        let bundle = if i == 0 && !a.starts_with('-') { Some(a) } else { a.strip_prefix('-').filter(|b| !b.starts_with('-')) };
+        if let Some(b) = bundle {
+            flags.push_str(b);
+            // 값을 받는 짧은 옵션이 묶음 끝에 있으면 다음 인자가 값
+            match b.chars().last() {
+                Some('f') => {
+                    archive = args.get(i + 1).copied();
+                    i += 1;
+                }
+                Some('C') => {
+                    dir = args.get(i + 1).copied();
+                    i += 1;
+                }
+                _ => {}
+            }
+        } else if let Some(v) = a.strip_prefix("--file=") {
+            archive = Some(v);
+        } else if let Some(v) = a.strip_prefix("--directory=") {
+            dir = Some(v);
+        } else if a == "--file" || a == "--directory" {
+            let v = args.get(i + 1).copied();
+            if a == "--file" {
+                archive = v;
+            } else {
+                dir = v;
+            }
+            i += 1;
+        } else {
+            match a {
+                "--create" => flags.push('c'),
+                "--append" => flags.push('r'),
+                "--update" => flags.push('u'),
+                "--extract" | "--get" => flags.push('x'),
+                "--list" => flags.push('t'),
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    if args.contains(&"--remove-files") {
+        t.note("tar --remove-files deletes the archived files".to_string());
+    }
+    let archive = archive.filter(|a| *a != "-");
+    if flags.contains(['c', 'r', 'u']) {
+        match archive {
+            Some(a) => t.add(a, base),
+            None => t.note("tar writes an archive whose name is not known".to_string()),
+        }
+    } else if flags.contains('x') {
+        let dest = resolve(dir.unwrap_or("."), base);
+        let members = archive.and_then(|a| archive_members("tar", &["-tf", &resolve(a, base).to_string_lossy()]));
+        match members {
+            Some(m) => add_members(&m, &dest, t),
+            None => t.note("tar extracts files whose names could not be listed".to_string()),
+        }
+    }
+}
+
+/// unzip a.zip [-d dir]: 아카이브에 든 최상위 경로들. -l/-t/-v/-p/-Z 는 읽기만
+fn unzip_paths(args: &[&str], base: &Path, t: &mut Touched) {
+    let mut archive = None;
+    let mut dir = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i] {
+            "-d" => {
+                dir = args.get(i + 1).copied();
+                i += 1;
+            }
+            a if a.starts_with('-') && a[1..].contains(['l', 't', 'v', 'p', 'Z']) => return,
+            a if a.starts_with('-') => {}
+            a if archive.is_none() => archive = Some(a),
+            _ => {}
+        }
+        i += 1;
+    }
+    let dest = resolve(dir.unwrap_or("."), base);
+    let members = archive.and_then(|a| {
+        let mut path = resolve(a, base);
+        if !path.exists() && path.extension().is_none() {
+            path.set_extension("zip");
+        }
+        archive_members("unzip", &["-Z1", &path.to_string_lossy()])
+    });
+    match members {
+        Some(m) => add_members(&m, &dest, t),
+        None => t.note("unzip extracts files whose names could not be listed".to_string()),
+    }
+}
+
+/// 아카이브 목록 (한 줄에 하나). 프로그램이 없거나 실패하면 None
+fn archive_members(prog: &str, args: &[&str]) -> Option<Vec<String>> {
+    let out = std::process::Command::new(prog)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&out.stdout).lines().take(MAX_ARCHIVE_MEMBERS).map(str::to_string).collect())
+}
+
+/// 멤버의 최상위 경로만 기록 (디렉토리면 통째로 백업/삭제)
+fn add_members(members: &[String], dest: &Path, t: &mut Touched) {
+    for m in members {
+        let rel = m.trim_start_matches("./").trim_start_matches('/');
+        match rel.split('/').next() {
+            Some("..") => t.note(format!("archive member '{}' is outside the target directory", m)),
+            Some(top) if !top.is_empty() && top != "." => push_unique(&mut t.paths, dest.join(top)),
+            _ => {}
+        }
+    }
+}
+
+/// 작업 트리를 바꾸는 git 명령. checkout/restore/rm/mv 는 경로를, 나머지는 추적하지 못한 변경으로
+fn git_paths(args: &[&str], base: &Path, t: &mut Touched) {
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') {
+        i += if matches!(args[i], "-C" | "-c" | "--git-dir" | "--work-tree") { 2 } else { 1 };
+    }
+    let Some(sub) = args.get(i).copied() else { return };
+    let rest = &args[i + 1..];
+    let after_dashdash = || rest.iter().skip_while(|a| **a != "--").skip(1).copied().collect::<Vec<_>>();
+    match sub {
+        "checkout" if rest.contains(&"--") => {
+            for p in after_dashdash() {
+                t.add(p, base);
+            }
+        }
+        "restore" => {
+            // --staged 만 있으면 인덱스만 바뀜
+            if rest.iter().any(|a| *a == "--staged" || *a == "-S") && !rest.iter().any(|a| *a == "--worktree" || *a == "-W") {
+                return;
+            }
+            let paths = if rest.contains(&"--") { after_dashdash() } else { operands(rest, &["-s", "--source"]) };
+            for p in paths {
+                t.add(p, base);
+            }
+        }
+        "rm" if !rest.contains(&"--cached") => {
+            for p in operands(rest, &[]) {
+                t.add(p, base);
+            }
+        }
+        "mv" => {
+            for p in operands(rest, &[]) {
+                t.add(p, base);
+            }
+        }
+        "clone" => {
+            let ops = operands(rest, &["-b", "--branch", "-o", "--origin", "--depth", "--reference", "-c", "--config"]);
+            let dir = match ops.as_slice() {
+                [_, dir, ..] => dir.to_string(),
+                [url] => url_file_name(url.trim_end_matches('/')).trim_end_matches(".git").to_string(),
+                [] => return,
+            };
+            t.add(&dir, base);
+        }
+        "stash" if matches!(rest.first().copied(), Some("list" | "show")) => {}
+        "checkout" | "switch" | "reset" | "clean" | "pull" | "merge" | "rebase" | "cherry-pick" | "revert" | "stash" | "am" | "apply" => {
+            t.note(format!("'git {}' may change files in the work tree that are not backed up", sub));
+        }
+        _ => {}
+    }
+}
+
+fn push_unique(out: &mut Vec<PathBuf>, p: PathBuf) {
+    if !out.contains(&p) {
+        out.push(p);
+    }
+}
+
+/// 값을 따로 받는 옵션 (예: truncate -s 0)
+fn value_options(prog: &str) -> &'static [&'static str] {
+    match prog {
+        "truncate" => &["-s", "-r", "--size", "--reference"],
+        "touch" => &["-d", "-t", "-r", "--date", "--reference"],
+        "mkdir" => &["-m", "--mode"],
+        "install" => &["-m", "-o", "-g", "-S", "--mode", "--owner", "--group"],
+        "cp" | "mv" | "ln" => &["-S", "--suffix"],
+        "sed" => &["-e", "-f", "-l", "--expression", "--file"],
+        "perl" => &["-e", "-E", "-M", "-I"],
+        "shred" => &["-n", "-s", "--iterations", "--size"],
+        "rsync" => &["-e", "--rsh", "--exclude", "--include", "--filter", "-f"],
+        _ => &[],
+    }
+}
+
+/// 옵션이 아닌 인자 ("--" 뒤는 전부). value_opts 의 다음 인자는 옵션 값이므로 제외.
+fn operands<'a>(args: &[&'a str], value_opts: &[&str]) -> Vec<&'a str> {
+    let mut out = Vec::new();
+    let mut only_operands = false;
+    let mut skip_next = false;
+    for a in args {
+        if skip_next {
+            skip_next = false;
+        } else if only_operands {
+            out.push(*a);
+        } else if *a == "--" {
+            only_operands = true;
+        } else if value_opts.contains(a) {
+            skip_next = true;
+        } else if !a.starts_with('-') || *a == "-" {
+            out.push(*a);
+        }
+    }
+    out.retain(|a| *a != "-" && !a.is_empty());
+    out
+}
+
+/// 마지막 컴포넌트의 *, ? 만 펼침. 매칭이 없으면 빈 목록.
+fn expand(p: &str, base: &Path) -> Vec<PathBuf> {
+    if p.starts_with("/dev/") || p.contains('$') || p.contains('`') {
+        return Vec::new();
+    }
+    let abs = resolve(p, base);
+    let name = abs.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    if !name.contains('*') && !name.contains('?') {
+        return vec![abs];
+    }
+    let Some(dir) = abs.parent() else { return Vec::new() };
+    let Ok(rd) = fs::read_dir(dir) else { return Vec::new() };
+    let mut out: Vec<PathBuf> = rd
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|n| (name.starts_with('.') || !n.starts_with('.')) && glob_match(&name, n))
+        .map(|n| dir.join(n))
+        .collect();
+    out.sort();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(id: &str, created: u64, paths: &[&str]) -> Journal {
+        Journal {
+            id: id.to_string(),
+            created,
+            request: String::new(),
+            cwd: PathBuf::from("/w"),
+            cmd: Vec::new(),
+            entries: paths
+                .iter()
+                .map(|p| JournalEntry {
+                    step: 1,
+                    path: PathBuf::from(p),
+                    action: EntryAction::Backup,
+                    kind: Some(BackupKind::File),
+                    backup: None,
+                    link_target: None,
+                    reason: None,
+                })
+                .collect(),
+            unknown: Vec::new(),
+            undone: false,
+            dir: PathBuf::new(),
+            used_bytes: 0,
+            limits: BackupLimits::default(),
+        }
+    }
+
+    #[test]
+    fn unparsable_command_is_an_error() {
+        assert!(touched_paths("echo a)", Path::new("/w")).is_err());
+        assert_eq!(touched_paths("rm a.txt > log", Path::new("/w")).unwrap().paths, [PathBuf::from("/w/log"), PathBuf::from("/w/a.txt")]);
+    }
+
+    fn paths(cmd: &str, cwd: &Path) -> Vec<PathBuf> {
+        touched_paths(cmd, cwd).unwrap().paths
+    }
+
+    fn untracked(cmd: &str) -> Vec<String> {
+        touched_paths(cmd, Path::new("/w")).unwrap().untracked
+    }
+
+    #[test]
+    fn compressors_and_find_delete() {
+        let w = Path::new("/w");
+        assert_eq!(paths("gzip a.log", w), [w.join("a.log"), w.join("a.log.gz")]);
+        assert_eq!(paths("gunzip -f b.tar.gz", w), [w.join("b.tar.gz"), w.join("b.tar")]);
+        assert_eq!(paths("xz -d c.xz", w), [w.join("c.xz"), w.join("c")]);
+        assert_eq!(paths("gzip -c a.log > a.gz", w), [w.join("a.gz")]);
+        assert_eq!(paths("find build tmp -name '*.o' -delete", w), [w.join("build"), w.join("tmp")]);
+        assert_eq!(paths("find -name x -exec rm {} +", w), [w.to_path_buf()]);
+        assert!(paths("find . -name '*.rs' -exec grep -l foo {} +", w).is_empty());
+        assert_eq!(paths("sh -c 'cd sub && rm x'", w), [w.join("sub/x")]);
+    }
+
+    #[test]
+    fn git_work_tree_writes() {
+        let w = Path::new("/w");
+        assert_eq!(paths("git checkout -- a.rs b.rs", w), [w.join("a.rs"), w.join("b.rs")]);
+        assert_eq!(paths("git restore src/lib.rs", w), [w.join("src/lib.rs")]);
+        assert!(paths("git restore --staged src/lib.rs", w).is_empty());
+        assert!(paths("git rm --cached a", w).is_empty());
+        assert_eq!(paths("git clone https://example.com/x/repo.git", w), [w.join("repo")]);
+        assert_eq!(untracked("git clean -fd"), ["'git clean' may change files in the work tree that are not backed up"]);
+        assert!(untracked("git status && git log -1 && git stash list").is_empty());
+    }
+
+    #[test]
+    fn unmodeled_writers_are_untracked() {
+        assert_eq!(untracked("make install"), ["'make' may change files that are not backed up"]);
+        assert_eq!(untracked("python3 gen.py | tee out.txt").len(), 1);
+        assert!(untracked("ls -la | grep x | wc -l").is_empty());
+        assert!(untracked("tar tzf a.tgz").is_empty());
+        assert_eq!(untracked("tar xf missing.tar"), ["tar extracts files whose names could not be listed"]);
+    }
+
+    #[test]
+    fn archives_list_their_members() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+        let dir = env::temp_dir().join(format!("sconny-journal-test-{}-{}", std::process::id(), nanos));
+        fs::create_dir_all(dir.join("src/deep")).unwrap();
+        fs::write(dir.join("src/deep/a.txt"), "a").unwrap();
+        fs::write(dir.join("top.txt"), "t").unwrap();
+        let ok = |prog: &str, args: &[&str]| {
+            std::process::Command::new(prog).args(args).current_dir(&dir).output().is_ok_and(|o| o.status.success())
+        };
+
+        assert_eq!(paths("tar czf out.tgz src top.txt", &dir), [dir.join("out.tgz")]);
+        if ok("tar", &["czf", "out.tgz", "src", "top.txt"]) {
+            assert_eq!(paths("tar xzf out.tgz -C dest", &dir), [dir.join("dest/src"), dir.join("dest/top.txt")]);
+            assert_eq!(paths("tar --extract --file=out.tgz", &dir), [dir.join("src"), dir.join("top.txt")]);
+        }
+        assert_eq!(paths("zip -r out src", &dir), [dir.join("out.zip")]);
+        if ok("zip", &["-qr", "out.zip", "src", "top.txt"]) {
+            assert_eq!(paths("unzip -o out -d dest", &dir), [dir.join("dest/src"), dir.join("dest/top.txt")]);
+            assert!(paths("unzip -l out.zip", &dir).is_empty());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn newer_plans_on_same_paths_conflict() {
+        let old = journal("a", 1, &["/w/src"]);
+        let mut all = vec![
+            old.clone(),
+            journal("b", 2, &["/w/src/main.rs"]), // 하위 경로
+            journal("c", 3, &["/w/other"]),
+            journal("d", 4, &["/w/src", "/w/x"]),
+        ];
+        let ids = |all: &[Journal]| conflicts_in(&old, all).into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids(&all), ["d", "b"]);
+
+        all[3].undone = true;
+        assert_eq!(ids(&all), ["b"]);
+        // 가장 최근 계획은 충돌 없음
+        assert!(conflicts_in(&all[3], &all).is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+// scy_listing.rs
+// 현재 디렉토리의 파일 목록(이름, 종류, 크기)을 프롬프트에 넣는다 (env.list_cwd, 기본 꺼짐).
+// "a.txt, b.txt, c/ 압축해줘" 같은 요청에서 모델이 실제로 있는 파일을 보고 계획을 세우게 하려는 것.
+// 깊이와 항목 수를 제한하고, .gitignore 에 걸리는 것과 민감한 이름(.env, 키 파일 등)은 넣지 않는다.
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::scy_policy::glob_match;
+use crate::scy_preview::human_size;
+
+/// env.list_deny 기본값. 이름(basename)에 대한 glob
+pub const DEFAULT_DENY: [&str; 20] = [
+    ".git", ".env", ".env.*", "*.pem", "*.key", "*.p12", "*.pfx", "*.kdbx", "id_rsa*", "id_dsa*", "id_ecdsa*",
+    "id_ed25519*", ".ssh", ".gnupg", ".aws", ".netrc", ".npmrc", ".pypirc", "*credentials*", "*secret*",
+];
+
+const MAX_READ_PER_DIR: usize = 5000; // 한 디렉토리에서 읽을 최대 항목 (정렬 전)
+const MAX_COUNT: usize = 1000;       // 펼치지 않은 디렉토리의 항목 수는 여기까지만 셈
+
+#[derive(Debug, Clone)]
+pub struct ListingLimits<'a> {
+    pub depth: usize,       // 1: CWD 바로 아래만
+    pub max_entries: usize,
+    pub deny: &'a [String],
+}
+
+#[derive(Debug)]
+struct Entry {
+    rel: Vec<String>, // CWD 기준 경로 조각 (정렬 = 트리 순서)
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Kind {
+    File(u64),
+    Dir(Option<usize>), // 펼치지 않았으면 항목 수
+    Symlink(Option<PathBuf>),
+    Other,
+}
+
+/// .gitignore 한 줄
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    base: Vec<String>, // .gitignore 가 있는 디렉토리
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool, // '/' 가 있으면 base 기준 경로 전체와 비교
+}
+
+/// 프롬프트용 목록. 보여줄 것이 없으면 None.
+pub fn listing(root: &Path, limits: &ListingLimits) -> Option<String> {
+    if limits.depth == 0 || limits.max_entries == 0 {
+        return None;
+    }
+    let mut entries: Vec<Entry> = Vec::new();
+    let (mut ignored, mut denied, mut truncated) = (0usize, 0usize, 0usize);
+
+    // 너비 우선: 항목 수가 모자라도 얕은 것부터 채워짐
+    let mut queue: VecDeque<(Vec<String>, usize, Vec<IgnoreRule>)> = VecDeque::new();
+    queue.push_back((Vec::new(), 1, Vec::new()));
+    while let Some((dir, depth, mut rules)) = queue.pop_front() {
+        let path = join(root, &dir);
+        rules.extend(read_gitignore(&path, &dir));
+        let Ok(rd) = fs::read_dir(&path) else { continue };
+        let mut names: Vec<(String, fs::Metadata)> = rd
+            .filter_map(Result::ok)
+            .take(MAX_READ_PER_DIR)
+            .filter_map(|e| Some((e.file_name().into_string().ok()?, e.path().symlink_metadata().ok()?)))
+            .collect();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (name, meta) in names {
+            if limits.deny.iter().any(|p| glob_match(p, &name)) {
+                denied += 1;
+                continue;
+            }
+            let mut rel = dir.clone();
+            rel.push(name);
+            if is_ignored(&rules, &rel, meta.is_dir()) {
+                ignored += 1;
+                continue;
+            }
+            if entries.len() >= limits.max_entries {
+                truncated += 1;
+                continue;
+            }
+            let kind = if meta.file_type().is_symlink() {
+                Kind::Symlink(fs::read_link(join(root, &rel)).ok())
+            } else if meta.is_dir() {
+                if depth < limits.depth {
+                    queue.push_back((rel.clone(), depth + 1, rules.clone()));
+                    Kind::Dir(None)
+                } else {
+                    Kind::Dir(Some(count_dir(&join(root, &rel))))
+                }
+            } else if meta.is_file() {
+                Kind::File(meta.len())
+            } else {
+                Kind::Other
+            };
+            entries.push(Entry { rel, kind });
+        }
+    }
+
+    if entries.is_empty() && truncated == 0 {
+        return None;
+    }
+    entries.sort_by(|a, b| a.rel.cmp(&b.rel));
+
+    let mut lines = Vec::with_capacity(entries.len() + 2);
+    for e in &entries {
+        let indent = "  ".repeat(e.rel.len() - 1);
+        let path = e.rel.join("/");
+        lines.push(match &e.kind {
+            Kind::File(size) => format!("  {}{} ({})", indent, path, human_size(*size)),
+            Kind::Dir(None) => format!("  {}{}/", indent, path),
+            Kind::Dir(Some(n)) if *n >= MAX_COUNT => format!("  {}{}/ ({}+ entries, not listed)", indent, path, MAX_COUNT),
+            Kind::Dir(Some(1)) => format!("  {}{}/ (1 entry, not listed)", indent, path),
+            Kind::Dir(Some(n)) => format!("  {}{}/ ({} entries, not listed)", indent, path, n),
+            Kind::Symlink(Some(t)) => format!("  {}{} -> {}", indent, path, t.display()),
+            Kind::Symlink(None) => format!("  {}{} (symlink)", indent, path),
+            Kind::Other => format!("  {}{} (special file)", indent, path),
+        });
+    }
+    let mut omitted = Vec::new();
+    if truncated > 0 {
+        omitted.push(format!("{} more not shown, limit {}", truncated, limits.max_entries));
+    }
+    if ignored > 0 {
+        omitted.push(format!("{} ignored by .gitignore", ignored));
+    }
+    if denied > 0 {
+        omitted.push(format!("{} sensitive entries hidden", denied));
+    }
+    if !omitted.is_empty() {
+        lines.push(format!("  ({})", omitted.join(", ")));
+    }
+    Some(lines.join("\n"))
+}
+
+fn join(root: &Path, rel: &[String]) -> PathBuf {
+    rel.iter().fold(root.to_path_buf(), |p, c| p.join(c))
+}
+
+fn count_dir(path: &Path) -> usize {
+    fs::read_dir(path).map(|rd| rd.take(MAX_COUNT).count()).unwrap_or(0)
+}
+
+/// dir/.gitignore 읽기. 지원: 주석, '!', 끝의 '/', '/' 포함(기준 디렉토리에 고정), '**/'
+fn read_gitignore(path: &Path, dir: &[String]) -> Vec<IgnoreRule> {
+    let Ok(text) = fs::read_to_string(path.join(".gitignore")) else { return Vec::new() };
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            // "**/foo" 는 어느 깊이의 foo 든 (= '/' 없는 패턴)
+            let line = line.strip_prefix("**/").unwrap_or(line);
+            let anchored = line.contains('/');
+            let pattern = line.trim_start_matches('/').to_string();
+            (!pattern.is_empty()).then(|| IgnoreRule { base: dir.to_vec(), pattern, negated, dir_only, anchored })
+        })
+        .collect()
+}
+
+/// 나중 규칙이 우선 (하위 디렉토리의 .gitignore 가 뒤에 옴)
+fn is_ignored(rules: &[IgnoreRule], rel: &[String], is_dir: bool) -> bool {
+    let mut ignored = false;
+    for r in rules {
+        if (r.dir_only && !is_dir) || !rel.starts_with(&r.base) {
+            continue;
+        }
+        let sub = &rel[r.base.len()..];
+        let hit = if r.anchored {
+            glob_match(&r.pattern, &sub.join("/"))
+        } else {
+            sub.last().is_some_and(|name| glob_match(&r.pattern, name))
+        };
+        if hit {
+            ignored = !r.negated;
+        }
+    }
+    ignored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn rel(p: &str) -> Vec<String> {
+        p.split('/').map(str::to_string).collect()
+    }
+
+    fn temp_dir() -> PathBuf {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+        std::env::temp_dir().join(format!("sconny-listing-test-{}-{}", std::process::id(), nanos))
+    }
+
+    #[test]
+    fn gitignore_rules() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".gitignore"), "# comment\n*.log\n!keep.log\nbuild/\n/target\ndocs/*.tmp\n**/cache\n").unwrap();
+        let rules = read_gitignore(&dir, &[]);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(rules.len(), 6);
+
+        let ignored = |p: &str, is_dir: bool| is_ignored(&rules, &rel(p), is_dir);
+        assert!(ignored("a.log", false));
+        assert!(ignored("sub/a.log", false));
+        assert!(!ignored("keep.log", false)); // '!' 로 다시 포함
+        assert!(ignored("build", true));
+        assert!(!ignored("build", false)); // 끝의 '/' 는 디렉토리만
+        assert!(ignored("target", true));
+        assert!(!ignored("sub/target", true)); // '/' 로 시작하면 기준 디렉토리에 고정
+        assert!(ignored("docs/x.tmp", false));
+        assert!(!ignored("sub/docs/x.tmp", false));
+        assert!(ignored("a/b/cache", true));
+    }
+
+    #[test]
+    fn nested_gitignore_applies_below_its_directory() {
+        let rules = vec![
+            IgnoreRule { base: Vec::new(), pattern: "*.o".to_string(), negated: false, dir_only: false, anchored: false },
+            IgnoreRule { base: rel("vendor"), pattern: "lib.o".to_string(), negated: true, dir_only: false, anchored: false },
+        ];
+        assert!(is_ignored(&rules, &rel("lib.o"), false));
+        assert!(!is_ignored(&rules, &rel("vendor/lib.o"), false));
+        assert!(is_ignored(&rules, &rel("vendor/x.o"), false));
+    }
+
+    #[test]
+    fn listing_hides_denied_and_ignored_entries() {
+        let dir = temp_dir();
+        fs::create_dir_all(dir.join("src/deep")).unwrap();
+        fs::create_dir_all(dir.join(".ssh")).unwrap();
+        fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(dir.join("run.log"), "x").unwrap();
+        fs::write(dir.join(".env"), "TOKEN=1").unwrap();
+        fs::write(dir.join("aws_credentials.json"), "{}").unwrap();
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("src/lib.rs"), "").unwrap();
+        fs::write(dir.join("src/deep/a.rs"), "").unwrap();
+
+        let deny: Vec<String> = DEFAULT_DENY.iter().map(|s| s.to_string()).collect();
+        let text = listing(&dir, &ListingLimits { depth: 2, max_entries: 100, deny: &deny }).unwrap();
+        assert_eq!(
+            text,
+            "  .gitignore (6 B)\n  main.rs (12 B)\n  src/\n    src/deep/ (1 entry, not listed)\n    src/lib.rs (0 B)\n  (1 ignored by .gitignore, 3 sensitive entries hidden)"
+        );
+
+        let text = listing(&dir, &ListingLimits { depth: 1, max_entries: 2, deny: &deny }).unwrap();
+        assert!(text.ends_with("(1 more not shown, limit 2, 1 ignored by .gitignore, 3 sensitive entries hidden)"), "{}", text);
+        assert!(listing(&dir, &ListingLimits { depth: 0, max_entries: 2, deny: &deny }).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,341 @@
+// scy_output.rs
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::scy_analyzer::Finding;
+use crate::scy_executor::CommandPlan;
+use crate::scy_preview::ChangeDetail;
+use crate::scy_process::Stream;
+
+/// --output 형식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,   // 요청 하나당 JSON 객체 하나 (끝날 때 출력)
+    Ndjson, // 레코드마다 한 줄씩 바로 출력
+}
+
+impl OutputFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "human" | "text" => Some(OutputFormat::Human),
+            "json" => Some(OutputFormat::Json),
+            "ndjson" | "jsonl" => Some(OutputFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// 명령 하나의 실행 결과
+#[derive(Debug, Clone, Serialize)]
+pub struct StepRecord {
+    pub index: usize, // 1부터
+    pub cmd: String,
+    pub exit_code: Option<i32>, // 시그널로 종료되면 None
+    pub stdout: String, // 마지막 policy.tail_lines 줄
+    pub stderr: String,
+    pub duration_ms: u128,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool, // 앞부분이 잘렸는지
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool, // Ctrl+C
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub sandboxed: bool, // 샌드박스(복사본)에서 실행
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub on_failure: bool, // index 번 명령이 실패해서 실행한 on_failure 핸들러
+}
+
+impl StepRecord {
+    pub fn new(index: usize, cmd: &str, exit_code: Option<i32>, stdout: String, stderr: String, duration: Duration) -> Self {
+        Self { index, cmd: cmd.to_string(), exit_code, stdout, stderr, duration_ms: duration.as_millis(), truncated: false, timed_out: false, cancelled: false, sandboxed: false, on_failure: false }
+    }
+}
+
+/// 실행 요약 표의 한 줄
+#[derive(Debug, Clone, Serialize)]
+pub struct SummaryRow {
+    pub index: usize,
+    pub cmd: String,
+    pub status: &'static str, // ok | failed | timed_out | cancelled | error | skipped
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<&'static str>, // 핸들러를 실행했으면 그 결과 status
+}
+
+/// 요청 하나의 출력.
+/// - Human: 지금까지처럼 stdout에 사람용 텍스트
+/// - Json/Ndjson: stdout에는 구조화된 레코드만, 사람용 텍스트는 stderr
+pub struct Output {
+    format: OutputFormat,
+    request: String,
+    plan: Option<Value>,
+    analysis: Vec<Finding>,
+    steps: Vec<StepRecord>,
+    changes: Vec<ChangeDetail>,
+    summary: Vec<SummaryRow>,
+    journal: Option<String>,
+    repairs: u32,
+    confirmation: Option<&'static str>,
+    llm_outputs: Vec<String>, // 모델 응답 원문 (history 기록용, 출력하지 않음)
+    errors: Vec<String>,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat, request: &str) -> Self {
+        Self { format, request: request.to_string(), plan: None, analysis: Vec::new(), steps: Vec::new(), changes: Vec::new(), summary: Vec::new(), journal: None, repairs: 0, confirmation: None, llm_outputs: Vec::new(), errors: Vec::new() }
+    }
+
+    pub fn request(&self) -> &str {
+        &self.request
+    }
+
+    pub fn is_machine(&self) -> bool {
+        self.format != OutputFormat::Human
+    }
+
+    /// 사람용 텍스트 한 줄
+    pub fn say(&self, args: fmt::Arguments) {
+        if self.is_machine() {
+            eprintln!("{}", args);
+        } else {
+            println!("{}", args);
+        }
+    }
+
+    pub fn plan(&mut self, plan: &CommandPlan) {
+        let v = serde_json::to_value(plan).unwrap_or(Value::Null);
+        self.emit(json!({ "type": "plan", "plan": v }));
+        self.plan = Some(v);
+    }
+
+    /// 로컬 안전성 분석 결과 (없으면 기록 안 함)
+    pub fn analysis(&mut self, findings: &[Finding]) {
+        if findings.is_empty() {
+            return;
+        }
+        self.emit(json!({ "type": "analysis", "findings": findings }));
+        self.analysis = findings.to_vec();
+    }
+
+    /// 실행 중인 명령의 출력 한 줄
+    pub fn output_line(&self, step: usize, stream: Stream, prefix: &str, line: &str) {
+        match (self.format, stream) {
+            (OutputFormat::Human, Stream::Stdout) => println!("{}{}", prefix, line),
+            (OutputFormat::Ndjson, _) => {
+                let name = if stream == Stream::Stdout { "stdout" } else { "stderr" };
+                self.emit(json!({ "type": "output", "step": step, "stream": name, "line": line }));
+            }
+            _ => eprintln!("{}{}", prefix, line),
+        }
+    }
+
+    pub fn step(&mut self, step: StepRecord) {
+        if self.format == OutputFormat::Ndjson {
+            let mut v = serde_json::to_value(&step).unwrap_or(Value::Null);
+            v["type"] = json!("step");
+            self.emit(v);
+        }
+        self.steps.push(step);
+    }
+
+    /// 모델 응답 원문 (재요청/repair 포함, 받은 순서대로)
+    pub fn llm_output(&mut self, text: &str) {
+        self.llm_outputs.push(text.to_string());
+    }
+
+    pub fn llm_outputs(&self) -> &[String] {
+        &self.llm_outputs
+    }
+
+    /// 실행 확인 결과: yes | no | auto_yes (--yes) | not_required
+    pub fn confirmation(&mut self, decision: &'static str) {
+        self.confirmation = Some(decision);
+    }
+
+    pub fn confirmation_decision(&self) -> Option<&'static str> {
+        self.confirmation
+    }
+
+    pub fn journal_id(&self) -> Option<&str> {
+        self.journal.as_deref()
+    }
+
+    /// 기록된 계획 (Output::plan)
+    pub fn plan_value(&self) -> Option<&Value> {
+        self.plan.as_ref()
+    }
+
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    pub fn steps(&self) -> &[StepRecord] {
+        &self.steps
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// since 번째 기록 이후 처음 실패한 명령 (on_failure 핸들러 제외)
+    pub fn failed_step(&self, since: usize) -> Option<&StepRecord> {
+        self.steps
+            .iter()
+            .skip(since)
+            .find(|s| !s.on_failure && (s.exit_code != Some(0) || s.timed_out || s.cancelled))
+    }
+
+    /// 실패를 LLM 에 돌려보내 고친 계획을 요청함 (attempt: 1부터)
+    pub fn repair(&mut self, attempt: u32, step: usize) {
+        self.emit(json!({ "type": "repair", "attempt": attempt, "step": step }));
+        self.repairs = attempt;
+    }
+
+    /// 계획 실행이 끝난 뒤 step별 상태
+    pub fn summary(&mut self, rows: &[SummaryRow]) {
+        self.emit(json!({ "type": "summary", "steps": rows }));
+        self.summary = rows.to_vec();
+    }
+
+    /// --preview 로 본 파일시스템 변경
+    pub fn changes(&mut self, changes: &[ChangeDetail]) {
+        self.emit(json!({ "type": "changes", "changes": changes }));
+        self.changes = changes.to_vec();
+    }
+
+    /// undo 저널 id (`sconny undo <id>`)
+    pub fn journal(&mut self, id: &str) {
+        self.emit(json!({ "type": "journal", "id": id }));
+        self.journal = Some(id.to_string());
+    }
+
+    /// 에러 기록. Human 모드에서는 stderr에 그대로 출력.
+    pub fn error(&mut self, message: &str) {
+        match self.format {
+            OutputFormat::Human => eprintln!("{}", message),
+            _ => self.emit(json!({ "type": "error", "message": message })),
+        }
+        self.errors.push(message.to_string());
+    }
+
+    /// 마지막 레코드. status: ok | dry_run | sandboxed | declined | blocked | failed | error
+    pub fn finish(self, status: &str) {
+        match (self.format, self.final_record(status)) {
+            (OutputFormat::Json, Some(v)) => println!("{}", serde_json::to_string_pretty(&v).unwrap_or_default()),
+            (_, Some(v)) => println!("{}", v),
+            (_, None) => {}
+        }
+    }
+
+    /// finish 가 출력할 레코드 (Human 이면 None)
+    fn final_record(&self, status: &str) -> Option<Value> {
+        match self.format {
+            OutputFormat::Human => None,
+            OutputFormat::Ndjson => Some(json!({ "type": "done", "status": status })),
+            OutputFormat::Json => Some(json!({
+                    "request": self.request,
+                    "status": status,
+                    "plan": self.plan,
+                    "analysis": self.analysis,
+                    "steps": self.steps,
+                    "summary": self.summary,
+                    "changes": self.changes,
+                    "journal": self.journal,
+                    "repairs": self.repairs,
+                    "confirmation": self.confirmation,
+                "errors": self.errors,
+            })),
+        }
+    }
+
+    fn emit(&self, record: Value) {
+        if self.format == OutputFormat::Ndjson {
+            println!("{}", record);
+        }
+    }
+}
+
+/// `say!(out, "...", args)`: Output::say 단축
+macro_rules! say {
+    ($out:expr, $($arg:tt)*) => {
+        $out.say(format_args!($($arg)*))
+    };
+}
+pub(crate) use say;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(index: usize, exit_code: Option<i32>) -> StepRecord {
+        StepRecord::new(index, "false", exit_code, String::new(), "err\n".to_string(), Duration::from_millis(5))
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(OutputFormat::parse(" JSON "), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::parse("jsonl"), Some(OutputFormat::Ndjson));
+        assert_eq!(OutputFormat::parse("text"), Some(OutputFormat::Human));
+        assert_eq!(OutputFormat::parse("yaml"), None);
+    }
+
+    #[test]
+    fn step_record_omits_false_flags() {
+        let mut s = step(1, Some(0));
+        let v = serde_json::to_value(&s).unwrap();
+        assert_eq!(v["exit_code"], 0);
+        assert_eq!(v["duration_ms"], 5);
+        for flag in ["truncated", "timed_out", "cancelled", "sandboxed", "on_failure"] {
+            assert!(v.get(flag).is_none(), "{}", flag);
+        }
+        s.timed_out = true;
+        assert_eq!(serde_json::to_value(&s).unwrap()["timed_out"], true);
+    }
+
+    #[test]
+    fn failed_step_skips_handlers_and_earlier_attempts() {
+        let mut out = Output::new(OutputFormat::Json, "req");
+        out.step(step(1, Some(1)));
+        let mut handler = step(1, Some(2));
+        handler.on_failure = true;
+        out.step(handler);
+        out.step(step(2, None)); // 시그널로 종료
+        assert_eq!(out.failed_step(0).map(|s| s.index), Some(1));
+        assert_eq!(out.failed_step(1).map(|s| s.index), Some(2));
+        assert!(out.failed_step(3).is_none());
+    }
+
+    #[test]
+    fn final_records() {
+        assert!(Output::new(OutputFormat::Human, "req").final_record("ok").is_none());
+        assert_eq!(
+            Output::new(OutputFormat::Ndjson, "req").final_record("blocked").unwrap(),
+            json!({ "type": "done", "status": "blocked" })
+        );
+
+        let mut out = Output::new(OutputFormat::Json, "list files");
+        out.step(step(1, Some(1)));
+        out.summary(&[SummaryRow { index: 1, cmd: "false".to_string(), status: "failed", exit_code: Some(1), duration_ms: Some(5), on_failure: None }]);
+        out.repair(1, 1);
+        out.confirmation("auto_yes");
+        out.error("boom");
+        let v = out.final_record("failed").unwrap();
+        assert_eq!(v["request"], "list files");
+        assert_eq!(v["status"], "failed");
+        assert_eq!(v["plan"], Value::Null);
+        assert_eq!(v["steps"][0]["stderr"], "err\n");
+        assert_eq!(v["summary"][0]["status"], "failed");
+        assert!(v["summary"][0].get("on_failure").is_none());
+        assert_eq!(v["repairs"], 1);
+        assert_eq!(v["confirmation"], "auto_yes");
+        assert_eq!(v["errors"], json!(["boom"]));
+        assert_eq!(v["journal"], Value::Null);
+    }
+}
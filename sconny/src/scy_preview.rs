@@ -0,0 +1,317 @@
+// scy_preview.rs
+// --preview: 계획을 샌드박스(CWD 복사본)에서 먼저 실행하고,
+// 생성/수정/삭제된 파일을 크기와 텍스트 diff 로 보여준다.
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::scy_output::{say, Output};
+use crate::scy_sandbox::{FsChange, Sandbox};
+
+const CONTEXT_LINES: usize = 3;
+const MAX_DIFF_LINES: usize = 200;     // 파일 하나당 출력할 diff 최대 줄 수
+const MAX_DIFF_BYTES: u64 = 512 * 1024; // 이보다 큰 파일은 diff 생략
+const MAX_LCS_CELLS: usize = 4_000_000; // 줄 수 곱이 이보다 크면 diff 생략
+
+/// 변경 한 건의 상세
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeDetail {
+    pub kind: &'static str, // created | modified | deleted
+    pub path: String,
+    pub is_dir: bool,
+    pub size_before: Option<u64>,
+    pub size_after: Option<u64>,
+    pub binary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>, // unified diff (텍스트 파일만)
+}
+
+/// 샌드박스 실행 후 변경 목록과 diff 계산
+pub fn describe(sb: &Sandbox) -> Result<Vec<ChangeDetail>, String> {
+    let changes = sb.changes().map_err(|e| format!("cannot compare working directory: {}", e))?;
+    Ok(changes.iter().map(|c| detail(sb, c)).collect())
+}
+
+fn detail(sb: &Sandbox, change: &FsChange) -> ChangeDetail {
+    let (kind, rel) = match change {
+        FsChange::Created(p) => ("created", p),
+        FsChange::Modified(p) => ("modified", p),
+        FsChange::Deleted(p) => ("deleted", p),
+    };
+    let before = sb.cwd().join(rel);
+    let after = sb.workdir().join(rel);
+    let (old, new) = match change {
+        FsChange::Created(_) => (None, Some(after.as_path())),
+        FsChange::Modified(_) => (Some(before.as_path()), Some(after.as_path())),
+        FsChange::Deleted(_) => (Some(before.as_path()), None),
+    };
+
+    let is_dir = new.or(old).and_then(|p| fs::symlink_metadata(p).ok()).is_some_and(|m| m.is_dir());
+    let mut d = ChangeDetail {
+        kind,
+        path: rel.display().to_string(),
+        is_dir,
+        size_before: old.and_then(file_size),
+        size_after: new.and_then(file_size),
+        binary: false,
+        diff: None,
+    };
+    // 삭제는 목록만, 디렉토리는 diff 없음
+    if is_dir || new.is_none() {
+        return d;
+    }
+
+    let too_big = d.size_before.max(d.size_after).unwrap_or(0) > MAX_DIFF_BYTES;
+    let old_text = old.map(read_text).unwrap_or(Some(Some(String::new())));
+    let new_text = new.map(read_text).unwrap_or(Some(Some(String::new())));
+    match (old_text, new_text) {
+        (Some(Some(a)), Some(Some(b))) if !too_big => {
+            d.diff = unified_diff(&a, &b, &format!("a/{}", d.path), &format!("b/{}", d.path));
+        }
+        (Some(None), _) | (_, Some(None)) => d.binary = true,
+        _ => {}
+    }
+    d
+}
+
+fn file_size(p: &Path) -> Option<u64> {
+    fs::symlink_metadata(p).ok().filter(|m| m.is_file()).map(|m| m.len())
+}
+
+/// None: 읽기 실패(심볼릭 링크 등), Some(None): 바이너리
+fn read_text(p: &Path) -> Option<Option<String>> {
+    if !fs::symlink_metadata(p).ok()?.is_file() {
+        return None;
+    }
+    let bytes = fs::read(p).ok()?;
+    if bytes.contains(&0) {
+        return Some(None);
+    }
+    Some(String::from_utf8(bytes).ok())
+}
+
+pub fn print_changes(changes: &[ChangeDetail], out: &Output) {
+    if changes.is_empty() {
+        say!(out, "\n[preview] No filesystem changes in the working directory.");
+        return;
+    }
+    say!(out, "\n=== PREVIEW: changes in the working directory ===");
+    for c in changes {
+        let mark = match c.kind {
+            "created" => '+',
+            "deleted" => '-',
+            _ => '~',
+        };
+        let name = if c.is_dir { format!("{}/", c.path) } else { c.path.clone() };
+        let size = match (c.size_before, c.size_after) {
+            _ if c.is_dir => String::new(),
+            (Some(a), Some(b)) => format!(" ({} -> {})", human_size(a), human_size(b)),
+            (Some(a), None) | (None, Some(a)) => format!(" ({})", human_size(a)),
+            (None, None) => String::new(),
+        };
+        let binary = if c.binary { " [binary]" } else { "" };
+        say!(out, "  {} {} {}{}{}", mark, c.kind, name, size, binary);
+    }
+
+    for c in changes.iter().filter(|c| c.diff.is_some()) {
+        say!(out, "");
+        say!(out, "{}", c.diff.as_deref().unwrap_or_default().trim_end());
+    }
+}
+
+pub fn human_size(n: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if n < 1024 {
+        return format!("{} B", n);
+    }
+    let mut v = n as f64 / 1024.0;
+    let mut unit = 0;
+    while v >= 1024.0 && unit + 1 < UNITS.len() {
+        v /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", v, UNITS[unit])
+}
+
+// -------------------- unified diff --------------------
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Same,
+    Del,
+    Add,
+}
+
+/// 줄 단위 LCS 로 unified diff 생성. 같으면 None.
+fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> Option<String> {
+    if old == new {
+        return None;
+    }
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    if a.len().saturating_mul(b.len()) > MAX_LCS_CELLS {
+        out.push_str("(file too large to diff)\n");
+        return Some(out);
+    }
+
+    let ops = diff_ops(&a, &b);
+    // ops 인덱스 → (old 줄 번호, new 줄 번호)
+    let mut pos = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0, 0);
+    for (op, _) in &ops {
+        pos.push((i, j));
+        match op {
+            Op::Same => {
+                i += 1;
+                j += 1;
+            }
+            Op::Del => i += 1,
+            Op::Add => j += 1,
+        }
+    }
+
+    // 변경 주변 CONTEXT_LINES 줄씩 묶어서 hunk 로
+    let changed: Vec<usize> = ops.iter().enumerate().filter(|(_, (op, _))| *op != Op::Same).map(|(k, _)| k).collect();
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &k in &changed {
+        let start = k.saturating_sub(CONTEXT_LINES);
+        let end = (k + CONTEXT_LINES + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(h) if start <= h.1 => h.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut lines = 0;
+    for (start, end) in hunks {
+        let old_len = ops[start..end].iter().filter(|(op, _)| *op != Op::Add).count();
+        let new_len = ops[start..end].iter().filter(|(op, _)| *op != Op::Del).count();
+        let (oi, nj) = pos[start];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(oi, old_len),
+            hunk_range(nj, new_len)
+        ));
+        for (op, text) in &ops[start..end] {
+            if lines >= MAX_DIFF_LINES {
+                out.push_str(&format!("... (diff truncated after {} lines)\n", MAX_DIFF_LINES));
+                return Some(out);
+            }
+            let mark = match op {
+                Op::Same => ' ',
+                Op::Del => '-',
+                Op::Add => '+',
+            };
+            out.push(mark);
+            out.push_str(text);
+            out.push('\n');
+            lines += 1;
+        }
+    }
+    Some(out)
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    // unified diff 는 1부터, 빈 범위는 앞 줄 번호
+    let first = if len == 0 { start } else { start + 1 };
+    if len == 1 {
+        first.to_string()
+    } else {
+        format!("{},{}", first, len)
+    }
+}
+
+fn diff_ops<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(Op, &'a str)> {
+    // lcs[i][j] = a[i..], b[j..] 의 LCS 길이
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i] == b[j] {
+            ops.push((Op::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push((Op::Del, a[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Add, b[j]));
+            j += 1;
+        }
+    }
+    ops.extend(a[i..].iter().map(|l| (Op::Del, *l)));
+    ops.extend(b[j..].iter().map(|l| (Op::Add, *l)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(old: &str, new: &str) -> Option<String> {
+        unified_diff(old, new, "a/f", "b/f")
+    }
+
+    #[test]
+    fn unified_diff_hunks() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), None);
+        assert_eq!(diff("a\nb\nc\n", "a\nx\nc\n").unwrap(), "--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n");
+        // 빈 파일에서 새로 생긴 파일
+        assert_eq!(diff("", "x\ny\n").unwrap(), "--- a/f\n+++ b/f\n@@ -0,0 +1,2 @@\n+x\n+y\n");
+
+        // 문맥 3줄보다 멀리 떨어진 변경은 hunk 두 개
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new: String = (1..=20)
+            .map(|i| match i {
+                2 => "two\n".to_string(),
+                18 => "eighteen\n".to_string(),
+                _ => format!("{}\n", i),
+            })
+            .collect();
+        let d = diff(&old, &new).unwrap();
+        assert_eq!(d.matches("\n@@ ").count(), 2);
+        assert!(d.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n"));
+        assert!(d.contains("@@ -15,6 +15,6 @@\n 15\n 16\n 17\n-18\n+eighteen\n 19\n 20\n"));
+    }
+
+    #[test]
+    fn lcs_keeps_common_lines() {
+        let a = ["a", "b", "c", "d"];
+        let b = ["b", "c", "e", "d"];
+        let ops: Vec<(char, &str)> = diff_ops(&a, &b)
+            .into_iter()
+            .map(|(op, l)| (match op { Op::Same => ' ', Op::Del => '-', Op::Add => '+' }, l))
+            .collect();
+        assert_eq!(ops, [('-', "a"), (' ', "b"), (' ', "c"), ('+', "e"), (' ', "d")]);
+    }
+
+    #[test]
+    fn diff_limits() {
+        let old: String = (0..300).map(|i| format!("old {}\n", i)).collect();
+        let new: String = (0..300).map(|i| format!("new {}\n", i)).collect();
+        let d = diff(&old, &new).unwrap();
+        assert!(d.ends_with(&format!("... (diff truncated after {} lines)\n", MAX_DIFF_LINES)));
+        assert_eq!(d.lines().filter(|l| l.starts_with(['-', '+']) && !l.starts_with("---") && !l.starts_with("+++")).count(), MAX_DIFF_LINES);
+
+        // 줄 수 곱이 MAX_LCS_CELLS 를 넘으면 LCS 를 계산하지 않음
+        let big = "x\n".repeat(2001);
+        let d = diff(&big, &"y\n".repeat(2001)).unwrap();
+        assert_eq!(d, "--- a/f\n+++ b/f\n(file too large to diff)\n");
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
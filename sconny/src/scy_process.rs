@@ -0,0 +1,258 @@
+// scy_process.rs
+// 계획의 명령 하나를 실행하면서 stdout/stderr 를 줄 단위로 바로 넘겨준다.
+// 전체 출력은 모으지 않고 마지막 몇 줄(tail)만 남겨서 에러 보고/기록에 쓴다.
+// 명령은 자기 프로세스 그룹에서 실행하고, 타임아웃/Ctrl+C 때는 그룹 전체에 시그널을 보낸다.
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::scy_signal::{self, CancelScope};
+
+const MAX_LINE_BYTES: usize = 8 * 1024; // 개행 없이 이보다 길면 잘라서 한 줄로 넘김
+const POLL: Duration = Duration::from_millis(100);
+const KILL_GRACE: Duration = Duration::from_secs(2); // SIGTERM/SIGINT 후 SIGKILL 까지 기다리는 시간
+const PIPE_GRACE: Duration = Duration::from_millis(500); // 프로세스가 끝난 뒤 남은 출력을 기다리는 시간
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// 마지막 max_lines 줄만 보관
+#[derive(Debug, Clone)]
+pub struct Tail {
+    lines: VecDeque<String>,
+    max_lines: usize,
+    pub dropped: usize, // 버려진 앞쪽 줄 수
+}
+
+impl Tail {
+    pub fn new(max_lines: usize) -> Self {
+        Self { lines: VecDeque::new(), max_lines, dropped: 0 }
+    }
+
+    fn push(&mut self, line: &str) {
+        if self.max_lines == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.lines.len() == self.max_lines {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line.to_string());
+    }
+
+    /// 줄마다 개행을 붙인 텍스트
+    pub fn text(&self) -> String {
+        self.lines.iter().map(|l| format!("{}\n", l)).collect()
+    }
+}
+
+/// 명령이 어떻게 끝났는지
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ended {
+    Exited,
+    TimedOut,
+    Cancelled, // Ctrl+C
+}
+
+pub struct Finished {
+    pub ended: Ended,
+    pub exit_code: Option<i32>, // 시그널로 종료되면 None
+    pub stdout: Tail,
+    pub stderr: Tail,
+    pub duration: Duration,
+}
+
+/// argv 를 실행하고 출력 줄이 나올 때마다 on_line 호출 (호출하는 스레드에서).
+/// timeout: None 이면 제한 없음
+pub fn run_streaming(
+    argv: &[String],
+    timeout: Option<Duration>,
+    tail_lines: usize,
+    on_line: &mut dyn FnMut(Stream, &str),
+) -> io::Result<Finished> {
+    let (program, args) = argv.split_first().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+    let mut cmd = Command::new(program);
+    cmd.args(args).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    // 새 프로세스 그룹: 터미널의 Ctrl+C 는 sconny 만 받고, 전달은 직접 한다
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+    let _scope = CancelScope::begin();
+    let started = Instant::now();
+    let mut child = cmd.spawn()?;
+    let _group = scy_signal::ChildGroup::register(child.id());
+
+    let (tx, rx) = mpsc::channel::<(Stream, String)>();
+    let mut readers = Vec::new();
+    if let Some(out) = child.stdout.take() {
+        readers.push(spawn_reader(out, Stream::Stdout, tx.clone()));
+    }
+    if let Some(err) = child.stderr.take() {
+        readers.push(spawn_reader(err, Stream::Stderr, tx.clone()));
+    }
+    drop(tx);
+
+    let mut stdout = Tail::new(tail_lines);
+    let mut stderr = Tail::new(tail_lines);
+    let deadline = timeout.map(|t| started + t);
+    let mut ended = Ended::Exited;
+    let mut kill_at: Option<Instant> = None;
+    let mut pipes_open = true;
+    let mut exited: Option<(ExitStatus, Instant)> = None;
+
+    // 출력이 끝나고(두 reader 모두 EOF) 프로세스도 끝날 때까지.
+    // 백그라운드로 띄운 자식(`srv &`)이 파이프를 계속 잡고 있을 수 있으므로
+    // 직접 실행한 프로세스가 끝나면 PIPE_GRACE 동안만 남은 출력을 더 읽는다.
+    let status: ExitStatus = loop {
+        if pipes_open {
+            match rx.recv_timeout(POLL) {
+                Ok((stream, line)) => {
+                    on_line(stream, &line);
+                    match stream {
+                        Stream::Stdout => stdout.push(&line),
+                        Stream::Stderr => stderr.push(&line),
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => pipes_open = false,
+            }
+        } else if exited.is_none() {
+            thread::sleep(POLL);
+        }
+
+        let now = Instant::now();
+        if exited.is_none() {
+            if let Some(st) = child.try_wait()? {
+                exited = Some((st, now));
+            }
+        }
+        if let Some((st, at)) = exited {
+            if !pipes_open || now >= at + PIPE_GRACE {
+                break st;
+            }
+            continue;
+        }
+
+        if ended == Ended::Exited {
+            if deadline.is_some_and(|d| now >= d) {
+                ended = Ended::TimedOut;
+                signal_group(&mut child, Signal::Terminate);
+                kill_at = Some(now + KILL_GRACE);
+            } else if scy_signal::is_cancelled() {
+                ended = Ended::Cancelled;
+                signal_group(&mut child, Signal::Interrupt);
+                kill_at = Some(now + KILL_GRACE);
+            }
+        }
+        if kill_at.is_some_and(|k| now >= k) {
+            signal_group(&mut child, Signal::Kill);
+            kill_at = None;
+        }
+    };
+    // 파이프가 아직 열려 있으면 reader 는 기다리지 않음 (rx 가 없어지면 다음 줄에서 끝남)
+    if !pipes_open {
+        for r in readers {
+            let _ = r.join();
+        }
+    }
+
+    Ok(Finished { ended, exit_code: status.code(), stdout, stderr, duration: started.elapsed() })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Signal {
+    Interrupt,
+    Terminate,
+    Kill,
+}
+
+/// 명령의 프로세스 그룹 전체에 시그널 (Windows 는 프로세스만 종료)
+#[cfg(unix)]
+fn signal_group(child: &mut Child, sig: Signal) {
+    let sig = match sig {
+        Signal::Interrupt => libc::SIGINT,
+        Signal::Terminate => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+    // process_group(0) 이라 pgid == pid
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), sig);
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_group(child: &mut Child, _sig: Signal) {
+    let _ = child.kill();
+}
+
+fn spawn_reader<R: Read + Send + 'static>(pipe: R, stream: Stream, tx: mpsc::Sender<(Stream, String)>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf: Vec<u8> = Vec::new();
+        loop {
+            let chunk = match reader.fill_buf() {
+                Ok([]) => break,
+                Ok(c) => c,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            };
+            let (take, newline) = match chunk.iter().position(|b| *b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (chunk.len(), false),
+            };
+            buf.extend_from_slice(&chunk[..take]);
+            reader.consume(take);
+
+            if newline || buf.len() >= MAX_LINE_BYTES {
+                if tx.send((stream, decode_line(&buf))).is_err() {
+                    return;
+                }
+                buf.clear();
+            }
+        }
+        if !buf.is_empty() {
+            let _ = tx.send((stream, decode_line(&buf)));
+        }
+    })
+}
+
+fn decode_line(bytes: &[u8]) -> String {
+    let s = String::from_utf8_lossy(bytes);
+    s.trim_end_matches('\n').trim_end_matches('\r').to_string()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn run(script: &str, timeout: Option<Duration>) -> (Finished, Vec<String>) {
+        let argv = ["sh", "-c", script].map(String::from);
+        let mut lines = Vec::new();
+        let done = run_streaming(&argv, timeout, 10, &mut |_, l| lines.push(l.to_string())).unwrap();
+        (done, lines)
+    }
+
+    #[test]
+    fn background_child_does_not_hold_the_step() {
+        // sleep 이 stdout 파이프를 물려받아도 sh 가 끝나면 바로 끝나야 함
+        let (done, lines) = run("sleep 5 & echo started", Some(Duration::from_secs(3)));
+        assert_eq!(done.ended, Ended::Exited);
+        assert_eq!(done.exit_code, Some(0));
+        assert_eq!(lines, ["started"]);
+        assert!(done.duration < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn timeout_kills_the_group() {
+        let (done, _) = run("sleep 5", Some(Duration::from_millis(300)));
+        assert_eq!(done.ended, Ended::TimedOut);
+        assert!(done.duration < Duration::from_secs(3));
+    }
+}
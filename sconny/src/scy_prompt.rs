@@ -1,7 +1,13 @@
 // scy_prompt.rs
 use std::env;
+use std::path::Path;
 
+use crate::scy_output::StepRecord;
+use crate::scy_schema;
 use crate::scy_setting::{ScyOs, SconnySetting};
+use crate::scy_git;
+use crate::scy_listing::{self, ListingLimits};
+use crate::scy_tools;
 
 #[derive(Debug, Clone)]
 pub struct Prompt {
@@ -43,6 +49,54 @@ pub fn build_prompt(setting: &SconnySetting, user_request: &str) -> Result<Promp
         .clone()
         .unwrap_or_else(|| "Unknown".to_string());
 
+    // 설치된 도구 (env.tools 가 비어 있으면 생략)
+    let mut tools = scy_tools::inventory(&setting.env.tools).prompt_lines().join("\n");
+    if !tools.is_empty() {
+        tools.push_str(concat!(
+            "\n- Only use installed tools. If a needed tool is NOT installed, use an installed alternative\n",
+            "  (e.g. tar instead of zip) or mention how to install it in notes.\n"
+        ));
+    }
+
+    // CWD 파일 목록 (env.list_cwd 를 켠 경우만)
+    let listing_limits = ListingLimits {
+        depth: setting.env.list_depth,
+        max_entries: setting.env.list_max_entries,
+        deny: &setting.env.list_deny,
+    };
+    let listing = setting
+        .env
+        .list_cwd
+        .then(|| scy_listing::listing(Path::new(&cwd), &listing_limits))
+        .flatten()
+        .map(|l| {
+            format!(
+                concat!(
+                    "- Files in CWD (depth {}, names relative to CWD):\n{}\n",
+                    "- Use the real file names above. If the user mentions a file that is not listed, say so in assumptions.\n"
+                ),
+                setting.env.list_depth, l
+            )
+        })
+        .unwrap_or_default();
+
+    // git 저장소 정보 (env.git)
+    let git = setting
+        .env
+        .git
+        .then(|| scy_git::detect(Path::new(&cwd), setting.env.git_commits))
+        .flatten()
+        .map(|g| {
+            let mut s = g.prompt_lines().join("\n");
+            s.push_str(concat!(
+                "\n- Git commands that rewrite history or discard work (reset --hard, rebase, commit --amend, push --force,\n",
+                "  clean -fd, branch -D, stash drop) are HIGH risk: prefer safe alternatives (git revert, --force-with-lease),\n",
+                "  suggest a backup branch first when history is rewritten, and set needs_confirmation=true.\n"
+            ));
+            s
+        })
+        .unwrap_or_default();
+
     // 정책
     let dry_run = setting.policy.dry_run;
     let require_confirmation = setting.policy.require_confirmation;
@@ -56,7 +110,7 @@ pub fn build_prompt(setting: &SconnySetting, user_request: &str) -> Result<Promp
         .unwrap_or_else(|| "unspecified".to_string());
 
     // system prompt: "명령 생성기" 역할과 안전 제약을 강하게
-    let system = format!(
+    let mut system = format!(
         concat!(
             "You are Sconny, a safe shell-command generator for a local console assistant.\n",
             "\n",
@@ -103,11 +157,19 @@ pub fn build_prompt(setting: &SconnySetting, user_request: &str) -> Result<Promp
             "- Version: {version}\n",
             "- Shell: {shell}\n",
             "- CWD: {cwd}\n",
+            "{tools}",
+            "{listing}",
+            "{git}",
             "\n",
             "Execution policy:\n",
             "- dry_run: {dry_run}\n",
             "- require_confirmation: {require_confirmation}\n",
-            "- timeout_sec: {timeout_sec}\n",
+            "- timeout_sec: {timeout_sec} (per command)\n",
+            "- For long-running commands (builds, large downloads) you may add an optional \"timeout_sec\" array aligned with cmd,\n",
+            "  e.g. \"timeout_sec\": [null, 600] (null = default, at most {max_timeout_sec}).\n",
+            "- on_error: {on_error} (what happens to the remaining commands when one fails)\n",
+            "- You may add an optional \"on_failure\" array aligned with cmd with a cleanup command to run if that command fails,\n",
+            "  e.g. \"on_failure\": [null, \"rm -f partial.tar\"] (null = none).\n",
             "\n",
             "LLM config (for logging):\n",
             "- llm_service: {llm_service}\n",
@@ -118,13 +180,32 @@ pub fn build_prompt(setting: &SconnySetting, user_request: &str) -> Result<Promp
         version = version,
         shell = shell,
         cwd = cwd,
+        tools = tools,
+        listing = listing,
+        git = git,
         dry_run = dry_run,
         require_confirmation = require_confirmation,
         timeout_sec = timeout_sec,
+        max_timeout_sec = setting.policy.max_timeout_sec,
+        on_error = setting.policy.on_error.name(),
         llm_service = llm_service,
         model = model
     );
 
+    // allow/deny 규칙: 어차피 실행 전에 차단되므로 처음부터 지키도록 알려줌
+    let rules = &setting.policy.rules;
+    if !rules.is_empty() {
+        system.push_str("\nCommand policy (commands violating these are blocked):\n");
+        for (kind, label) in [("allow", "only allowed"), ("deny", "denied")] {
+            for field in ["programs", "args", "paths"] {
+                let list = rules.list(kind, field);
+                if !list.is_empty() {
+                    system.push_str(&format!("- {} {}: {}\n", label, field, list.join(", ")));
+                }
+            }
+        }
+    }
+
     // user prompt: 사용자의 자연어 요청을 그대로
     // 추가로 "명령 후보가 여러개면 어떤 기준으로 선택" 같은 힌트를 더 줄 수도 있음
     let user = format!(
@@ -142,3 +223,75 @@ pub fn build_prompt(setting: &SconnySetting, user_request: &str) -> Result<Promp
 
     Ok(Prompt { system, user })
 }
+
+/// 계획의 명령이 실패했을 때 고친 계획을 요청하는 프롬프트.
+/// 원래 요청 프롬프트 + 실행한 계획 + 실패한 명령/종료 코드/stderr 마지막 부분.
+pub fn build_repair_prompt(request: &Prompt, previous_cmd: &[String], failed: &StepRecord) -> Prompt {
+    let mut prompt = request.clone();
+
+    let status = if failed.timed_out {
+        "timed out".to_string()
+    } else {
+        match failed.exit_code {
+            Some(code) => format!("exit code {}", code),
+            None => "killed by a signal".to_string(),
+        }
+    };
+    let tail = |s: &str| if s.trim().is_empty() { "(empty)".to_string() } else { s.trim_end().to_string() };
+
+    prompt.user.push_str("\nA previous plan for this request failed.\n");
+    prompt.user.push_str("Previous plan:\n");
+    for (i, c) in previous_cmd.iter().enumerate() {
+        prompt.user.push_str(&format!("{}. {}\n", i + 1, c));
+    }
+    prompt.user.push_str(&format!(
+        concat!(
+            "\n",
+            "Command {index} failed ({status}):\n",
+            "{cmd}\n",
+            "--- stderr (last lines) ---\n",
+            "{stderr}\n",
+            "--- stdout (last lines) ---\n",
+            "{stdout}\n",
+            "\n",
+            "Return a corrected plan in the same JSON format.\n",
+            "- Commands before {index} already ran; do not repeat their side effects unless needed.\n",
+            "- Fix the cause of the error (wrong flags, missing paths, unavailable tools) instead of retrying the same command.\n",
+            "- If the request cannot be done on this system, return an empty cmd array and explain why.\n"
+        ),
+        index = failed.index,
+        status = status,
+        cmd = failed.cmd,
+        stderr = tail(&failed.stderr),
+        stdout = tail(&failed.stdout),
+    ));
+    prompt
+}
+
+/// 응답이 계획 스키마에 맞지 않았을 때 한 번 더 요청하는 프롬프트 (에러 + JSON Schema)
+pub fn build_reask_prompt(prompt: &Prompt, bad_output: &str, error: &str) -> Prompt {
+    const MAX_ECHO: usize = 2000;
+    let mut echo: String = bad_output.trim().chars().take(MAX_ECHO).collect();
+    if bad_output.trim().chars().count() > MAX_ECHO {
+        echo.push_str("\n...(truncated)");
+    }
+    let schema = serde_json::to_string_pretty(&scy_schema::plan_schema()).unwrap_or_default();
+
+    let mut user = prompt.user.clone();
+    user.push_str(&format!(
+        concat!(
+            "\nYour previous reply could not be used:\n",
+            "{error}\n",
+            "\n",
+            "Previous reply:\n",
+            "{echo}\n",
+            "\n",
+            "Reply again with ONLY a JSON object that conforms to this JSON Schema:\n",
+            "{schema}\n"
+        ),
+        error = error,
+        echo = echo,
+        schema = schema,
+    ));
+    Prompt { system: prompt.system.clone(), user }
+}
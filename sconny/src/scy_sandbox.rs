@@ -0,0 +1,494 @@
+// scy_sandbox.rs
+// 계획을 격리된 환경에서 실행 (Linux 전용, opt-in).
+// - 루트 파일시스템은 읽기 전용
+// - CWD 는 임시 복사본을 덮어씌워서(쓰기 가능) 실제 디렉토리는 건드리지 않음
+// - 네트워크 없음
+// bubblewrap(bwrap)이 있으면 사용하고, 없으면 util-linux unshare + mount 로 같은 구성을 만든다.
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxMode {
+    Off,
+    Auto,      // bwrap → unshare 순서로 사용 가능한 것
+    Bwrap,
+    Unshare,
+}
+
+impl SandboxMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "off" | "false" | "no" | "0" => Some(SandboxMode::Off),
+            "auto" | "on" | "true" | "yes" | "1" => Some(SandboxMode::Auto),
+            "bwrap" | "bubblewrap" => Some(SandboxMode::Bwrap),
+            "unshare" => Some(SandboxMode::Unshare),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SandboxMode::Off => "off",
+            SandboxMode::Auto => "auto",
+            SandboxMode::Bwrap => "bwrap",
+            SandboxMode::Unshare => "unshare",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SandboxMode {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        SandboxMode::parse(&s)
+            .ok_or_else(|| de::Error::custom(format!("invalid sandbox mode '{}' (expected off, auto, bwrap or unshare)", s)))
+    }
+}
+
+/// 실제로 사용할 격리 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Bwrap,
+    Unshare,
+}
+
+/// 샌드박스 한 번(계획 하나) 분량. drop 되면 임시 디렉토리 삭제.
+pub struct Sandbox {
+    backend: Backend,
+    cwd: PathBuf,        // 실제 작업 디렉토리 (샌드박스 안에서도 같은 경로로 보임)
+    root: PathBuf,       // 임시 디렉토리
+    workdir: PathBuf,    // root/work: CWD 복사본
+    tmpdir: PathBuf,     // root/tmp: 샌드박스 안의 TMPDIR
+    devdir: PathBuf,     // root/dev: unshare 방식에서 /dev 대신 쓰는 최소 장치 목록
+}
+
+/// 파일시스템 변경 한 건 (CWD 기준 상대 경로)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsChange {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Deleted(PathBuf),
+}
+
+impl fmt::Display for FsChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsChange::Created(p) => write!(f, "+ {}", p.display()),
+            FsChange::Modified(p) => write!(f, "~ {}", p.display()),
+            FsChange::Deleted(p) => write!(f, "- {}", p.display()),
+        }
+    }
+}
+
+impl Sandbox {
+    /// CWD 를 임시 디렉토리로 복사하고 격리 방식을 고른다.
+    /// max_bytes: 복사할 CWD 전체 크기 제한
+    pub fn prepare(mode: SandboxMode, max_bytes: u64) -> Result<Sandbox, String> {
+        if !cfg!(target_os = "linux") {
+            return Err("sandbox mode is only supported on Linux".to_string());
+        }
+        let backend = pick_backend(mode)?;
+        let cwd = env::current_dir().map_err(|e| format!("cannot read current directory: {}", e))?;
+
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let root = env::temp_dir().join(format!("sconny-sandbox-{}-{}", std::process::id(), nanos));
+        let sb = Sandbox {
+            backend,
+            workdir: root.join("work"),
+            tmpdir: root.join("tmp"),
+            devdir: root.join("dev"),
+            root,
+            cwd,
+        };
+
+        for d in [&sb.workdir, &sb.tmpdir, &sb.devdir] {
+            fs::create_dir_all(d).map_err(|e| format!("cannot create {}: {}", d.display(), e))?;
+        }
+
+        let size = tree_size(&sb.cwd, max_bytes).map_err(|e| format!("cannot scan {}: {}", sb.cwd.display(), e))?;
+        if size > max_bytes {
+            return Err(format!(
+                "working directory is larger than policy.sandbox_max_mb ({} MB); refusing to copy it into the sandbox",
+                max_bytes / (1024 * 1024)
+            ));
+        }
+        copy_tree(&sb.cwd, &sb.workdir).map_err(|e| format!("cannot copy working directory into sandbox: {}", e))?;
+
+        // 마운트 구성이 되는지 먼저 확인 (읽기 전용으로 못 만든 마운트가 있으면 여기서 중단)
+        if sb.backend == Backend::Unshare {
+            let argv = sb.wrap(&["true".to_string()]);
+            let out = Command::new(&argv[0])
+                .args(&argv[1..])
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|e| format!("cannot start sandbox: {}", e))?;
+            if !out.status.success() {
+                let err = String::from_utf8_lossy(&out.stderr);
+                return Err(format!("sandbox setup failed: {}", err.trim()));
+            }
+        }
+
+        Ok(sb)
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        match self.backend {
+            Backend::Bwrap => "bwrap",
+            Backend::Unshare => "unshare",
+        }
+    }
+
+    /// 실제 작업 디렉토리
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// 샌드박스 안에서 CWD 로 보이는 복사본 경로
+    pub fn workdir(&self) -> &Path {
+        &self.workdir
+    }
+
+    /// argv(예: ["sh", "-lc", cmd])를 샌드박스 안에서 실행하는 argv로 감쌈
+    pub fn wrap(&self, argv: &[String]) -> Vec<String> {
+        let p = |p: &Path| p.to_string_lossy().into_owned();
+        let mut out: Vec<String> = match self.backend {
+            Backend::Bwrap => vec![
+                "bwrap".into(), "--ro-bind".into(), "/".into(), "/".into(),
+                "--dev".into(), "/dev".into(), "--proc".into(), "/proc".into(),
+                "--bind".into(), p(&self.workdir), p(&self.cwd),
+                "--bind".into(), p(&self.tmpdir), p(&self.tmpdir),
+                "--unshare-all".into(), "--die-with-parent".into(),
+                "--setenv".into(), "TMPDIR".into(), p(&self.tmpdir),
+                "--chdir".into(), p(&self.cwd),
+                "--".into(),
+            ],
+            Backend::Unshare => vec![
+                "unshare".into(), "--user".into(), "--map-root-user".into(), "--mount".into(),
+                "--net".into(), "--pid".into(), "--fork".into(), "--kill-child".into(),
+                "env".into(), format!("TMPDIR={}", p(&self.tmpdir)),
+                "sh".into(), "-c".into(), UNSHARE_SETUP.into(), "sconny-sandbox".into(),
+                p(&self.workdir), p(&self.cwd), p(&self.tmpdir), p(&self.devdir),
+            ],
+        };
+        out.extend(argv.iter().cloned());
+        out
+    }
+
+    /// 실행 후 복사본과 실제 CWD 비교
+    pub fn changes(&self) -> io::Result<Vec<FsChange>> {
+        let before = snapshot(&self.cwd)?;
+        let after = snapshot(&self.workdir)?;
+        let mut out = Vec::new();
+
+        for (rel, a) in &after {
+            match before.get(rel) {
+                None => out.push(FsChange::Created(rel.clone())),
+                Some(b) if b != a || (a.is_file() && !same_contents(&self.cwd.join(rel), &self.workdir.join(rel))?) => {
+                    out.push(FsChange::Modified(rel.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for rel in before.keys() {
+            if !after.contains_key(rel) {
+                out.push(FsChange::Deleted(rel.clone()));
+            }
+        }
+        out.sort_by(|a, b| change_path(a).cmp(change_path(b)));
+        Ok(out)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = make_writable(&self.root);
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn change_path(c: &FsChange) -> &Path {
+    match c {
+        FsChange::Created(p) | FsChange::Modified(p) | FsChange::Deleted(p) => p,
+    }
+}
+
+// unshare 방식 초기화 스크립트. $1=복사본 $2=CWD $3=TMPDIR $4=최소 /dev, 나머지는 실행할 argv.
+// 1) 복사본을 CWD 위에, TMPDIR 을 자기 자신 위에 bind (쓰기 가능)
+// 2) /dev 는 null/zero/random 등만 있는 디렉토리로 교체, /proc 은 새 pid 네임스페이스 기준으로
+// 3) 나머지 마운트는 전부 읽기 전용으로 다시 마운트. user namespace 에서는 nosuid/nodev/noexec/atime
+//    플래그가 잠겨 있어서 그대로 유지해야 remount 가 됨. 하나라도 실패하면 명령을 실행하지 않고 종료.
+const UNSHARE_SETUP: &str = r#"set -e
+work="$1"; cwd="$2"; tmp="$3"; dev="$4"; shift 4
+for d in null zero full random urandom tty; do
+  if [ -e "/dev/$d" ]; then touch "$dev/$d"; mount --bind "/dev/$d" "$dev/$d"; fi
+done
+mount --bind "$work" "$cwd"
+mount --bind "$tmp" "$tmp"
+mount --rbind "$dev" /dev
+mount -t proc proc /proc
+mounts=$(cat /proc/self/mounts)
+while read -r _src mp _fstype opts _rest; do
+  mp=$(printf '%b' "$mp")
+  case "$mp" in
+    /proc|/proc/*|/dev|/dev/*|/sys|/sys/*) continue;;
+    "$cwd"|"$cwd"/*|"$tmp"|"$tmp"/*) continue;;
+  esac
+  keep=""
+  for o in $(printf '%s' "$opts" | tr ',' ' '); do
+    case "$o" in nosuid|nodev|noexec|noatime|nodiratime|relatime|strictatime) keep="$keep,$o";; esac
+  done
+  if ! err=$(mount -o "remount,bind,ro$keep" "$mp" 2>&1); then
+    echo "sconny-sandbox: cannot make $mp read-only: $err" >&2
+    exit 125
+  fi
+done <<EOF
+$mounts
+EOF
+cd "$cwd"
+exec "$@""#;
+
+fn pick_backend(mode: SandboxMode) -> Result<Backend, String> {
+    match mode {
+        SandboxMode::Off => Err("sandbox is off".to_string()),
+        SandboxMode::Bwrap => {
+            if has_bwrap() {
+                Ok(Backend::Bwrap)
+            } else {
+                Err("sandbox = \"bwrap\" but bwrap was not found in PATH".to_string())
+            }
+        }
+        SandboxMode::Unshare => {
+            if has_unshare() {
+                Ok(Backend::Unshare)
+            } else {
+                Err("sandbox = \"unshare\" but user namespaces are not available (unshare --user failed)".to_string())
+            }
+        }
+        SandboxMode::Auto => {
+            if has_bwrap() {
+                Ok(Backend::Bwrap)
+            } else if has_unshare() {
+                Ok(Backend::Unshare)
+            } else {
+                Err("no sandbox available: install bubblewrap (bwrap) or enable unprivileged user namespaces".to_string())
+            }
+        }
+    }
+}
+
+fn has_bwrap() -> bool {
+    Command::new("bwrap")
+        .args(["--ro-bind", "/", "/", "--unshare-all", "true"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+fn has_unshare() -> bool {
+    Command::new("unshare")
+        .args(["--user", "--map-root-user", "--mount", "--net", "true"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+// -------------------- file tree helpers --------------------
+
+/// limit 을 넘으면 더 세지 않고 바로 리턴
+pub fn tree_size(dir: &Path, limit: u64) -> io::Result<u64> {
+    let mut total = 0;
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        for entry in fs::read_dir(&d)? {
+            let entry = entry?;
+            let meta = entry.file_type()?;
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else if meta.is_file() {
+                total += entry.metadata()?.len();
+                if total > limit {
+                    return Ok(total);
+                }
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// 심볼릭 링크는 따라가지 않고 링크로 복사. 소켓/장치 파일은 건너뜀.
+pub fn copy_tree(src: &Path, dst: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let ty = entry.file_type()?;
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        if ty.is_dir() {
+            fs::create_dir_all(&to)?;
+            copy_tree(&from, &to)?;
+            fs::set_permissions(&to, entry.metadata()?.permissions())?;
+        } else if ty.is_file() {
+            fs::copy(&from, &to)?;
+        } else if ty.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(fs::read_link(&from)?, &to)?;
+        }
+    }
+    Ok(())
+}
+
+/// 읽기 전용 디렉토리가 있어도 지울 수 있게
+fn make_writable(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let mut perm = entry.metadata()?.permissions();
+            #[allow(clippy::permissions_set_readonly_false)]
+            perm.set_readonly(false);
+            let _ = fs::set_permissions(entry.path(), perm);
+            make_writable(&entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    File { size: u64 },
+    Dir,
+    Symlink(PathBuf),
+}
+
+impl EntryKind {
+    fn is_file(&self) -> bool {
+        matches!(self, EntryKind::File { .. })
+    }
+}
+
+pub fn snapshot(root: &Path) -> io::Result<BTreeMap<PathBuf, EntryKind>> {
+    let mut out = BTreeMap::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        for entry in fs::read_dir(&d)? {
+            let entry = entry?;
+            let path = entry.path();
+            let rel = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            let ty = entry.file_type()?;
+            if ty.is_dir() {
+                out.insert(rel, EntryKind::Dir);
+                stack.push(path);
+            } else if ty.is_file() {
+                out.insert(rel, EntryKind::File { size: entry.metadata()?.len() });
+            } else if ty.is_symlink() {
+                out.insert(rel, EntryKind::Symlink(fs::read_link(&path)?));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    Ok(fs::read(a)? == fs::read(b)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 격리 없이 경로만 채운 샌드박스 (root 는 drop 때 지워짐)
+    fn sandbox(backend: Backend, root: &Path, cwd: &Path) -> Sandbox {
+        Sandbox {
+            backend,
+            cwd: cwd.to_path_buf(),
+            root: root.to_path_buf(),
+            workdir: root.join("work"),
+            tmpdir: root.join("tmp"),
+            devdir: root.join("dev"),
+        }
+    }
+
+    fn pos(argv: &[String], window: &[&str]) -> Option<usize> {
+        argv.windows(window.len()).position(|w| w.iter().zip(window).all(|(a, b)| a == b))
+    }
+
+    #[test]
+    fn mode_names() {
+        assert_eq!(SandboxMode::parse("Yes"), Some(SandboxMode::Auto));
+        assert_eq!(SandboxMode::parse("bubblewrap"), Some(SandboxMode::Bwrap));
+        assert_eq!(SandboxMode::parse("0"), Some(SandboxMode::Off));
+        assert_eq!(SandboxMode::parse("docker"), None);
+        assert_eq!(SandboxMode::Unshare.name(), "unshare");
+    }
+
+    #[test]
+    fn bwrap_arguments() {
+        let sb = sandbox(Backend::Bwrap, Path::new("/nonexistent/sb"), Path::new("/home/u/proj"));
+        let argv = sb.wrap(&["sh".to_string(), "-lc".to_string(), "ls".to_string()]);
+        assert_eq!(argv[0], "bwrap");
+        assert!(pos(&argv, &["--ro-bind", "/", "/"]).is_some());
+        assert!(pos(&argv, &["--bind", "/nonexistent/sb/work", "/home/u/proj"]).is_some());
+        assert!(pos(&argv, &["--setenv", "TMPDIR", "/nonexistent/sb/tmp"]).is_some());
+        assert!(pos(&argv, &["--chdir", "/home/u/proj"]).is_some());
+        assert!(argv.iter().any(|a| a == "--unshare-all"));
+        // 쓰기 가능한 bind 는 읽기 전용 루트 뒤에 와야 덮어씀
+        assert!(pos(&argv, &["--ro-bind"]) < pos(&argv, &["--bind"]));
+        assert_eq!(argv[argv.len() - 4..], ["--", "sh", "-lc", "ls"]);
+    }
+
+    #[test]
+    fn unshare_arguments() {
+        let sb = sandbox(Backend::Unshare, Path::new("/nonexistent/sb"), Path::new("/home/u/proj"));
+        let argv = sb.wrap(&["sh".to_string(), "-lc".to_string(), "ls".to_string()]);
+        assert_eq!(argv[..2], ["unshare", "--user"]);
+        assert!(argv.iter().any(|a| a == "--net"));
+        assert!(argv.contains(&"TMPDIR=/nonexistent/sb/tmp".to_string()));
+        // 초기화 스크립트의 $0..$4, 그 뒤에 실행할 argv
+        let at = pos(&argv, &["-c", UNSHARE_SETUP]).unwrap();
+        assert_eq!(
+            argv[at + 2..],
+            ["sconny-sandbox", "/nonexistent/sb/work", "/home/u/proj", "/nonexistent/sb/tmp", "/nonexistent/sb/dev", "sh", "-lc", "ls"]
+        );
+    }
+
+    #[test]
+    fn changes_between_copy_and_original() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+        let base = env::temp_dir().join(format!("sconny-sandbox-test-{}-{}", std::process::id(), nanos));
+        let cwd = base.join("cwd");
+        fs::create_dir_all(cwd.join("sub")).unwrap();
+        fs::write(cwd.join("same.txt"), "1").unwrap();
+        fs::write(cwd.join("edit.txt"), "abc").unwrap();
+        fs::write(cwd.join("sub/gone.txt"), "x").unwrap();
+
+        let sb = sandbox(Backend::Bwrap, &base.join("root"), &cwd);
+        fs::create_dir_all(sb.workdir()).unwrap();
+        copy_tree(&cwd, sb.workdir()).unwrap();
+        assert!(sb.changes().unwrap().is_empty());
+
+        fs::write(sb.workdir().join("edit.txt"), "abd").unwrap(); // 크기는 같고 내용만 다름
+        fs::remove_file(sb.workdir().join("sub/gone.txt")).unwrap();
+        fs::write(sb.workdir().join("new.txt"), "n").unwrap();
+        assert_eq!(
+            sb.changes().unwrap(),
+            [
+                FsChange::Modified("edit.txt".into()),
+                FsChange::Created("new.txt".into()),
+                FsChange::Deleted("sub/gone.txt".into()),
+            ]
+        );
+        drop(sb);
+        assert!(!base.join("root").exists());
+        let _ = fs::remove_dir_all(&base);
+    }
+}
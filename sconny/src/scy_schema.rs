@@ -0,0 +1,373 @@
+// scy_schema.rs
+// CommandPlan 의 JSON Schema 와, 모델이 자주 틀리는 형태를 고쳐서 스키마에 맞추는 검사기.
+// (cmd 를 문자열 하나나 argv 토큰 배열로 주는 경우, risk 철자가 다른 경우 등)
+// 고칠 수 없으면 에러 목록을 돌려주고, 호출한 쪽이 그 에러와 스키마를 붙여 한 번 더 요청한다.
+use serde_json::{json, Map, Value};
+
+use crate::scy_analyzer::Risk;
+use crate::scy_setting::OnError;
+
+/// CommandPlan 의 JSON Schema (draft 2020-12)
+pub fn plan_schema() -> Value {
+    let strings = json!({ "type": "array", "items": { "type": "string" } });
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "CommandPlan",
+        "type": "object",
+        "required": ["cmd"],
+        "properties": {
+            "cmd": {
+                "description": "full shell command strings, one per step (not argv tokens)",
+                "type": "array",
+                "items": { "type": "string", "minLength": 1 }
+            },
+            "explain": { "type": ["string", "null"] },
+            "needs_confirmation": { "type": ["boolean", "null"] },
+            "risk": { "enum": ["low", "medium", "high", null] },
+            "assumptions": strings,
+            "notes": strings,
+            "timeout_sec": {
+                "description": "per-command timeout aligned with cmd, null = default",
+                "type": "array",
+                "items": { "type": ["integer", "null"], "minimum": 0 }
+            },
+            "on_failure": {
+                "description": "cleanup command aligned with cmd, run if that command fails",
+                "type": "array",
+                "items": { "type": ["string", "null"] }
+            },
+            "on_error": { "enum": ["stop", "continue", null] }
+        }
+    })
+}
+
+/// 흔한 실수를 고치고 스키마에 맞는지 검사.
+/// Ok: (고친 값, 무엇을 고쳤는지), Err: 스키마 위반 목록
+pub fn conform(value: Value) -> Result<(Value, Vec<String>), Vec<String>> {
+    let mut fixes = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut obj = match value {
+        Value::Object(m) => m,
+        // 명령 배열만 준 경우
+        Value::Array(items) => {
+            fixes.push("top-level array used as \"cmd\"".to_string());
+            let mut m = Map::new();
+            m.insert("cmd".to_string(), Value::Array(items));
+            m
+        }
+        other => return Err(vec![format!("expected a JSON object, got {}", type_name(&other))]),
+    };
+
+    // cmd 대신 다른 이름
+    if !obj.contains_key("cmd") {
+        for alias in ["commands", "command", "cmds"] {
+            if let Some(v) = obj.remove(alias) {
+                fixes.push(format!("\"{}\" renamed to \"cmd\"", alias));
+                obj.insert("cmd".to_string(), v);
+                break;
+            }
+        }
+    }
+
+    match obj.remove("cmd") {
+        Some(v) => match conform_cmd(v, &mut fixes) {
+            Ok(cmd) => {
+                obj.insert("cmd".to_string(), json!(cmd));
+            }
+            Err(e) => errors.push(e),
+        },
+        None => errors.push("\"cmd\" is required".to_string()),
+    }
+    let cmd_len = obj.get("cmd").and_then(Value::as_array).map(Vec::len).unwrap_or(0);
+
+    fix_field(&mut obj, "explain", &mut errors, |v| match v {
+        Value::Null | Value::String(_) => Ok(v),
+        Value::Array(items) if items.iter().all(Value::is_string) => {
+            Ok(json!(items.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" ")))
+        }
+        other => Err(format!("expected a string, got {}", type_name(&other))),
+    });
+
+    fix_field(&mut obj, "needs_confirmation", &mut errors, |v| match v {
+        Value::Null | Value::Bool(_) => Ok(v),
+        Value::String(s) => match s.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" => Ok(json!(true)),
+            "false" | "no" | "0" => Ok(json!(false)),
+            _ => Err(format!("expected a boolean, got \"{}\"", s)),
+        },
+        Value::Number(n) => Ok(json!(n.as_f64() != Some(0.0))),
+        other => Err(format!("expected a boolean, got {}", type_name(&other))),
+    });
+
+    // 모르는 risk 는 받아들이되 medium 으로 (로컬 분석이 더 높으면 그쪽으로 올라감)
+    if let Some(v) = obj.get_mut("risk") {
+        let parsed = v.as_str().map(|s| (s.to_string(), Risk::parse(s)));
+        match parsed {
+            _ if v.is_null() => {}
+            Some((s, Some(r))) => {
+                if s != r.as_str() {
+                    fixes.push(format!("risk \"{}\" read as \"{}\"", s, r));
+                }
+                *v = json!(r.as_str());
+            }
+            _ => {
+                fixes.push(format!("unknown risk {} treated as \"medium\"", v));
+                *v = json!("medium");
+            }
+        }
+    }
+
+    for key in ["assumptions", "notes"] {
+        fix_field(&mut obj, key, &mut errors, |v| match v {
+            Value::Null => Ok(v),
+            Value::String(s) => Ok(json!([s])),
+            Value::Array(items) => Ok(json!(items
+                .into_iter()
+                .filter(|x| !x.is_null())
+                .map(|x| match x {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                })
+                .collect::<Vec<_>>())),
+            other => Err(format!("expected an array of strings, got {}", type_name(&other))),
+        });
+    }
+
+    // 숫자 하나면 모든 명령에 같은 값
+    fix_field(&mut obj, "timeout_sec", &mut errors, |v| {
+        let item = |x: &Value| match x {
+            Value::Null => Ok(Value::Null),
+            Value::Number(n) if n.as_u64().is_some() => Ok(x.clone()),
+            Value::String(s) if s.trim().parse::<u64>().is_ok() => Ok(json!(s.trim().parse::<u64>().unwrap_or(0))),
+            other => Err(format!("expected a non-negative integer or null, got {}", other)),
+        };
+        match v {
+            Value::Null => Ok(v),
+            Value::Array(items) => items.iter().map(item).collect::<Result<Vec<_>, _>>().map(Value::Array),
+            other => item(&other).map(|x| Value::Array(vec![x; cmd_len])),
+        }
+    });
+
+    fix_field(&mut obj, "on_failure", &mut errors, |v| match v {
+        Value::Null => Ok(v),
+        Value::String(_) if cmd_len == 1 => Ok(json!([v])),
+        Value::Array(items) if items.iter().all(|x| x.is_null() || x.is_string()) => Ok(Value::Array(items)),
+        other => Err(format!("expected an array of strings or nulls, got {}", other)),
+    });
+
+    if let Some(v) = obj.get_mut("on_error") {
+        match v.as_str().map(|s| (s.to_string(), OnError::parse(s))) {
+            _ if v.is_null() => {}
+            Some((_, Some(e))) => *v = json!(e.name()),
+            _ => {
+                fixes.push(format!("unknown on_error {} ignored", v));
+                *v = Value::Null;
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok((Value::Object(obj), fixes))
+    } else {
+        Err(errors)
+    }
+}
+
+/// 키가 있으면 f 로 고쳐서 다시 넣고, 실패하면 에러 목록에 추가
+fn fix_field(obj: &mut Map<String, Value>, key: &str, errors: &mut Vec<String>, f: impl FnOnce(Value) -> Result<Value, String>) {
+    if let Some(v) = obj.remove(key) {
+        match f(v) {
+            Ok(v) => {
+                obj.insert(key.to_string(), v);
+            }
+            Err(e) => errors.push(format!("\"{}\": {}", key, e)),
+        }
+    }
+}
+
+fn conform_cmd(v: Value, fixes: &mut Vec<String>) -> Result<Vec<String>, String> {
+    let items = match v {
+        Value::String(s) => {
+            fixes.push("\"cmd\" string wrapped in an array".to_string());
+            return Ok(drop_empty(vec![s]));
+        }
+        Value::Array(items) => items,
+        other => return Err(format!("\"cmd\": expected an array of command strings, got {}", type_name(&other))),
+    };
+
+    // [["tar", "-czf", ...], ["ls"]]: 명령마다 argv 배열
+    if !items.is_empty() && items.iter().all(Value::is_array) {
+        fixes.push("\"cmd\" argv arrays joined into command strings".to_string());
+        return items
+            .iter()
+            .enumerate()
+            .map(|(i, argv)| {
+                let tokens = argv.as_array().map(Vec::as_slice).unwrap_or_default();
+                let words: Option<Vec<&str>> = tokens.iter().map(Value::as_str).collect();
+                words
+                    .map(|w| join_argv(&w))
+                    .ok_or_else(|| format!("\"cmd\"[{}]: argv array must contain only strings", i))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(drop_empty);
+    }
+
+    let mut out = Vec::with_capacity(items.len());
+    for (i, item) in items.into_iter().enumerate() {
+        match item {
+            Value::String(s) => out.push(s),
+            // [{"cmd": "..."}]
+            Value::Object(m) => match m.get("cmd").or_else(|| m.get("command")).and_then(Value::as_str) {
+                Some(s) => {
+                    fixes.push(format!("\"cmd\"[{}] object reduced to its command string", i));
+                    out.push(s.to_string());
+                }
+                None => return Err(format!("\"cmd\"[{}]: expected a command string, got an object", i)),
+            },
+            other => return Err(format!("\"cmd\"[{}]: expected a command string, got {}", i, type_name(&other))),
+        }
+    }
+
+    // ["tar", "-czf", "a.tar.gz", "dir/"]: 한 명령을 argv 토큰으로 나눈 경우.
+    // 두 번째가 옵션이면 (옵션은 명령 하나가 될 수 없음) 합치고,
+    // ["make", "./a.out"] 처럼 각각 명령일 수도 있으면 추측하지 않고 에러.
+    let words: Vec<&str> = out.iter().map(String::as_str).collect();
+    match argv_shape(&words) {
+        ArgvShape::Argv => {
+            fixes.push("\"cmd\" argv tokens joined into one command".to_string());
+            Ok(vec![join_argv(&words)])
+        }
+        ArgvShape::Ambiguous => Err(format!(
+            "\"cmd\": {:?} could be argv tokens of one command or separate commands; give one full command string per step",
+            words
+        )),
+        ArgvShape::Commands => Ok(drop_empty(out)),
+    }
+}
+
+enum ArgvShape {
+    Argv,
+    Ambiguous,
+    Commands,
+}
+
+/// 공백 없는 토큰들이고 두 번째가 옵션이면 argv, 뒤에 경로/값처럼 보이는 것만 있으면 애매함.
+/// (["pwd", "ls"] 처럼 한 단어 명령 여러 개는 그대로 둠)
+fn argv_shape(words: &[&str]) -> ArgvShape {
+    if words.len() < 2 || words.iter().any(|w| w.is_empty() || w.contains(char::is_whitespace)) {
+        return ArgvShape::Commands;
+    }
+    if words[1].starts_with('-') {
+        ArgvShape::Argv
+    } else if words[1..].iter().any(|w| w.starts_with('-') || w.contains(['/', '.', '=', '*'])) {
+        ArgvShape::Ambiguous
+    } else {
+        ArgvShape::Commands
+    }
+}
+
+fn join_argv(words: &[&str]) -> String {
+    const OPERATORS: [&str; 9] = ["&&", "||", "|", ";", ">", ">>", "<", "2>", "2>&1"];
+    words
+        .iter()
+        .map(|w| {
+            let safe = !w.is_empty() && w.chars().all(|c| c.is_ascii_alphanumeric() || "_@%+=:,./-*~".contains(c));
+            if safe || OPERATORS.contains(w) {
+                w.to_string()
+            } else {
+                format!("'{}'", w.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn drop_empty(cmds: Vec<String>) -> Vec<String> {
+    cmds.into_iter().filter(|c| !c.trim().is_empty()).collect()
+}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(v: Value) -> (Value, Vec<String>) {
+        conform(v.clone()).unwrap_or_else(|e| panic!("{}: {:?}", v, e))
+    }
+
+    fn cmd(v: Value) -> Value {
+        ok(v).0["cmd"].clone()
+    }
+
+    #[test]
+    fn cmd_shapes() {
+        // 배열만, 다른 이름, 문자열 하나
+        assert_eq!(cmd(json!(["ls -la"])), json!(["ls -la"]));
+        assert_eq!(cmd(json!({"commands": ["ls"]})), json!(["ls"]));
+        assert_eq!(cmd(json!({"cmd": "ls -la"})), json!(["ls -la"]));
+        // 명령마다 argv 배열, 객체
+        assert_eq!(cmd(json!({"cmd": [["tar", "-czf", "a b.tgz", "c/"], ["ls"]]})), json!(["tar -czf 'a b.tgz' c/", "ls"]));
+        assert_eq!(cmd(json!({"cmd": [{"cmd": "ls"}, {"command": "pwd"}]})), json!(["ls", "pwd"]));
+        assert_eq!(cmd(json!({"cmd": ["ls", " ", "pwd"]})), json!(["ls", "pwd"]));
+        assert!(conform(json!({"cmd": 3})).is_err());
+        assert!(conform(json!({"explain": "x"})).is_err());
+    }
+
+    #[test]
+    fn flat_argv_tokens_are_quoted() {
+        assert_eq!(cmd(json!({"cmd": ["grep", "-r", "a|b", "."]})), json!(["grep -r 'a|b' ."]));
+        assert_eq!(cmd(json!({"cmd": ["ls", "-la", "|", "wc", "-l"]})), json!(["ls -la | wc -l"]));
+        // 한 단어 명령 여러 개는 그대로
+        assert_eq!(cmd(json!({"cmd": ["pwd", "ls"]})), json!(["pwd", "ls"]));
+    }
+
+    #[test]
+    fn ambiguous_flat_tokens_are_an_error() {
+        let errors = conform(json!({"cmd": ["make", "./a.out"]})).unwrap_err();
+        assert!(errors[0].contains("separate commands"), "{:?}", errors);
+    }
+
+    #[test]
+    fn field_fixes() {
+        let (v, fixes) = ok(json!({
+            "cmd": ["a", "b"],
+            "explain": ["one", "two"],
+            "needs_confirmation": "yes",
+            "risk": "critical",
+            "assumptions": "x",
+            "notes": ["n", null, 3],
+            "timeout_sec": "30",
+            "on_error": "whatever",
+        }));
+        assert_eq!(v["explain"], json!("one two"));
+        assert_eq!(v["needs_confirmation"], json!(true));
+        assert_eq!(v["risk"], json!("high"));
+        assert_eq!(v["assumptions"], json!(["x"]));
+        assert_eq!(v["notes"], json!(["n", "3"]));
+        assert_eq!(v["timeout_sec"], json!([30, 30]));
+        assert_eq!(v["on_error"], Value::Null);
+        assert!(fixes.iter().any(|f| f.contains("on_error")));
+
+        assert_eq!(ok(json!({"cmd": ["a"], "risk": "spicy"})).0["risk"], json!("medium"));
+        assert_eq!(ok(json!({"cmd": ["a"], "needs_confirmation": 0})).0["needs_confirmation"], json!(false));
+        assert_eq!(ok(json!({"cmd": ["a"], "on_failure": "undo"})).0["on_failure"], json!(["undo"]));
+        assert_eq!(ok(json!({"cmd": ["a"], "on_error": "continue_on_error"})).0["on_error"], json!("continue"));
+    }
+
+    #[test]
+    fn field_errors() {
+        let errors = conform(json!({"cmd": ["a", "b"], "timeout_sec": [-1], "on_failure": "x", "needs_confirmation": "maybe"})).unwrap_err();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+    }
+}
@@ -0,0 +1,247 @@
+// scy_session.rs
+// REPL 세션의 대화 기록. 이전 요청, 생성된 계획, 실행 결과를 chat history 로 백엔드에 보내서
+// "거기서 .log 파일도 빼줘" 같은 후속 요청이 앞의 대화를 참고할 수 있게 한다.
+// 토큰 예산을 넘으면 오래된 턴부터 한 줄 요약으로 바꾸고, 요약도 넘치면 버린다.
+use std::collections::VecDeque;
+
+use serde_json::Value;
+
+use crate::scy_backend::{ChatMessage, ChatRole};
+use crate::scy_output::Output;
+
+const OUTPUT_TAIL_LINES: usize = 15;  // 결과에 넣을 stdout/stderr 마지막 줄 수
+const OUTPUT_TAIL_CHARS: usize = 1500;
+
+/// 요청 하나와 그 결과
+#[derive(Debug, Clone)]
+struct Turn {
+    request: String,
+    plan: String,    // 계획 JSON (assistant 메시지)
+    cmd: Vec<String>,
+    status: String,
+    outcome: String, // 다음 user 메시지 앞에 붙는 실행 결과
+}
+
+pub struct Session {
+    turns: VecDeque<Turn>,
+    summary: VecDeque<String>, // 예산 때문에 밀려난 턴의 한 줄 요약
+    budget_tokens: usize,      // 0 이면 기록하지 않음
+}
+
+impl Session {
+    pub fn new(budget_tokens: usize) -> Self {
+        Self { turns: VecDeque::new(), summary: VecDeque::new(), budget_tokens }
+    }
+
+    /// 기억하고 있는 턴 수 (요약 포함)
+    pub fn len(&self) -> usize {
+        self.turns.len() + self.summary.len()
+    }
+
+    pub fn reset(&mut self) {
+        self.turns.clear();
+        self.summary.clear();
+    }
+
+    /// 요청 하나가 끝난 뒤 기록. 계획을 받지 못한 요청(API 에러 등)은 기록하지 않음.
+    pub fn record(&mut self, request: &str, out: &Output, status: &str) {
+        if self.budget_tokens == 0 {
+            return;
+        }
+        let Some(plan) = out.plan_value() else {
+            return;
+        };
+        let cmd = plan
+            .get("cmd")
+            .and_then(Value::as_array)
+            .map(|a| a.iter().filter_map(Value::as_str).map(str::to_string).collect())
+            .unwrap_or_default();
+        self.turns.push_back(Turn {
+            request: request.to_string(),
+            plan: plan.to_string(),
+            cmd,
+            status: status.to_string(),
+            outcome: describe_outcome(out, status),
+        });
+        self.fit_budget();
+    }
+
+    /// 이번 요청 앞에 보낼 대화. user/assistant 가 번갈아 나오고 assistant 로 끝남.
+    pub fn history(&self) -> Vec<ChatMessage> {
+        let mut messages = Vec::new();
+        let mut pending = self.summary_text();
+        for t in &self.turns {
+            let mut user = pending.take().map(|p| format!("{}\n\n", p)).unwrap_or_default();
+            user.push_str(&format!("User request:\n{}", t.request));
+            messages.push(ChatMessage { role: ChatRole::User, content: user });
+            messages.push(ChatMessage { role: ChatRole::Assistant, content: t.plan.clone() });
+            pending = Some(t.outcome.clone());
+        }
+        messages
+    }
+
+    /// 이번 user 프롬프트 앞에 붙일 내용: 직전 계획의 실행 결과 (턴이 없으면 요약)
+    pub fn context_note(&self) -> Option<String> {
+        match self.turns.back() {
+            Some(t) => Some(format!(
+                "{}\n\nThe next request may refer to the previous ones; resolve references like \"it\" or \"those files\" from the conversation.",
+                t.outcome
+            )),
+            None => self.summary_text(),
+        }
+    }
+
+    fn summary_text(&self) -> Option<String> {
+        if self.summary.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = self.summary.iter().map(|s| format!("- {}", s)).collect();
+        Some(format!("Earlier in this session:\n{}", lines.join("\n")))
+    }
+
+    fn tokens(&self) -> usize {
+        let turns: usize = self.turns.iter().map(|t| estimate_tokens(&t.request) + estimate_tokens(&t.plan) + estimate_tokens(&t.outcome)).sum();
+        turns + self.summary_text().map(|s| estimate_tokens(&s)).unwrap_or(0)
+    }
+
+    /// 예산을 넘으면 오래된 턴을 요약으로, 요약이 예산의 1/4 을 넘으면 오래된 요약부터 버림.
+    /// 마지막 턴은 항상 남김.
+    fn fit_budget(&mut self) {
+        while self.tokens() > self.budget_tokens && self.turns.len() > 1 {
+            if let Some(t) = self.turns.pop_front() {
+                self.summary.push_back(summarize(&t));
+            }
+        }
+        let summary_budget = self.budget_tokens / 4;
+        while !self.summary.is_empty() && self.summary_text().map(|s| estimate_tokens(&s)).unwrap_or(0) > summary_budget {
+            self.summary.pop_front();
+        }
+    }
+}
+
+/// 대략 4글자에 1토큰
+fn estimate_tokens(s: &str) -> usize {
+    s.chars().count().div_ceil(4)
+}
+
+fn summarize(t: &Turn) -> String {
+    let mut cmd = t.cmd.join(" && ");
+    if cmd.chars().count() > 120 {
+        cmd = cmd.chars().take(117).collect::<String>() + "...";
+    }
+    format!("\"{}\" -> `{}` ({})", t.request, cmd, t.status)
+}
+
+/// 실행 결과를 모델이 읽을 문장으로
+fn describe_outcome(out: &Output, status: &str) -> String {
+    let mut s = match status {
+        "ok" if out.step_count() == 0 => "Result of the previous plan: shown to the user, not executed.".to_string(),
+        "ok" => "Result of the previous plan: all commands succeeded.".to_string(),
+        "dry_run" => "Result of the previous plan: not executed (dry run).".to_string(),
+        "declined" => "Result of the previous plan: the user declined to run it.".to_string(),
+        "sandboxed" => "Result of the previous plan: ran in a sandbox copy; the real directory was not changed.".to_string(),
+        "blocked" => "Result of the previous plan: blocked by the local command policy.".to_string(),
+        other => format!("Result of the previous plan: {}.", other),
+    };
+    if let Some(e) = out.errors().last() {
+        s.push_str(&format!("\nError: {}", e.lines().next().unwrap_or_default()));
+    }
+    match out.failed_step(0) {
+        Some(f) => {
+            s.push_str(&format!("\nCommand {} `{}` exited with {:?}.", f.index, f.cmd, f.exit_code));
+            push_tail(&mut s, "stderr", &f.stderr);
+        }
+        None => {
+            // 성공한 경우 마지막 명령의 출력 ("그 중 제일 큰 파일" 같은 후속 요청용)
+            if let Some(last) = out.steps().iter().rev().find(|st| !st.on_failure) {
+                push_tail(&mut s, "output", &last.stdout);
+            }
+        }
+    }
+    s
+}
+
+fn push_tail(s: &mut String, label: &str, text: &str) {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return;
+    }
+    let mut tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n");
+    if tail.chars().count() > OUTPUT_TAIL_CHARS {
+        let skip = tail.chars().count() - OUTPUT_TAIL_CHARS;
+        tail = format!("...{}", tail.chars().skip(skip).collect::<String>());
+    }
+    s.push_str(&format!("\n--- {} (last lines) ---\n{}", label, tail));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::scy_executor::parse_plan;
+    use crate::scy_output::{OutputFormat, StepRecord};
+
+    /// cmd 를 실행해 stdout 을 얻은 것처럼 기록한 Output
+    fn output(request: &str, cmd: &str, exit_code: i32, stdout: &str, stderr: &str) -> Output {
+        let mut out = Output::new(OutputFormat::Json, request);
+        out.plan(&parse_plan(&format!(r#"{{"cmd":[{}]}}"#, serde_json::json!(cmd))).unwrap());
+        out.step(StepRecord::new(1, cmd, Some(exit_code), stdout.to_string(), stderr.to_string(), Duration::ZERO));
+        out
+    }
+
+    fn record(s: &mut Session, request: &str, cmd: &str) {
+        s.record(request, &output(request, cmd, 0, "a.txt\nb.txt\n", ""), "ok");
+    }
+
+    #[test]
+    fn history_alternates_and_carries_outcomes() {
+        let mut s = Session::new(4000);
+        record(&mut s, "list files", "ls");
+        s.record("delete b", &output("delete b", "rm b.txt", 1, "", "rm: cannot remove 'b.txt'\n"), "failed");
+
+        let h = s.history();
+        let roles: Vec<ChatRole> = h.iter().map(|m| m.role).collect();
+        assert_eq!(roles, [ChatRole::User, ChatRole::Assistant, ChatRole::User, ChatRole::Assistant]);
+        assert_eq!(h[0].content, "User request:\nlist files");
+        assert!(h[1].content.contains(r#""ls""#));
+        // 다음 user 메시지 앞에 이전 계획의 결과
+        assert!(h[2].content.starts_with("Result of the previous plan: all commands succeeded.\n--- output (last lines) ---\na.txt\nb.txt"));
+        assert!(h[2].content.ends_with("User request:\ndelete b"));
+
+        let note = s.context_note().unwrap();
+        assert!(note.starts_with("Result of the previous plan: failed.\nCommand 1 `rm b.txt` exited with Some(1)."));
+        assert!(note.contains("rm: cannot remove 'b.txt'"));
+    }
+
+    #[test]
+    fn fit_budget_summarizes_old_turns_and_keeps_the_last() {
+        let mut s = Session::new(120);
+        for i in 0..4 {
+            record(&mut s, &format!("request {}", i), &format!("echo {}", i));
+        }
+        assert_eq!(s.turns.len(), 1);
+        assert_eq!(s.turns[0].request, "request 3");
+        assert!(!s.summary.is_empty() && s.summary.len() < 4);
+        assert_eq!(s.summary.back().unwrap(), "\"request 2\" -> `echo 2` (ok)");
+        assert!(s.history()[0].content.starts_with("Earlier in this session:\n- "));
+
+        // 예산보다 큰 턴 하나도 버리지 않음
+        let mut s = Session::new(5);
+        record(&mut s, &"x".repeat(100), "true");
+        assert_eq!(s.len(), 1);
+        assert_eq!(s.history().len(), 2);
+    }
+
+    #[test]
+    fn zero_budget_or_no_plan_records_nothing() {
+        let mut s = Session::new(0);
+        record(&mut s, "list files", "ls");
+        assert_eq!(s.len(), 0);
+
+        let mut s = Session::new(4000);
+        s.record("x", &Output::new(OutputFormat::Json, "x"), "error");
+        assert!(s.history().is_empty());
+        assert!(s.context_note().is_none());
+    }
+}
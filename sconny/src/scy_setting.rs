@@ -1,17 +1,49 @@
 // scy_setting.rs
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::fmt;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
-const DEFAULT_SCONNY_CONFIG_FILE: &str = "sconny_config.toml";
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::scy_policy::CommandRules;
+use crate::scy_sandbox::SandboxMode;
+use crate::scy_listing;
+use crate::scy_tools;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LlmService {
     OpenAI,
     Gemini,
     Ollama, // ✅ 추가
 }
 
+impl LlmService {
+    pub const ALL: [LlmService; 3] = [LlmService::OpenAI, LlmService::Gemini, LlmService::Ollama];
+
+    /// 설정 파일/환경변수에서 쓰는 이름 (예: "ollama" → [providers.ollama], SCONNY_OLLAMA_BASE_URL)
+    pub fn name(&self) -> &'static str {
+        match self {
+            LlmService::OpenAI => "openai",
+            LlmService::Gemini => "gemini",
+            LlmService::Ollama => "ollama",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LlmService {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        parse_llm_service(&s).ok_or_else(|| {
+            let names: Vec<&str> = LlmService::ALL.iter().map(|x| x.name()).collect();
+            de::Error::custom(format!("unknown llm service '{}', expected one of: {}", s, names.join(", ")))
+        })
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScyOs {
@@ -19,6 +51,44 @@ pub enum ScyOs {
     Linux,
 }
 
+impl<'de> Deserialize<'de> for ScyOs {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        parse_os(&s).ok_or_else(|| de::Error::custom(format!("unknown os '{}', expected linux or windows", s)))
+    }
+}
+
+/// 계획 중간에 명령이 실패했을 때
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    Stop,     // 남은 명령은 건너뜀
+    Continue, // 남은 명령도 실행
+}
+
+impl OnError {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "stop" | "stop-on-error" | "abort" => Some(OnError::Stop),
+            "continue" | "continue-on-error" => Some(OnError::Continue),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OnError::Stop => "stop",
+            OnError::Continue => "continue",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OnError {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        OnError::parse(&s).ok_or_else(|| de::Error::custom(format!("unknown on_error '{}', expected stop or continue", s)))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ScyEnvInfo {
     pub os: ScyOs,
@@ -26,23 +96,110 @@ pub struct ScyEnvInfo {
     pub version_id: Option<String>,     // e.g., "22.04"
     pub pretty_name: Option<String>,    // e.g., "Ubuntu 22.04.3 LTS"
     pub shell: Option<String>,          // e.g., "/bin/bash"
+    pub tools: Vec<String>,             // 설치 여부/버전을 조사해서 프롬프트에 넣을 도구 (비우면 조사 안 함)
+    pub list_cwd: bool,                 // CWD 파일 목록을 프롬프트에 넣을지 (기본 꺼짐)
+    pub list_depth: usize,              // 1: CWD 바로 아래만
+    pub list_max_entries: usize,
+    pub list_deny: Vec<String>,         // 목록에서 빼는 민감한 이름 (glob)
+    pub git: bool,                      // CWD 가 git 저장소면 브랜치/상태/최근 커밋을 프롬프트에 넣을지
+    pub git_commits: usize,             // 넣을 최근 커밋 제목 수 (0: 넣지 않음)
 }
 
 #[derive(Debug, Clone)]
 pub struct ExecPolicy {
     pub dry_run: bool,
     pub require_confirmation: bool,
-    pub timeout_sec: u64,
+    pub timeout_sec: u64,        // 명령 하나당 (0: 제한 없음)
+    pub max_timeout_sec: u64,    // 계획이 step별로 요청할 수 있는 최대 타임아웃 (0: 제한 없음)
+    pub on_error: OnError,       // 설정하지 않으면 계획의 on_error, 그것도 없으면 stop
+    pub repair_attempts: u32,    // 명령이 실패하면 에러를 LLM 에 돌려보내 고친 계획을 받는 횟수 (0: 끔)
+    pub rules: CommandRules,     // [policy.allow] / [policy.deny]
+    pub sandbox: SandboxMode,    // 격리 실행 (기본 off)
+    pub sandbox_max_mb: u64,     // 샌드박스로 복사할 CWD 최대 크기
+    pub preview: bool,           // 샌드박스에서 먼저 실행해 변경(diff)을 보여준 뒤 적용 여부 확인
+    pub undo: bool,              // 실행 전에 건드릴 파일을 백업해서 `sconny undo` 로 복원 가능하게
+    pub backup_max_mb: u64,      // 계획 하나당 백업 총량
+    pub backup_max_file_mb: u64, // 파일 하나당 백업 최대 크기
+    pub step_prefix: bool,       // 명령 출력 줄 앞에 "[N] " (N: 몇 번째 명령)
+    pub tail_lines: usize,       // 에러 보고/기록용으로 남길 stdout/stderr 마지막 줄 수
+}
+
+/// [history] 요청/계획/실행 결과 기록 (`sconny history`)
+#[derive(Debug, Clone)]
+pub struct HistorySetting {
+    pub enabled: bool,
+    pub max_entries: usize, // 넘으면 오래된 기록부터 삭제
+}
+
+/// [providers.<name>] 섹션
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProviderSetting {
+    pub base_url: Option<String>,
+    pub model: Option<String>,      // [llm] model 이 없을 때 이 제공자의 기본 모델
+    pub timeout_sec: Option<u64>,   // HTTP 타임아웃
+}
+
+/// 설정 파일 레이어 (나중 레이어가 앞 레이어를 덮어씀)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigLayer {
+    System,     // /etc/sconny/config.toml
+    User,       // $XDG_CONFIG_HOME/sconny/config.toml
+    Project,    // CWD에서 위로 올라가며 찾은 가장 가까운 .sconny.toml
+    Explicit,   // --config 또는 SCONNY_CONFIG 로 지정한 파일
+}
+
+impl ConfigLayer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigLayer::System => "system",
+            ConfigLayer::User => "user",
+            ConfigLayer::Project => "project",
+            ConfigLayer::Explicit => "explicit",
+        }
+    }
+}
+
+/// 값이 어디서 왔는지 (`sconny config show --origin`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    Default,
+    Detected,                       // 실행 환경에서 자동 감지
+    File(ConfigLayer, PathBuf),
+    Env(String),                    // 환경변수 이름
+    Cli(String),                    // 플래그 이름
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigOrigin::Default => write!(f, "default"),
+            ConfigOrigin::Detected => write!(f, "detected"),
+            ConfigOrigin::File(layer, path) => write!(f, "{} ({})", layer.name(), path.display()),
+            ConfigOrigin::Env(var) => write!(f, "env ({})", var),
+            ConfigOrigin::Cli(flag) => write!(f, "cli ({})", flag),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigFileInfo {
+    pub layer: ConfigLayer,
+    pub path: PathBuf,
+    pub loaded: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct SconnySetting {
     pub llm_service: LlmService,
     pub model: Option<String>,
-    pub ollama_base_url: Option<String>,
+    pub providers: HashMap<LlmService, ProviderSetting>,
+    pub stream: bool,                            // 지원하는 백엔드면 토큰 스트리밍
+    pub history_tokens: usize,                   // REPL 대화 기록에 쓸 토큰 예산 (0: 기록 안 보냄)
     pub env: ScyEnvInfo,
     pub policy: ExecPolicy,
-    pub config_path: String,
+    pub history: HistorySetting,
+    pub config_files: Vec<ConfigFileInfo>,       // 조회한 설정 파일 (레이어 순)
+    pub origins: BTreeMap<String, ConfigOrigin>, // "policy.dry_run" → 값을 마지막으로 설정한 곳
 }
 
 impl SconnySetting {
@@ -50,198 +207,857 @@ impl SconnySetting {
         SconnySetting {
             llm_service: LlmService::OpenAI,
             model: None,
-            ollama_base_url: None,
+            providers: HashMap::new(),
+            stream: true,
+            history_tokens: 4000,
             env: ScyEnvInfo {
                 os: detect_os(),
                 distro_id: None,
                 version_id: None,
                 pretty_name: None,
                 shell: None,
+                tools: scy_tools::DEFAULT_TOOLS.iter().map(|t| t.to_string()).collect(),
+                list_cwd: false,
+                list_depth: 2,
+                list_max_entries: 100,
+                list_deny: scy_listing::DEFAULT_DENY.iter().map(|t| t.to_string()).collect(),
+                git: true,
+                git_commits: 5,
             },
             policy: ExecPolicy {
                 dry_run: true,               // 안전하게 기본 dry-run
                 require_confirmation: true,   // 기본 확인
                 timeout_sec: 15,              // 기본 타임아웃
+                max_timeout_sec: 3600,
+                on_error: OnError::Stop,
+                repair_attempts: 0,
+                rules: CommandRules::default(),
+                sandbox: SandboxMode::Off,
+                sandbox_max_mb: 256,
+                preview: false,
+                undo: true,
+                backup_max_mb: 100,
+                backup_max_file_mb: 20,
+                step_prefix: false,
+                tail_lines: 50,
             },
-            config_path: DEFAULT_SCONNY_CONFIG_FILE.to_string(),
+            history: HistorySetting { enabled: true, max_entries: 1000 },
+            config_files: Vec::new(),
+            origins: BTreeMap::new(),
         }
     }
 
-    pub fn load_setting(&mut self) -> Result<(), String> {
-        if let Ok(p) = env::var("SCONNY_CONFIG") {
-            if !p.trim().is_empty() {
-                self.config_path = p;
-            }
-        }
+    /// system → user → project → explicit(--config 또는 SCONNY_CONFIG) → env 순서로 적용.
+    /// CLI 레이어는 이후 apply_cli_override로 적용.
+    pub fn load_setting(&mut self, explicit_config: Option<&Path>) -> Result<(), String> {
+        // 1) 설정 파일 레이어
+        for (layer, path) in discover_config_files(explicit_config) {
+            let contents = match fs::read_to_string(&path) {
+                Ok(c) => c,
+                Err(e) if e.kind() == io::ErrorKind::NotFound && layer != ConfigLayer::Explicit => {
+                    self.config_files.push(ConfigFileInfo { layer, path, loaded: false });
+                    continue;
+                }
+                Err(e) => return Err(format!("{}: {}", path.display(), e)),
+            };
 
-        // 1) 파일에서 로드 (있으면)
-        if let Ok(contents) = fs::read_to_string(&self.config_path) {
-            let kv = parse_loose_toml_kv(&contents);
-            apply_kv(self, &kv);
+            let (mut file, warnings) = parse_config_file(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+            for w in warnings {
+                eprintln!("warning: {}:{}", path.display(), w);
+            }
+            if layer == ConfigLayer::Project {
+                for key in restrict_project_file(&mut file) {
+                    eprintln!(
+                        "warning: {}: ignoring '{}': not allowed in a project config (set it in the user config, --config or --set)",
+                        path.display(),
+                        key
+                    );
+                }
+            }
+            apply_config_file(self, file, &ConfigOrigin::File(layer, path.clone()));
+            self.config_files.push(ConfigFileInfo { layer, path, loaded: true });
         }
 
         // 2) ENV 오버라이드
-        apply_env_overrides(self);
+        apply_env_overrides(self)?;
 
         // 3) 환경 자동 감지 보강
+        let before = self.env.shell.is_some();
         fill_env_defaults(&mut self.env);
+        if !before && self.env.shell.is_some() {
+            self.mark("env.shell", &ConfigOrigin::Detected);
+        }
+        if !self.origins.contains_key("env.os") {
+            self.mark("env.os", &ConfigOrigin::Detected);
+        }
 
         Ok(())
     }
-}
 
-// -------------------- internal helpers --------------------
+    /// CLI 레이어: 점 표기 키에 문자열 값을 적용 (`--set policy.dry_run=false`)
+    pub fn apply_cli_override(&mut self, key: &str, value: &str, flag: &str) -> Result<(), String> {
+        let value = value.trim();
+        let bad = |what: &str| format!("{}: invalid {} for '{}': '{}'", flag, what, key, value);
 
-fn detect_os() -> ScyOs {
-    if cfg!(windows) {
-        ScyOs::Windows
-    } else {
-        ScyOs::Linux
+        match key {
+            "llm.service" => self.llm_service = parse_llm_service(value).ok_or_else(|| bad("service"))?,
+            "llm.model" => self.model = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "llm.stream" => self.stream = parse_bool(value).ok_or_else(|| bad("bool"))?,
+            "llm.history_tokens" => self.history_tokens = value.parse().map_err(|_| bad("number"))?,
+            "policy.dry_run" => self.policy.dry_run = parse_bool(value).ok_or_else(|| bad("bool"))?,
+            "policy.require_confirmation" => {
+                self.policy.require_confirmation = parse_bool(value).ok_or_else(|| bad("bool"))?
+            }
+            "policy.timeout_sec" => self.policy.timeout_sec = value.parse().map_err(|_| bad("number"))?,
+            "policy.max_timeout_sec" => self.policy.max_timeout_sec = value.parse().map_err(|_| bad("number"))?,
+            "policy.on_error" => self.policy.on_error = OnError::parse(value).ok_or_else(|| bad("on_error (stop|continue)"))?,
+            "policy.repair_attempts" => self.policy.repair_attempts = value.parse().map_err(|_| bad("number"))?,
+            "policy.sandbox" => self.policy.sandbox = SandboxMode::parse(value).ok_or_else(|| bad("sandbox mode"))?,
+            "policy.sandbox_max_mb" => self.policy.sandbox_max_mb = value.parse().map_err(|_| bad("number"))?,
+            "policy.preview" => self.policy.preview = parse_bool(value).ok_or_else(|| bad("bool"))?,
+            "policy.undo" => self.policy.undo = parse_bool(value).ok_or_else(|| bad("bool"))?,
+            "policy.backup_max_mb" => self.policy.backup_max_mb = value.parse().map_err(|_| bad("number"))?,
+            "policy.backup_max_file_mb" => self.policy.backup_max_file_mb = value.parse().map_err(|_| bad("number"))?,
+            "policy.step_prefix" => self.policy.step_prefix = parse_bool(value).ok_or_else(|| bad("bool"))?,
+            "policy.tail_lines" => self.policy.tail_lines = value.parse().map_err(|_| bad("number"))?,
+            // 목록은 쉼표로 구분: --set policy.allow.programs=git,tar
+            k if k.starts_with("policy.allow.") || k.starts_with("policy.deny.") => {
+                let mut parts = k.splitn(3, '.').skip(1);
+                let (kind, field) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+                if !matches!(field, "programs" | "args" | "paths") {
+                    return Err(format!("{}: unknown key '{}'", flag, key));
+                }
+                let list = value.split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect();
+                merge_rule_list(&mut self.policy.rules, kind, field, list);
+            }
+            "env.os" => self.env.os = parse_os(value).ok_or_else(|| bad("os"))?,
+            "env.shell" => self.env.shell = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "env.tools" => {
+                self.env.tools = value.split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+            }
+            "env.list_cwd" => self.env.list_cwd = parse_bool(value).ok_or_else(|| bad("bool"))?,
+            "env.list_depth" => self.env.list_depth = value.parse().map_err(|_| bad("number"))?,
+            "env.list_max_entries" => self.env.list_max_entries = value.parse().map_err(|_| bad("number"))?,
+            "env.list_deny" => {
+                self.env.list_deny = value.split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+            }
+            "env.git" => self.env.git = parse_bool(value).ok_or_else(|| bad("bool"))?,
+            "env.git_commits" => self.env.git_commits = value.parse().map_err(|_| bad("number"))?,
+            "history.enabled" => self.history.enabled = parse_bool(value).ok_or_else(|| bad("bool"))?,
+            "history.max_entries" => self.history.max_entries = value.parse().map_err(|_| bad("number"))?,
+            _ => {
+                let rest = key.strip_prefix("providers.").ok_or_else(|| format!("{}: unknown key '{}'", flag, key))?;
+                let (name, field) = rest.split_once('.').ok_or_else(|| format!("{}: unknown key '{}'", flag, key))?;
+                let svc = parse_llm_service(name).ok_or_else(|| format!("{}: unknown provider '{}'", flag, name))?;
+                let p = self.providers.entry(svc).or_default();
+                match field {
+                    "base_url" => p.base_url = Some(value.to_string()),
+                    "model" => p.model = Some(value.to_string()),
+                    "timeout_sec" => p.timeout_sec = Some(value.parse().map_err(|_| bad("number"))?),
+                    _ => return Err(format!("{}: unknown key '{}'", flag, key)),
+                }
+                let key = format!("providers.{}.{}", svc.name(), field);
+                self.mark(&key, &ConfigOrigin::Cli(flag.to_string()));
+                return Ok(());
+            }
+        }
+
+        self.mark(key, &ConfigOrigin::Cli(flag.to_string()));
+        Ok(())
     }
-}
 
-fn parse_loose_toml_kv(contents: &str) -> HashMap<String, String> {
-    let mut map = HashMap::new();
+    fn mark(&mut self, key: &str, origin: &ConfigOrigin) {
+        self.origins.insert(key.to_string(), origin.clone());
+    }
 
-    for line in contents.lines() {
-        let mut s = line.trim().to_string();
-        if s.is_empty() { continue; }
+    pub fn origin_of(&self, key: &str) -> ConfigOrigin {
+        self.origins.get(key).cloned().unwrap_or(ConfigOrigin::Default)
+    }
 
-        // 주석 제거
-        if let Some(pos) = s.find('#') {
-            s.truncate(pos);
-            s = s.trim().to_string();
-            if s.is_empty() { continue; }
+    /// 현재 유효한 값 목록 (키, TOML 표기 값). 설정되지 않은 선택 값은 제외.
+    pub fn effective_values(&self) -> Vec<(String, String)> {
+        let q = |s: &str| toml::Value::String(s.to_string()).to_string();
+        let mut out = vec![
+            ("llm.service".to_string(), q(self.llm_service.name())),
+        ];
+        if let Some(m) = &self.model {
+            out.push(("llm.model".to_string(), q(m)));
         }
-
-        // 섹션([xxx])은 무시
-        if s.starts_with('[') && s.ends_with(']') {
-            continue;
+        out.push(("llm.stream".to_string(), self.stream.to_string()));
+        out.push(("llm.history_tokens".to_string(), self.history_tokens.to_string()));
+        out.push(("policy.dry_run".to_string(), self.policy.dry_run.to_string()));
+        out.push(("policy.require_confirmation".to_string(), self.policy.require_confirmation.to_string()));
+        out.push(("policy.timeout_sec".to_string(), self.policy.timeout_sec.to_string()));
+        out.push(("policy.max_timeout_sec".to_string(), self.policy.max_timeout_sec.to_string()));
+        out.push(("policy.on_error".to_string(), q(self.policy.on_error.name())));
+        out.push(("policy.repair_attempts".to_string(), self.policy.repair_attempts.to_string()));
+        out.push(("policy.sandbox".to_string(), q(self.policy.sandbox.name())));
+        out.push(("policy.sandbox_max_mb".to_string(), self.policy.sandbox_max_mb.to_string()));
+        out.push(("policy.preview".to_string(), self.policy.preview.to_string()));
+        out.push(("policy.undo".to_string(), self.policy.undo.to_string()));
+        out.push(("policy.backup_max_mb".to_string(), self.policy.backup_max_mb.to_string()));
+        out.push(("policy.backup_max_file_mb".to_string(), self.policy.backup_max_file_mb.to_string()));
+        out.push(("policy.step_prefix".to_string(), self.policy.step_prefix.to_string()));
+        out.push(("policy.tail_lines".to_string(), self.policy.tail_lines.to_string()));
+        for kind in ["allow", "deny"] {
+            for field in ["programs", "args", "paths"] {
+                let list = self.policy.rules.list(kind, field);
+                if !list.is_empty() {
+                    let arr = toml::Value::Array(list.iter().map(|v| toml::Value::String(v.clone())).collect());
+                    out.push((format!("policy.{}.{}", kind, field), arr.to_string()));
+                }
+            }
+        }
+        out.push((
+            "env.os".to_string(),
+            q(match self.env.os {
+                ScyOs::Linux => "linux",
+                ScyOs::Windows => "windows",
+            }),
+        ));
+        if let Some(sh) = &self.env.shell {
+            out.push(("env.shell".to_string(), q(sh)));
+        }
+        let tools = toml::Value::Array(self.env.tools.iter().map(|v| toml::Value::String(v.clone())).collect());
+        out.push(("env.tools".to_string(), tools.to_string()));
+        out.push(("env.list_cwd".to_string(), self.env.list_cwd.to_string()));
+        out.push(("env.list_depth".to_string(), self.env.list_depth.to_string()));
+        out.push(("env.list_max_entries".to_string(), self.env.list_max_entries.to_string()));
+        let deny = toml::Value::Array(self.env.list_deny.iter().map(|v| toml::Value::String(v.clone())).collect());
+        out.push(("env.list_deny".to_string(), deny.to_string()));
+        out.push(("env.git".to_string(), self.env.git.to_string()));
+        out.push(("env.git_commits".to_string(), self.env.git_commits.to_string()));
+        out.push(("history.enabled".to_string(), self.history.enabled.to_string()));
+        out.push(("history.max_entries".to_string(), self.history.max_entries.to_string()));
+        for svc in LlmService::ALL {
+            if let Some(p) = self.provider(svc) {
+                let prefix = format!("providers.{}", svc.name());
+                if let Some(v) = &p.base_url {
+                    out.push((format!("{}.base_url", prefix), q(v)));
+                }
+                if let Some(v) = &p.model {
+                    out.push((format!("{}.model", prefix), q(v)));
+                }
+                if let Some(v) = p.timeout_sec {
+                    out.push((format!("{}.timeout_sec", prefix), v.to_string()));
+                }
+            }
         }
+        out
+    }
 
-        let eq = match s.find('=') {
-            Some(p) => p,
-            None => continue,
-        };
+    pub fn provider(&self, service: LlmService) -> Option<&ProviderSetting> {
+        self.providers.get(&service)
+    }
 
-        let key = s[..eq].trim().to_string();
-        let mut val = s[eq + 1..].trim().to_string();
-        if key.is_empty() || val.is_empty() { continue; }
+    pub fn base_url(&self, service: LlmService) -> Option<&str> {
+        self.provider(service).and_then(|p| p.base_url.as_deref())
+    }
 
-        val = strip_quotes(&val);
-        map.insert(key, val);
+    /// [llm] model → [providers.<svc>] model 순서
+    pub fn model_for(&self, service: LlmService) -> Option<String> {
+        self.model
+            .clone()
+            .or_else(|| self.provider(service).and_then(|p| p.model.clone()))
     }
+}
 
-    map
+// -------------------- config file schema --------------------
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    llm: Option<LlmSection>,
+    policy: Option<PolicySection>,
+    env: Option<EnvSection>,
+    history: Option<HistorySection>,
+    #[serde(default)]
+    providers: BTreeMap<String, ProviderSetting>,
+
+    // 섹션 도입 전 평면 키 (하위 호환)
+    llm_service: Option<LlmService>,
+    model: Option<String>,
+    stream: Option<bool>,
+    ollama_base_url: Option<String>,
+    gemini_base_url: Option<String>,
+    os: Option<ScyOs>,
+    shell: Option<String>,
+    dry_run: Option<bool>,
+    require_confirmation: Option<bool>,
+    timeout_sec: Option<u64>,
 }
 
-fn strip_quotes(s: &str) -> String {
-    let t = s.trim();
-    if (t.starts_with('"') && t.ends_with('"')) || (t.starts_with('\'') && t.ends_with('\'')) {
-        if t.len() >= 2 {
-            return t[1..t.len() - 1].to_string();
+#[derive(Debug, Default, Deserialize)]
+struct LlmSection {
+    service: Option<LlmService>,
+    model: Option<String>,
+    stream: Option<bool>,
+    history_tokens: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PolicySection {
+    dry_run: Option<bool>,
+    require_confirmation: Option<bool>,
+    timeout_sec: Option<u64>,
+    max_timeout_sec: Option<u64>,
+    on_error: Option<OnError>,
+    repair_attempts: Option<u32>,
+    sandbox: Option<SandboxMode>,
+    sandbox_max_mb: Option<u64>,
+    preview: Option<bool>,
+    undo: Option<bool>,
+    backup_max_mb: Option<u64>,
+    backup_max_file_mb: Option<u64>,
+    step_prefix: Option<bool>,
+    tail_lines: Option<usize>,
+    allow: Option<RuleListSection>,
+    deny: Option<RuleListSection>,
+}
+
+/// [policy.allow] / [policy.deny]. allow 는 레이어에 있는 목록으로 통째로 교체, deny 는 모든 레이어를 합침.
+#[derive(Debug, Default, Deserialize)]
+struct RuleListSection {
+    programs: Option<Vec<String>>,
+    args: Option<Vec<String>>,
+    paths: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HistorySection {
+    enabled: Option<bool>,
+    max_entries: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct EnvSection {
+    os: Option<ScyOs>,
+    shell: Option<String>,
+    tools: Option<Vec<String>>,
+    list_cwd: Option<bool>,
+    list_depth: Option<usize>,
+    list_max_entries: Option<usize>,
+    list_deny: Option<Vec<String>>,
+    git: Option<bool>,
+    git_commits: Option<usize>,
+}
+
+const LEGACY_KEYS: [(&str, &str); 10] = [
+    ("llm_service", "[llm] service"),
+    ("model", "[llm] model"),
+    ("stream", "[llm] stream"),
+    ("ollama_base_url", "[providers.ollama] base_url"),
+    ("gemini_base_url", "[providers.gemini] base_url"),
+    ("os", "[env] os"),
+    ("shell", "[env] shell"),
+    ("dry_run", "[policy] dry_run"),
+    ("require_confirmation", "[policy] require_confirmation"),
+    ("timeout_sec", "[policy] timeout_sec"),
+];
+
+/// TOML 파싱. 문법/타입 오류는 줄 번호가 포함된 에러로,
+/// 모르는 키·제공자·구식 평면 키는 "<line>: <message>" 형식 경고로 돌려준다.
+fn parse_config_file(contents: &str) -> Result<(ConfigFile, Vec<String>), String> {
+    let mut unknown = Vec::new();
+    let de = toml::Deserializer::new(contents);
+    // serde_ignored는 Option 안쪽을 "?"로 표시하므로 제거 ("llm.?.foo" → "llm.foo")
+    let file: ConfigFile = serde_ignored::deserialize(de, |path| {
+        let p: Vec<String> = path.to_string().split('.').filter(|s| *s != "?").map(|s| s.to_string()).collect();
+        unknown.push(p.join("."));
+    })
+        .map_err(|e| e.to_string().trim_end().to_string())?;
+
+    let mut warnings = Vec::new();
+    for path in unknown {
+        warnings.push(format!("{}: unknown key `{}` (ignored)", line_of_key(contents, &path), path));
+    }
+    for name in file.providers.keys() {
+        if parse_llm_service(name).is_none() {
+            let path = format!("providers.{}", name);
+            warnings.push(format!("{}: unknown provider `{}` (ignored)", line_of_section(contents, &path), name));
         }
     }
-    t.to_string()
+    for (key, replacement) in LEGACY_KEYS {
+        if let Some(line) = find_key_line(contents, "", key) {
+            warnings.push(format!("{}: top-level `{}` is deprecated, use {}", line, key, replacement));
+        }
+    }
+
+    Ok((file, warnings))
 }
 
-fn apply_kv(setting: &mut SconnySetting, kv: &HashMap<String, String>) {
-    // llm_service
-    if let Some(v) = kv.get("llm_service") {
-        if let Some(svc) = parse_llm_service(v) {
-            setting.llm_service = svc;
-        }
+fn apply_config_file(setting: &mut SconnySetting, f: ConfigFile, origin: &ConfigOrigin) {
+    // 구식 평면 키를 먼저 적용하고, 섹션 값이 있으면 덮어씀
+    if let Some(v) = f.llm_service {
+        setting.llm_service = v;
+        setting.mark("llm.service", origin);
+    }
+    if let Some(v) = non_empty(f.model) {
+        setting.model = Some(v);
+        setting.mark("llm.model", origin);
+    }
+    if let Some(v) = f.stream {
+        setting.stream = v;
+        setting.mark("llm.stream", origin);
+    }
+    if let Some(v) = non_empty(f.ollama_base_url) {
+        setting.providers.entry(LlmService::Ollama).or_default().base_url = Some(v);
+        setting.mark("providers.ollama.base_url", origin);
+    }
+    if let Some(v) = non_empty(f.gemini_base_url) {
+        setting.providers.entry(LlmService::Gemini).or_default().base_url = Some(v);
+        setting.mark("providers.gemini.base_url", origin);
+    }
+    if let Some(v) = f.os {
+        setting.env.os = v;
+        setting.mark("env.os", origin);
+    }
+    if let Some(v) = non_empty(f.shell) {
+        setting.env.shell = Some(v);
+        setting.mark("env.shell", origin);
+    }
+    if let Some(v) = f.dry_run {
+        setting.policy.dry_run = v;
+        setting.mark("policy.dry_run", origin);
+    }
+    if let Some(v) = f.require_confirmation {
+        setting.policy.require_confirmation = v;
+        setting.mark("policy.require_confirmation", origin);
+    }
+    if let Some(v) = f.timeout_sec {
+        setting.policy.timeout_sec = v;
+        setting.mark("policy.timeout_sec", origin);
     }
 
-    // model
-    if let Some(v) = kv.get("model") {
-        if !v.trim().is_empty() {
-            setting.model = Some(v.trim().to_string());
+    if let Some(llm) = f.llm {
+        if let Some(v) = llm.service {
+            setting.llm_service = v;
+            setting.mark("llm.service", origin);
+        }
+        if let Some(v) = non_empty(llm.model) {
+            setting.model = Some(v);
+            setting.mark("llm.model", origin);
+        }
+        if let Some(v) = llm.stream {
+            setting.stream = v;
+            setting.mark("llm.stream", origin);
+        }
+        if let Some(v) = llm.history_tokens {
+            setting.history_tokens = v;
+            setting.mark("llm.history_tokens", origin);
         }
     }
 
-    // ✅ ollama_base_url
-    if let Some(v) = kv.get("ollama_base_url") {
-        if !v.trim().is_empty() {
-            setting.ollama_base_url = Some(v.trim().to_string());
+    if let Some(p) = f.policy {
+        if let Some(v) = p.dry_run {
+            setting.policy.dry_run = v;
+            setting.mark("policy.dry_run", origin);
+        }
+        if let Some(v) = p.require_confirmation {
+            setting.policy.require_confirmation = v;
+            setting.mark("policy.require_confirmation", origin);
+        }
+        if let Some(v) = p.timeout_sec {
+            setting.policy.timeout_sec = v;
+            setting.mark("policy.timeout_sec", origin);
+        }
+        if let Some(v) = p.max_timeout_sec {
+            setting.policy.max_timeout_sec = v;
+            setting.mark("policy.max_timeout_sec", origin);
+        }
+        if let Some(v) = p.on_error {
+            setting.policy.on_error = v;
+            setting.mark("policy.on_error", origin);
+        }
+        if let Some(v) = p.repair_attempts {
+            setting.policy.repair_attempts = v;
+            setting.mark("policy.repair_attempts", origin);
+        }
+        if let Some(v) = p.sandbox {
+            setting.policy.sandbox = v;
+            setting.mark("policy.sandbox", origin);
+        }
+        if let Some(v) = p.sandbox_max_mb {
+            setting.policy.sandbox_max_mb = v;
+            setting.mark("policy.sandbox_max_mb", origin);
+        }
+        if let Some(v) = p.preview {
+            setting.policy.preview = v;
+            setting.mark("policy.preview", origin);
+        }
+        if let Some(v) = p.undo {
+            setting.policy.undo = v;
+            setting.mark("policy.undo", origin);
+        }
+        if let Some(v) = p.backup_max_mb {
+            setting.policy.backup_max_mb = v;
+            setting.mark("policy.backup_max_mb", origin);
+        }
+        if let Some(v) = p.backup_max_file_mb {
+            setting.policy.backup_max_file_mb = v;
+            setting.mark("policy.backup_max_file_mb", origin);
+        }
+        if let Some(v) = p.step_prefix {
+            setting.policy.step_prefix = v;
+            setting.mark("policy.step_prefix", origin);
+        }
+        if let Some(v) = p.tail_lines {
+            setting.policy.tail_lines = v;
+            setting.mark("policy.tail_lines", origin);
+        }
+        if let Some(sec) = p.allow {
+            apply_rule_list(setting, "allow", sec, origin);
+        }
+        if let Some(sec) = p.deny {
+            apply_rule_list(setting, "deny", sec, origin);
         }
     }
 
-    // os (강제 지정 가능)
-    if let Some(v) = kv.get("os") {
-        if let Some(os) = parse_os(v) {
-            setting.env.os = os;
+    if let Some(e) = f.env {
+        if let Some(v) = e.os {
+            setting.env.os = v;
+            setting.mark("env.os", origin);
+        }
+        if let Some(v) = non_empty(e.shell) {
+            setting.env.shell = Some(v);
+            setting.mark("env.shell", origin);
+        }
+        if let Some(v) = e.tools {
+            setting.env.tools = v;
+            setting.mark("env.tools", origin);
+        }
+        if let Some(v) = e.list_cwd {
+            setting.env.list_cwd = v;
+            setting.mark("env.list_cwd", origin);
+        }
+        if let Some(v) = e.list_depth {
+            setting.env.list_depth = v;
+            setting.mark("env.list_depth", origin);
+        }
+        if let Some(v) = e.list_max_entries {
+            setting.env.list_max_entries = v;
+            setting.mark("env.list_max_entries", origin);
+        }
+        if let Some(v) = e.list_deny {
+            setting.env.list_deny = v;
+            setting.mark("env.list_deny", origin);
+        }
+        if let Some(v) = e.git {
+            setting.env.git = v;
+            setting.mark("env.git", origin);
+        }
+        if let Some(v) = e.git_commits {
+            setting.env.git_commits = v;
+            setting.mark("env.git_commits", origin);
         }
     }
 
-    // shell
-    if let Some(v) = kv.get("shell") {
-        if !v.trim().is_empty() {
-            setting.env.shell = Some(v.trim().to_string());
+    if let Some(h) = f.history {
+        if let Some(v) = h.enabled {
+            setting.history.enabled = v;
+            setting.mark("history.enabled", origin);
+        }
+        if let Some(v) = h.max_entries {
+            setting.history.max_entries = v;
+            setting.mark("history.max_entries", origin);
         }
     }
 
-    // policy
-    if let Some(v) = kv.get("dry_run") {
-        if let Some(b) = parse_bool(v) {
-            setting.policy.dry_run = b;
+    for (name, prov) in f.providers {
+        if let Some(svc) = parse_llm_service(&name) {
+            let prefix = format!("providers.{}", svc.name());
+            if let Some(v) = non_empty(prov.base_url) {
+                setting.providers.entry(svc).or_default().base_url = Some(v);
+                setting.mark(&format!("{}.base_url", prefix), origin);
+            }
+            if let Some(v) = non_empty(prov.model) {
+                setting.providers.entry(svc).or_default().model = Some(v);
+                setting.mark(&format!("{}.model", prefix), origin);
+            }
+            if let Some(v) = prov.timeout_sec {
+                setting.providers.entry(svc).or_default().timeout_sec = Some(v);
+                setting.mark(&format!("{}.timeout_sec", prefix), origin);
+            }
         }
     }
-    if let Some(v) = kv.get("require_confirmation") {
-        if let Some(b) = parse_bool(v) {
-            setting.policy.require_confirmation = b;
+}
+
+fn non_empty(v: Option<String>) -> Option<String> {
+    v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// "policy.foo" → [policy] 아래 foo 키의 줄 번호 ("?" if not found)
+fn line_of_key(contents: &str, path: &str) -> String {
+    let (section, key) = match path.rsplit_once('.') {
+        Some((s, k)) => (s, k),
+        None => ("", path),
+    };
+    find_key_line(contents, section, key)
+        .or_else(|| find_section_line(contents, path))
+        .map(|n| n.to_string())
+        .unwrap_or_else(|| "?".to_string())
+}
+
+fn line_of_section(contents: &str, section: &str) -> String {
+    find_section_line(contents, section)
+        .map(|n| n.to_string())
+        .unwrap_or_else(|| "?".to_string())
+}
+
+fn find_section_line(contents: &str, section: &str) -> Option<usize> {
+    contents
+        .lines()
+        .position(|l| section_header(l).as_deref() == Some(section))
+        .map(|i| i + 1)
+}
+
+fn find_key_line(contents: &str, section: &str, key: &str) -> Option<usize> {
+    let mut current = String::new();
+    for (i, line) in contents.lines().enumerate() {
+        if let Some(h) = section_header(line) {
+            current = h;
+            continue;
         }
-    }
-    if let Some(v) = kv.get("timeout_sec") {
-        if let Ok(n) = v.trim().parse::<u64>() {
-            setting.policy.timeout_sec = n;
+        if current != section {
+            continue;
+        }
+        let t = line.trim_start();
+        let k = match t.split_once('=') {
+            Some((k, _)) => k.trim().trim_matches('"').trim_matches('\''),
+            None => continue,
+        };
+        if k == key {
+            return Some(i + 1);
         }
     }
+    None
 }
 
-fn apply_env_overrides(setting: &mut SconnySetting) {
-    if let Ok(v) = env::var("SCONNY_LLM_SERVICE") {
-        if let Some(svc) = parse_llm_service(&v) {
-            setting.llm_service = svc;
-        }
+fn section_header(line: &str) -> Option<String> {
+    let t = line.trim();
+    let inner = t.strip_prefix("[[").and_then(|x| x.split("]]").next())
+        .or_else(|| t.strip_prefix('[').and_then(|x| x.split(']').next()))?;
+    Some(inner.split('.').map(|p| p.trim().trim_matches('"')).collect::<Vec<_>>().join("."))
+}
+
+// -------------------- internal helpers --------------------
+
+fn detect_os() -> ScyOs {
+    if cfg!(windows) {
+        ScyOs::Windows
+    } else {
+        ScyOs::Linux
+    }
+}
+
+fn strip_quotes(s: &str) -> String {
+    let t = s.trim();
+    if ((t.starts_with('"') && t.ends_with('"')) || (t.starts_with('\'') && t.ends_with('\''))) && t.len() >= 2 {
+        return t[1..t.len() - 1].to_string();
     }
-    if let Ok(v) = env::var("SCONNY_MODEL") {
-        if !v.trim().is_empty() {
-            setting.model = Some(v.trim().to_string());
+    t.to_string()
+}
+
+fn apply_env_overrides(setting: &mut SconnySetting) -> Result<(), String> {
+    let env_origin = |var: &str| ConfigOrigin::Env(var.to_string());
+    // 비어 있으면 설정하지 않은 것으로 보고, 값이 잘못되면 설정 파일과 같이 에러
+    let var = |name: &str| env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let bad = |name: &str, what: &str, v: &str| format!("{}: invalid {} '{}'", name, what, v);
+
+    if let Some(v) = var("SCONNY_LLM_SERVICE") {
+        setting.llm_service = parse_llm_service(&v).ok_or_else(|| bad("SCONNY_LLM_SERVICE", "service", &v))?;
+        setting.mark("llm.service", &env_origin("SCONNY_LLM_SERVICE"));
+    }
+    if let Some(v) = var("SCONNY_MODEL") {
+        setting.model = Some(v);
+        setting.mark("llm.model", &env_origin("SCONNY_MODEL"));
+    }
+
+    // SCONNY_<SERVICE>_BASE_URL
+    for svc in LlmService::ALL {
+        let name = format!("SCONNY_{}_BASE_URL", svc.name().to_uppercase());
+        if let Some(v) = var(&name) {
+            setting.providers.entry(svc).or_default().base_url = Some(v);
+            setting.mark(&format!("providers.{}.base_url", svc.name()), &env_origin(&name));
         }
     }
 
-    if let Ok(v) = env::var("SCONNY_OLLAMA_BASE_URL") {
-        if !v.trim().is_empty() {
-            setting.ollama_base_url = Some(v.trim().to_string());
+    type BoolField = fn(&mut SconnySetting) -> &mut bool;
+    let bools: [(&str, &str, BoolField); 6] = [
+        ("SCONNY_STREAM", "llm.stream", |s| &mut s.stream),
+        ("SCONNY_LIST_CWD", "env.list_cwd", |s| &mut s.env.list_cwd),
+        ("SCONNY_DRY_RUN", "policy.dry_run", |s| &mut s.policy.dry_run),
+        ("SCONNY_CONFIRM", "policy.require_confirmation", |s| &mut s.policy.require_confirmation),
+        ("SCONNY_PREVIEW", "policy.preview", |s| &mut s.policy.preview),
+        ("SCONNY_HISTORY", "history.enabled", |s| &mut s.history.enabled),
+    ];
+    for (name, key, field) in bools {
+        if let Some(v) = var(name) {
+            *field(setting) = parse_bool(&v).ok_or_else(|| bad(name, "bool", &v))?;
+            setting.mark(key, &env_origin(name));
         }
     }
 
-    if let Ok(v) = env::var("SCONNY_OS") {
-        if let Some(os) = parse_os(&v) {
-            setting.env.os = os;
+    if let Some(v) = var("SCONNY_OS") {
+        setting.env.os = parse_os(&v).ok_or_else(|| bad("SCONNY_OS", "os", &v))?;
+        setting.mark("env.os", &env_origin("SCONNY_OS"));
+    }
+    if let Some(v) = var("SCONNY_SHELL") {
+        setting.env.shell = Some(v);
+        setting.mark("env.shell", &env_origin("SCONNY_SHELL"));
+    }
+    if let Some(v) = var("SCONNY_TIMEOUT_SEC") {
+        setting.policy.timeout_sec = v.parse().map_err(|_| bad("SCONNY_TIMEOUT_SEC", "number", &v))?;
+        setting.mark("policy.timeout_sec", &env_origin("SCONNY_TIMEOUT_SEC"));
+    }
+    if let Some(v) = var("SCONNY_SANDBOX") {
+        setting.policy.sandbox = SandboxMode::parse(&v).ok_or_else(|| bad("SCONNY_SANDBOX", "sandbox mode", &v))?;
+        setting.mark("policy.sandbox", &env_origin("SCONNY_SANDBOX"));
+    }
+    Ok(())
+}
+
+/// 프로젝트 설정(.sconny.toml)은 clone 한 저장소에 들어 있을 수 있으므로 보안에 관련된 키는 무시.
+/// (API 키가 전송될 base_url, 확인/dry-run/undo 끄기, allow 목록 넓히기, 실패 후 계속, 파일 목록 전송). 무시한 키 목록 리턴.
+fn restrict_project_file(f: &mut ConfigFile) -> Vec<String> {
+    let mut ignored = Vec::new();
+    let mut drop = |present: bool, key: &str| {
+        if present {
+            ignored.push(key.to_string());
         }
+    };
+    drop(f.ollama_base_url.take().is_some(), "ollama_base_url");
+    drop(f.gemini_base_url.take().is_some(), "gemini_base_url");
+    drop(f.dry_run.take().is_some(), "dry_run");
+    drop(f.require_confirmation.take().is_some(), "require_confirmation");
+    for (name, p) in f.providers.iter_mut() {
+        drop(p.base_url.take().is_some(), &format!("providers.{}.base_url", name));
+    }
+    if let Some(p) = f.policy.as_mut() {
+        drop(p.dry_run.take().is_some(), "policy.dry_run");
+        drop(p.require_confirmation.take().is_some(), "policy.require_confirmation");
+        drop(p.undo.take().is_some(), "policy.undo");
+        // sandbox/preview 는 켜는 것만 받음
+        drop(p.sandbox.take_if(|m| *m == SandboxMode::Off).is_some(), "policy.sandbox");
+        drop(p.preview.take_if(|on| !*on).is_some(), "policy.preview");
+        drop(p.on_error.take().is_some(), "policy.on_error");
+        drop(p.max_timeout_sec.take().is_some(), "policy.max_timeout_sec");
+        drop(p.allow.take().is_some(), "policy.allow");
     }
-    if let Ok(v) = env::var("SCONNY_SHELL") {
-        if !v.trim().is_empty() {
-            setting.env.shell = Some(v.trim().to_string());
+    if let Some(e) = f.env.as_mut() {
+        drop(e.list_cwd.take().is_some(), "env.list_cwd");
+        drop(e.list_deny.take().is_some(), "env.list_deny");
+    }
+    ignored
+}
+
+fn apply_rule_list(setting: &mut SconnySetting, kind: &str, sec: RuleListSection, origin: &ConfigOrigin) {
+    let fields = [("programs", sec.programs), ("args", sec.args), ("paths", sec.paths)];
+    for (field, value) in fields {
+        if let Some(v) = value {
+            merge_rule_list(&mut setting.policy.rules, kind, field, v);
+            setting.mark(&format!("policy.{}.{}", kind, field), origin);
         }
     }
-    if let Ok(v) = env::var("SCONNY_DRY_RUN") {
-        if let Some(b) = parse_bool(&v) {
-            setting.policy.dry_run = b;
+}
+
+/// 뒤 레이어가 시스템 설정의 deny 를 지우거나 줄이지 못하게 deny 는 합치고, allow 는 교체
+fn merge_rule_list(rules: &mut CommandRules, kind: &str, field: &str, values: Vec<String>) {
+    let list = rules.list_mut(kind, field);
+    if kind == "deny" {
+        for v in values {
+            if !list.contains(&v) {
+                list.push(v);
+            }
         }
+    } else {
+        *list = values;
+    }
+}
+
+// -------------------- config discovery --------------------
+
+const PROJECT_CONFIG_FILE: &str = ".sconny.toml";
+const LEGACY_CONFIG_FILE: &str = "sconny_config.toml";
+
+/// 레이어 순서대로 (레이어, 경로). 존재 여부는 호출 측에서 확인.
+fn discover_config_files(explicit: Option<&Path>) -> Vec<(ConfigLayer, PathBuf)> {
+    let mut out = Vec::new();
+
+    out.push((ConfigLayer::System, system_config_path()));
+
+    if let Some(dir) = user_config_dir() {
+        out.push((ConfigLayer::User, dir.join("sconny").join("config.toml")));
     }
-    if let Ok(v) = env::var("SCONNY_CONFIRM") {
-        if let Some(b) = parse_bool(&v) {
-            setting.policy.require_confirmation = b;
+
+    if let Some(p) = find_project_config() {
+        out.push((ConfigLayer::Project, p));
+    }
+
+    // --config 가 SCONNY_CONFIG 보다 우선
+    match explicit {
+        Some(p) => out.push((ConfigLayer::Explicit, p.to_path_buf())),
+        None => {
+            if let Ok(p) = env::var("SCONNY_CONFIG") {
+                if !p.trim().is_empty() {
+                    out.push((ConfigLayer::Explicit, PathBuf::from(p.trim())));
+                }
+            }
         }
     }
-    if let Ok(v) = env::var("SCONNY_TIMEOUT_SEC") {
-        if let Ok(n) = v.trim().parse::<u64>() {
-            setting.policy.timeout_sec = n;
+
+    out
+}
+
+fn system_config_path() -> PathBuf {
+    if cfg!(windows) {
+        let base = env::var("PROGRAMDATA").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+        Path::new(&base).join("sconny").join("config.toml")
+    } else {
+        PathBuf::from("/etc/sconny/config.toml")
+    }
+}
+
+fn user_config_dir() -> Option<PathBuf> {
+    let from_env = |k: &str| env::var(k).ok().filter(|v| !v.trim().is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        return from_env("APPDATA");
+    }
+    from_env("XDG_CONFIG_HOME").or_else(|| from_env("HOME").map(|h| h.join(".config")))
+}
+
+/// 기록(저널, 히스토리 등)을 두는 곳: $XDG_DATA_HOME (없으면 ~/.local/share), Windows: %LOCALAPPDATA%
+pub fn user_data_dir() -> Option<PathBuf> {
+    let from_env = |k: &str| env::var(k).ok().filter(|v| !v.trim().is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        return from_env("LOCALAPPDATA");
+    }
+    from_env("XDG_DATA_HOME").or_else(|| from_env("HOME").map(|h| h.join(".local").join("share")))
+}
+
+/// 다시 만들 수 있는 캐시 (도구 조사 결과 등): $XDG_CACHE_HOME (없으면 ~/.cache), Windows: %LOCALAPPDATA%
+pub fn user_cache_dir() -> Option<PathBuf> {
+    let from_env = |k: &str| env::var(k).ok().filter(|v| !v.trim().is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        return from_env("LOCALAPPDATA");
+    }
+    from_env("XDG_CACHE_HOME").or_else(|| from_env("HOME").map(|h| h.join(".cache")))
+}
+
+/// CWD부터 루트까지 올라가며 가장 가까운 .sconny.toml.
+/// 없으면 CWD의 구식 sconny_config.toml.
+fn find_project_config() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    for dir in cwd.ancestors() {
+        let p = dir.join(PROJECT_CONFIG_FILE);
+        if p.is_file() {
+            return Some(p);
         }
     }
+    let legacy = cwd.join(LEGACY_CONFIG_FILE);
+    if legacy.is_file() {
+        return Some(legacy);
+    }
+    None
 }
 
 fn parse_bool(s: &str) -> Option<bool> {
@@ -253,12 +1069,8 @@ fn parse_bool(s: &str) -> Option<bool> {
 }
 
 fn parse_llm_service(s: &str) -> Option<LlmService> {
-    match s.trim().to_lowercase().as_str() {
-        "openai" => Some(LlmService::OpenAI),
-        "gemini" => Some(LlmService::Gemini),
-        "ollama" => Some(LlmService::Ollama),
-        _ => None,
-    }
+    let s = s.trim().to_lowercase();
+    LlmService::ALL.into_iter().find(|svc| svc.name() == s)
 }
 
 fn parse_os(s: &str) -> Option<ScyOs> {
@@ -321,3 +1133,46 @@ fn parse_os_release(contents: &str) -> HashMap<String, String> {
     }
     map
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_config_drops_sensitive_keys() {
+        let toml = "dry_run = false\n\
+                    [providers.openai]\nbase_url = \"http://evil\"\nmodel = \"m\"\n\
+                    [policy]\nrequire_confirmation = false\nsandbox = \"off\"\ntimeout_sec = 5\n\
+                    preview = false\non_error = \"continue\"\nmax_timeout_sec = 0\n\
+                    deny.programs = [\"curl\"]\nallow.programs = [\"rm\"]\n";
+        let (mut file, _) = parse_config_file(toml).unwrap();
+        let ignored = restrict_project_file(&mut file);
+        assert_eq!(
+            ignored,
+            [
+                "dry_run",
+                "providers.openai.base_url",
+                "policy.require_confirmation",
+                "policy.sandbox",
+                "policy.preview",
+                "policy.on_error",
+                "policy.max_timeout_sec",
+                "policy.allow"
+            ]
+        );
+        // 안전에 영향 없는 값은 남음
+        assert_eq!(file.providers["openai"].model.as_deref(), Some("m"));
+        let policy = file.policy.unwrap();
+        assert_eq!(policy.timeout_sec, Some(5));
+        assert!(policy.deny.is_some());
+    }
+
+    #[test]
+    fn project_config_keeps_sandbox_and_preview_on() {
+        let (mut file, _) = parse_config_file("[policy]\nsandbox = \"auto\"\npreview = true\n").unwrap();
+        assert!(restrict_project_file(&mut file).is_empty());
+        let policy = file.policy.unwrap();
+        assert!(policy.sandbox.is_some());
+        assert_eq!(policy.preview, Some(true));
+    }
+}
@@ -0,0 +1,794 @@
+// scy_shell.rs
+// CommandPlan.cmd 문자열을 분석용 AST로 파싱하는 작은 POSIX sh 파서.
+// 실행용이 아니라 안전성 검사용이므로 확장($VAR, glob)은 하지 않고 글자 그대로 둔다.
+// 대신 $(...) / `...` / <(...) 안쪽은 재귀적으로 파싱해서 Word::subs 에 담는다.
+
+/// 따옴표가 제거된 단어 하나
+#[derive(Debug, Clone, Default)]
+pub struct Word {
+    pub text: String,
+    pub quoted: bool,        // 일부라도 따옴표/escape가 있었는지 ("{" 같은 예약어 판별용)
+    pub subs: Vec<Script>,   // 명령 치환 / 프로세스 치환
+}
+
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    pub op: String,          // ">", ">>", "<", "&>", "2>" ...
+    pub target: Word,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SimpleCommand {
+    pub assignments: Vec<Word>,  // FOO=bar
+    pub words: Vec<Word>,        // argv
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Simple(SimpleCommand),
+    Subshell(Script),                        // ( ... )
+    Group(Script),                           // { ...; }
+    Function { name: String, body: Box<Command> },
+    Case { words: Vec<Word>, arms: Vec<Script> }, // case 대상 단어 + 패턴들, 분기별 본문
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+    pub background: bool,    // 끝이 &
+}
+
+/// ; && || 로 이어진 파이프라인 목록 (연결 연산자는 분석에 필요 없어서 버림)
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    pub pipelines: Vec<Pipeline>,
+}
+
+impl Script {
+    /// 중첩된 것(서브셸, 그룹, 함수 본문, 명령 치환)까지 모든 SimpleCommand를 방문
+    pub fn visit_simple<'a>(&'a self, f: &mut dyn FnMut(&'a SimpleCommand)) {
+        for p in &self.pipelines {
+            for c in &p.commands {
+                c.visit_simple(f);
+            }
+        }
+    }
+}
+
+impl Command {
+    pub fn visit_simple<'a>(&'a self, f: &mut dyn FnMut(&'a SimpleCommand)) {
+        match self {
+            Command::Simple(sc) => {
+                f(sc);
+                let words = sc.assignments.iter().chain(&sc.words).chain(sc.redirects.iter().map(|r| &r.target));
+                for w in words {
+                    for s in &w.subs {
+                        s.visit_simple(f);
+                    }
+                }
+            }
+            Command::Subshell(s) | Command::Group(s) => s.visit_simple(f),
+            Command::Function { body, .. } => body.visit_simple(f),
+            Command::Case { words, arms } => {
+                for s in words.iter().flat_map(|w| &w.subs) {
+                    s.visit_simple(f);
+                }
+                for s in arms {
+                    s.visit_simple(f);
+                }
+            }
+        }
+    }
+}
+
+impl SimpleCommand {
+    pub fn argv(&self) -> Vec<&str> {
+        self.words.iter().map(|w| w.text.as_str()).collect()
+    }
+}
+
+pub fn parse(src: &str) -> Result<Script, String> {
+    let toks = tokenize(src)?;
+    let mut p = Parser { toks, pos: 0 };
+    let script = p.script(&[])?;
+    if let Some(t) = p.peek() {
+        return Err(format!("unexpected {}", t.describe()));
+    }
+    Ok(script)
+}
+
+// -------------------- tokenizer --------------------
+
+#[derive(Debug, Clone)]
+enum Tok {
+    Word(Word),
+    Op(&'static str),        // | || & && ; ( ) |& ;;
+    Redir(String),           // 앞의 fd 숫자 포함
+    Newline,
+}
+
+impl Tok {
+    fn describe(&self) -> String {
+        match self {
+            Tok::Word(w) => format!("'{}'", w.text),
+            Tok::Op(op) => format!("'{}'", op),
+            Tok::Redir(op) => format!("'{}'", op),
+            Tok::Newline => "newline".to_string(),
+        }
+    }
+}
+
+const OPS: [&str; 9] = ["&&", "||", "|&", ";;", "|", "&", ";", "(", ")"];
+const REDIRS: [&str; 10] = ["&>>", "<<<", "<<-", "&>", ">>", ">|", ">&", "<&", "<<", "<>"];
+
+fn is_meta(c: char) -> bool {
+    c.is_whitespace() || "|&;()<>".contains(c)
+}
+
+fn tokenize(src: &str) -> Result<Vec<Tok>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut toks = Vec::new();
+    let mut heredocs: Vec<(String, bool)> = Vec::new(); // (종료 구분자, <<- 여부)
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\n' {
+            i += 1;
+            // 대기 중인 heredoc 본문은 건너뜀
+            for (delim, strip_tabs) in heredocs.drain(..) {
+                loop {
+                    if i >= chars.len() {
+                        break;
+                    }
+                    let end = chars[i..].iter().position(|&c| c == '\n').map(|p| i + p).unwrap_or(chars.len());
+                    let line: String = chars[i..end].iter().collect();
+                    i = (end + 1).min(chars.len());
+                    let line = if strip_tabs { line.trim_start_matches('\t') } else { line.as_str() };
+                    if line == delim {
+                        break;
+                    }
+                }
+            }
+            toks.push(Tok::Newline);
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '\\' && chars.get(i + 1) == Some(&'\n') {
+            i += 2;
+            continue;
+        }
+        if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        // 프로세스 치환 <(...) >(...) 은 단어로 취급
+        if (c == '<' || c == '>') && chars.get(i + 1) == Some(&'(') {
+            let (w, next) = read_word(&chars, i)?;
+            toks.push(Tok::Word(w));
+            i = next;
+            continue;
+        }
+
+        // fd 숫자가 붙은 리다이렉션: 2> 2>> 1>&2
+        let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0 && matches!(chars.get(i + digits), Some('<') | Some('>')) {
+            let fd: String = chars[i..i + digits].iter().collect();
+            let (op, len) = read_redir(&chars, i + digits);
+            toks.push(Tok::Redir(format!("{}{}", fd, op)));
+            i += digits + len;
+            continue;
+        }
+
+        if c == '<' || c == '>' || (c == '&' && chars.get(i + 1) == Some(&'>')) {
+            let (op, len) = read_redir(&chars, i);
+            i += len;
+            if op == "<<" || op == "<<-" {
+                // heredoc 구분자 (따옴표 제거)
+                while i < chars.len() && (chars[i] == ' ' || chars[i] == '\t') {
+                    i += 1;
+                }
+                let (w, next) = read_word(&chars, i)?;
+                heredocs.push((w.text.clone(), op == "<<-"));
+                toks.push(Tok::Redir(op));
+                toks.push(Tok::Word(w));
+                i = next;
+                continue;
+            }
+            toks.push(Tok::Redir(op));
+            continue;
+        }
+
+        if let Some(op) = OPS.iter().find(|op| starts_with(&chars, i, op)) {
+            toks.push(Tok::Op(op));
+            i += op.len();
+            continue;
+        }
+
+        let (w, next) = read_word(&chars, i)?;
+        toks.push(Tok::Word(w));
+        i = next;
+    }
+
+    Ok(toks)
+}
+
+fn starts_with(chars: &[char], at: usize, s: &str) -> bool {
+    s.chars().enumerate().all(|(k, c)| chars.get(at + k) == Some(&c))
+}
+
+fn read_redir(chars: &[char], at: usize) -> (String, usize) {
+    for op in REDIRS {
+        if starts_with(chars, at, op) {
+            return (op.to_string(), op.len());
+        }
+    }
+    (chars[at].to_string(), 1)
+}
+
+/// at 위치부터 단어 하나를 읽어 (Word, 다음 위치)
+fn read_word(chars: &[char], at: usize) -> Result<(Word, usize), String> {
+    let mut w = Word::default();
+    let mut i = at;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                w.quoted = true;
+                match chars.get(i + 1) {
+                    Some('\n') => {}
+                    Some(&n) => w.text.push(n),
+                    None => {}
+                }
+                i += 2;
+            }
+            '\'' => {
+                w.quoted = true;
+                let end = find_char(chars, i + 1, '\'').ok_or("unterminated single quote")?;
+                w.text.extend(&chars[i + 1..end]);
+                i = end + 1;
+            }
+            '"' => {
+                w.quoted = true;
+                i = read_double_quoted(chars, i + 1, &mut w)?;
+            }
+            '`' => {
+                i = read_backtick(chars, i + 1, &mut w)?;
+            }
+            '$' => {
+                i = read_dollar(chars, i, &mut w)?;
+            }
+            '<' | '>' if chars.get(i + 1) == Some(&'(') && w.text.is_empty() => {
+                let end = find_closing_paren(chars, i + 2)?;
+                let inner: String = chars[i + 2..end].iter().collect();
+                w.subs.push(parse(&inner)?);
+                w.text.extend(&chars[i..=end]);
+                i = end + 1;
+            }
+            _ if is_meta(c) => break,
+            _ => {
+                w.text.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    Ok((w, i))
+}
+
+fn find_char(chars: &[char], from: usize, target: char) -> Option<usize> {
+    (from..chars.len()).find(|&k| chars[k] == target)
+}
+
+fn read_double_quoted(chars: &[char], mut i: usize, w: &mut Word) -> Result<usize, String> {
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Ok(i + 1),
+            '\\' => {
+                match chars.get(i + 1) {
+                    Some(&n) if "$`\"\\".contains(n) => w.text.push(n),
+                    Some('\n') => {}
+                    Some(&n) => {
+                        w.text.push('\\');
+                        w.text.push(n);
+                    }
+                    None => {}
+                }
+                i += 2;
+            }
+            '`' => i = read_backtick(chars, i + 1, w)?,
+            '$' => i = read_dollar(chars, i, w)?,
+            c => {
+                w.text.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err("unterminated double quote".to_string())
+}
+
+fn read_backtick(chars: &[char], from: usize, w: &mut Word) -> Result<usize, String> {
+    let mut inner = String::new();
+    let mut i = from;
+    while i < chars.len() {
+        match chars[i] {
+            '`' => {
+                w.subs.push(parse(&inner)?);
+                w.text.push('`');
+                w.text.push_str(&inner);
+                w.text.push('`');
+                return Ok(i + 1);
+            }
+            '\\' if i + 1 < chars.len() => {
+                inner.push(chars[i + 1]);
+                i += 2;
+            }
+            c => {
+                inner.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err("unterminated backquote".to_string())
+}
+
+/// $VAR, ${...}, $(...), $((...)) — chars[at] == '$'
+fn read_dollar(chars: &[char], at: usize, w: &mut Word) -> Result<usize, String> {
+    match chars.get(at + 1) {
+        Some('(') if chars.get(at + 2) == Some(&'(') => {
+            // 산술 확장: 내용은 그대로 둠
+            let end = find_closing_paren(chars, at + 2)?;
+            w.text.extend(&chars[at..=end]);
+            Ok(end + 1)
+        }
+        Some('(') => {
+            let end = find_closing_paren(chars, at + 2)?;
+            let inner: String = chars[at + 2..end].iter().collect();
+            w.subs.push(parse(&inner)?);
+            w.text.extend(&chars[at..=end]);
+            Ok(end + 1)
+        }
+        Some('{') => read_brace(chars, at + 2, w),
+        _ => {
+            w.text.push('$');
+            Ok(at + 1)
+        }
+    }
+}
+
+/// ${...} 의 본문. from 은 '{' 바로 다음 위치.
+/// ${x:-$(cmd)} 처럼 안에 있는 치환도 subs 에 담고, 중첩된 ${...} 는 짝을 맞춰 닫는다.
+fn read_brace(chars: &[char], from: usize, w: &mut Word) -> Result<usize, String> {
+    w.text.push_str("${");
+    let mut i = from;
+    while i < chars.len() {
+        match chars[i] {
+            '}' => {
+                w.text.push('}');
+                return Ok(i + 1);
+            }
+            '\\' => {
+                if let Some(&n) = chars.get(i + 1) {
+                    w.text.push(n);
+                }
+                i += 2;
+            }
+            '\'' => {
+                let end = find_char(chars, i + 1, '\'').ok_or("unterminated single quote")?;
+                w.text.extend(&chars[i + 1..end]);
+                i = end + 1;
+            }
+            '"' => i = read_double_quoted(chars, i + 1, w)?,
+            '`' => i = read_backtick(chars, i + 1, w)?,
+            '$' => i = read_dollar(chars, i, w)?,
+            c => {
+                w.text.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err("unterminated ${".to_string())
+}
+
+/// from 은 '(' 바로 다음 위치. 따옴표를 고려해 짝이 맞는 ')' 위치를 찾는다.
+fn find_closing_paren(chars: &[char], from: usize) -> Result<usize, String> {
+    let mut depth = 1;
+    let mut i = from;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '\'' => i = find_char(chars, i + 1, '\'').ok_or("unterminated single quote")?,
+            '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Err("unterminated $( or (".to_string())
+}
+
+// -------------------- parser --------------------
+
+struct Parser {
+    toks: Vec<Tok>,
+    pos: usize,
+}
+
+// 명령 앞에 붙는 제어 예약어: 뒤따르는 명령만 분석하면 됨
+const LEADING_KEYWORDS: [&str; 9] = ["if", "then", "else", "elif", "while", "until", "do", "!", "time"];
+const CLOSING_KEYWORDS: [&str; 3] = ["fi", "done", "esac"];
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Tok> {
+        let t = self.toks.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn peek_op(&self, op: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Op(o)) if *o == op)
+    }
+
+    fn peek_keyword(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Word(w)) if !w.quoted && w.text == kw)
+    }
+
+    /// `end` 중 하나(")", "}", ";;", "esac")를 만나면 멈춤 (소비하지 않음)
+    fn script(&mut self, end: &[&str]) -> Result<Script, String> {
+        let mut script = Script::default();
+        loop {
+            // 구분자 건너뛰기 (case 분기 안에서는 ";;" 가 끝)
+            while matches!(self.peek(), Some(Tok::Newline))
+                || self.peek_op(";")
+                || (self.peek_op(";;") && !end.contains(&";;"))
+            {
+                self.pos += 1;
+            }
+            match self.peek() {
+                None => break,
+                Some(Tok::Op(op)) if end.contains(op) => break,
+                Some(Tok::Word(w)) if !w.quoted && end.contains(&w.text.as_str()) => break,
+                _ => {}
+            }
+
+            let start = self.pos;
+            let mut pipeline = self.pipeline(end)?;
+            // 아무 토큰도 소비하지 못했으면 여기서 처리할 수 없는 토큰 (짝 없는 ')' 등)
+            if self.pos == start {
+                return Err(format!("unexpected {}", describe(self.peek().cloned())));
+            }
+            loop {
+                if self.peek_op("&") {
+                    self.pos += 1;
+                    pipeline.background = true;
+                    break;
+                }
+                if self.peek_op("&&") || self.peek_op("||") {
+                    self.pos += 1;
+                    while matches!(self.peek(), Some(Tok::Newline)) {
+                        self.pos += 1;
+                    }
+                    script.pipelines.push(pipeline);
+                    pipeline = self.pipeline(end)?;
+                    continue;
+                }
+                break;
+            }
+            script.pipelines.push(pipeline);
+        }
+        Ok(script)
+    }
+
+    fn pipeline(&mut self, end: &[&str]) -> Result<Pipeline, String> {
+        let mut p = Pipeline::default();
+        p.commands.push(self.command(end)?);
+        while self.peek_op("|") || self.peek_op("|&") {
+            self.pos += 1;
+            while matches!(self.peek(), Some(Tok::Newline)) {
+                self.pos += 1;
+            }
+            p.commands.push(self.command(end)?);
+        }
+        Ok(p)
+    }
+
+    fn command(&mut self, end: &[&str]) -> Result<Command, String> {
+        // 제어 예약어는 건너뛰고 뒤 명령을 봄
+        while LEADING_KEYWORDS.iter().any(|k| self.peek_keyword(k)) {
+            self.pos += 1;
+        }
+
+        if self.peek_op("(") {
+            self.pos += 1;
+            let inner = self.script(&[")"])?;
+            self.expect_op(")")?;
+            self.skip_redirects()?;
+            return Ok(Command::Subshell(inner));
+        }
+        if self.peek_keyword("{") {
+            self.pos += 1;
+            let inner = self.script(&["}"])?;
+            if !self.peek_keyword("}") {
+                return Err("missing '}'".to_string());
+            }
+            self.pos += 1;
+            self.skip_redirects()?;
+            return Ok(Command::Group(inner));
+        }
+        if self.peek_keyword("case") {
+            return self.case();
+        }
+        if self.peek_keyword("function") {
+            self.pos += 1;
+            let name = match self.next() {
+                Some(Tok::Word(w)) => w.text,
+                other => return Err(format!("expected function name, got {}", describe(other))),
+            };
+            if self.peek_op("(") {
+                self.pos += 1;
+                self.expect_op(")")?;
+            }
+            return self.function_body(name, end);
+        }
+
+        // name() { ... }
+        if let (Some(Tok::Word(w)), Some(Tok::Op("(")), Some(Tok::Op(")"))) =
+            (self.toks.get(self.pos), self.toks.get(self.pos + 1), self.toks.get(self.pos + 2))
+        {
+            let name = w.text.clone();
+            self.pos += 3;
+            return self.function_body(name, end);
+        }
+
+        self.simple(end)
+    }
+
+    /// case WORD in [(]PAT[|PAT]...) LIST ;; ... esac
+    fn case(&mut self) -> Result<Command, String> {
+        self.pos += 1;
+        let mut words = match self.next() {
+            Some(Tok::Word(w)) => vec![w],
+            other => return Err(format!("expected word after 'case', got {}", describe(other))),
+        };
+        self.skip_newlines();
+        if !self.peek_keyword("in") {
+            return Err(format!("expected 'in', got {}", describe(self.peek().cloned())));
+        }
+        self.pos += 1;
+
+        let mut arms = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.peek_keyword("esac") {
+                self.pos += 1;
+                break;
+            }
+            if self.peek_op("(") {
+                self.pos += 1;
+            }
+            loop {
+                match self.next() {
+                    Some(Tok::Word(w)) => words.push(w),
+                    other => return Err(format!("expected case pattern, got {}", describe(other))),
+                }
+                if !self.peek_op("|") {
+                    break;
+                }
+                self.pos += 1;
+            }
+            self.expect_op(")")?;
+            arms.push(self.script(&[";;", "esac"])?);
+            if self.peek_op(";;") {
+                self.pos += 1;
+            } else if !self.peek_keyword("esac") {
+                return Err(format!("expected ';;' or 'esac', got {}", describe(self.peek().cloned())));
+            }
+        }
+        self.skip_redirects()?;
+        Ok(Command::Case { words, arms })
+    }
+
+    fn skip_newlines(&mut self) {
+        while matches!(self.peek(), Some(Tok::Newline)) {
+            self.pos += 1;
+        }
+    }
+
+    fn function_body(&mut self, name: String, end: &[&str]) -> Result<Command, String> {
+        while matches!(self.peek(), Some(Tok::Newline)) {
+            self.pos += 1;
+        }
+        let body = self.command(end)?;
+        Ok(Command::Function { name, body: Box::new(body) })
+    }
+
+    fn simple(&mut self, end: &[&str]) -> Result<Command, String> {
+        let mut sc = SimpleCommand::default();
+        loop {
+            match self.peek() {
+                Some(Tok::Word(w)) => {
+                    if sc.words.is_empty() && !w.quoted && (end.contains(&w.text.as_str()) || CLOSING_KEYWORDS.contains(&w.text.as_str())) {
+                        if CLOSING_KEYWORDS.contains(&w.text.as_str()) {
+                            self.pos += 1;
+                            continue;
+                        }
+                        break;
+                    }
+                    let w = w.clone();
+                    self.pos += 1;
+                    if sc.words.is_empty() && is_assignment(&w) {
+                        sc.assignments.push(w);
+                    } else {
+                        sc.words.push(w);
+                    }
+                }
+                Some(Tok::Redir(op)) => {
+                    let op = op.clone();
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Tok::Word(target)) => sc.redirects.push(Redirect { op, target }),
+                        other => return Err(format!("expected redirect target after '{}', got {}", op, describe(other))),
+                    }
+                }
+                _ => break,
+            }
+        }
+        Ok(Command::Simple(sc))
+    }
+
+    fn skip_redirects(&mut self) -> Result<(), String> {
+        while let Some(Tok::Redir(_)) = self.peek() {
+            self.pos += 1;
+            if !matches!(self.next(), Some(Tok::Word(_))) {
+                return Err("expected redirect target".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Tok::Op(o)) if o == op => Ok(()),
+            other => Err(format!("expected '{}', got {}", op, describe(other))),
+        }
+    }
+}
+
+fn describe(t: Option<Tok>) -> String {
+    t.map(|t| t.describe()).unwrap_or_else(|| "end of input".to_string())
+}
+
+fn is_assignment(w: &Word) -> bool {
+    match w.text.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 모든 SimpleCommand 의 argv (중첩 포함, 방문 순서)
+    fn argvs(src: &str) -> Vec<Vec<String>> {
+        let script = parse(src).unwrap_or_else(|e| panic!("parse {:?}: {}", src, e));
+        let mut out = Vec::new();
+        script.visit_simple(&mut |sc| out.push(sc.argv().iter().map(|s| s.to_string()).collect()));
+        out.retain(|a: &Vec<String>| !a.is_empty());
+        out
+    }
+
+    #[test]
+    fn simple_and_lists() {
+        assert_eq!(argvs("echo hi"), vec![vec!["echo", "hi"]]);
+        assert_eq!(argvs("mkdir -p out && tar -czf out/a.tgz a.txt; ls | wc -l"), vec![
+            vec!["mkdir", "-p", "out"],
+            vec!["tar", "-czf", "out/a.tgz", "a.txt"],
+            vec!["ls"],
+            vec!["wc", "-l"],
+        ]);
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        assert_eq!(argvs(r#"echo 'a b' "c $HOME" d\ e"#), vec![vec!["echo", "a b", "c $HOME", "d e"]]);
+    }
+
+    #[test]
+    fn nested_substitutions_are_parsed() {
+        assert_eq!(argvs("echo $(rm -rf x) `id`"), vec![vec!["echo", "$(rm -rf x)", "`id`"], vec!["rm", "-rf", "x"], vec!["id"]]);
+        assert_eq!(argvs("diff <(ls a) <(ls b)").len(), 3);
+    }
+
+    #[test]
+    fn substitutions_inside_parameter_expansion() {
+        assert_eq!(argvs("echo ${x:-$(rm -rf /)}"), vec![vec!["echo", "${x:-$(rm -rf /)}"], vec!["rm", "-rf", "/"]]);
+        assert_eq!(argvs(r#"echo "${x:-`id`}" y"#), vec![vec!["echo", "${x:-`id`}", "y"], vec!["id"]]);
+        // 중첩된 ${} 는 바깥 } 까지 한 단어
+        assert_eq!(argvs("echo ${a:-${b}}c d"), vec![vec!["echo", "${a:-${b}}c", "d"]]);
+        assert_eq!(argvs("echo ${a:-'}'} x"), vec![vec!["echo", "${a:-}}", "x"]]);
+        assert!(parse("echo ${a:-${b}").is_err());
+    }
+
+    #[test]
+    fn redirects_and_assignments() {
+        let script = parse("FOO=1 cmd 2>&1 > out.txt").unwrap();
+        let Command::Simple(sc) = &script.pipelines[0].commands[0] else { panic!("not simple") };
+        assert_eq!(sc.assignments[0].text, "FOO=1");
+        assert_eq!(sc.argv(), vec!["cmd"]);
+        assert_eq!(sc.redirects.iter().map(|r| r.op.as_str()).collect::<Vec<_>>(), vec!["2>&", ">"]);
+    }
+
+    #[test]
+    fn heredoc_body_is_skipped() {
+        assert_eq!(argvs("cat <<EOF > f\nrm -rf /\nEOF\nls"), vec![vec!["cat"], vec!["ls"]]);
+    }
+
+    #[test]
+    fn subshell_group_function_and_background() {
+        let script = parse("(cd a && make) & { echo x; }").unwrap();
+        assert!(script.pipelines[0].background);
+        assert!(matches!(script.pipelines[0].commands[0], Command::Subshell(_)));
+        assert!(matches!(script.pipelines[1].commands[0], Command::Group(_)));
+        assert!(matches!(parse("f() { f | f & }; f").unwrap().pipelines[0].commands[0], Command::Function { .. }));
+    }
+
+    #[test]
+    fn control_keywords() {
+        assert_eq!(argvs("if test -f a; then rm a; fi"), vec![vec!["test", "-f", "a"], vec!["rm", "a"]]);
+        assert_eq!(argvs("for f in *.log; do gzip \"$f\"; done")[1], vec!["gzip", "$f"]);
+    }
+
+    #[test]
+    fn case_statement() {
+        assert_eq!(argvs(r#"case "$x" in *.log) rm "$x";; esac"#), vec![vec!["rm", "$x"]]);
+        assert_eq!(
+            argvs("case $1 in\n  (a|b) echo ab ;;\n  *) echo other; exit 1 ;;\nesac\necho done"),
+            vec![vec!["echo", "ab"], vec!["echo", "other"], vec!["exit", "1"], vec!["echo", "done"]]
+        );
+        assert_eq!(argvs("case x in x) ;; esac"), Vec::<Vec<String>>::new());
+        assert!(parse("case x in x) echo").is_err());
+    }
+
+    #[test]
+    fn stray_tokens_are_errors() {
+        assert!(parse("echo a)").is_err());
+        assert!(parse(")").is_err());
+        assert!(parse("a && )").is_err());
+        assert!(parse("(echo a").is_err());
+        assert!(parse("echo 'a").is_err());
+        assert!(parse("echo $(ls").is_err());
+        assert!(parse("cat >").is_err());
+    }
+}
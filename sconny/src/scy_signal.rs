@@ -0,0 +1,77 @@
+// scy_signal.rs
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+// Ctrl+C 처리
+// - 취소 가능한 작업(스트리밍 등)이 진행 중이면: 취소 플래그만 세우고 작업이 스스로 멈추게 함
+// - 이미 취소 요청된 상태에서 한 번 더 누르거나, 진행 중인 작업이 없으면: 즉시 종료(130)
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static CHILD_GROUP: AtomicU32 = AtomicU32::new(0); // 실행 중인 명령의 프로세스 그룹 (0: 없음)
+
+pub fn install_handler() -> Result<(), String> {
+    ctrlc::set_handler(|| {
+        if ACTIVE.load(Ordering::SeqCst) && !CANCELLED.swap(true, Ordering::SeqCst) {
+            eprintln!("\n^C cancelling... (press Ctrl+C again to quit)");
+            return;
+        }
+        kill_child_group();
+        process::exit(130);
+    })
+    .map_err(|e| format!("failed to install Ctrl+C handler: {}", e))
+}
+
+/// 취소 가능한 구간. drop 되면 구간 종료.
+pub struct CancelScope {
+    _private: (),
+}
+
+impl CancelScope {
+    pub fn begin() -> Self {
+        CANCELLED.store(false, Ordering::SeqCst);
+        ACTIVE.store(true, Ordering::SeqCst);
+        Self { _private: () }
+    }
+}
+
+impl Drop for CancelScope {
+    fn drop(&mut self) {
+        ACTIVE.store(false, Ordering::SeqCst);
+        CANCELLED.store(false, Ordering::SeqCst);
+    }
+}
+
+/// 실행 중인 명령의 프로세스 그룹. drop 되면 해제.
+/// 두 번째 Ctrl+C 로 sconny 가 종료될 때 남은 명령도 같이 종료하기 위함.
+pub struct ChildGroup {
+    _private: (),
+}
+
+impl ChildGroup {
+    pub fn register(pgid: u32) -> Self {
+        CHILD_GROUP.store(pgid, Ordering::SeqCst);
+        Self { _private: () }
+    }
+}
+
+impl Drop for ChildGroup {
+    fn drop(&mut self) {
+        CHILD_GROUP.store(0, Ordering::SeqCst);
+    }
+}
+
+fn kill_child_group() {
+    let pgid = CHILD_GROUP.load(Ordering::SeqCst);
+    #[cfg(unix)]
+    if pgid != 0 {
+        unsafe {
+            libc::kill(-(pgid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = pgid;
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,298 @@
+// scy_tools.rs
+// 자주 쓰는 도구(tar, zip, git, docker ...)가 설치되어 있는지, 버전은 무엇인지 조사해서
+// 프롬프트에 넣는다. 모델이 없는 도구(zip, rg, 7z 등)를 쓰는 계획을 내지 않게 하려는 것.
+// 버전은 `<tool> --version` 을 실행해야 해서 호스트별로 캐시하고 ($XDG_CACHE_HOME/sconny/tools-<host>.json),
+// 설치 여부는 매번 PATH 에서 다시 확인한다. 실행 파일이 바뀌면(경로/수정 시각) 버전을 다시 조사.
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::scy_setting::user_cache_dir;
+
+/// env.tools 기본값
+pub const DEFAULT_TOOLS: [&str; 17] = [
+    "tar", "gzip", "xz", "zstd", "zip", "unzip", "7z", "git", "docker", "rsync", "jq", "python3", "rg", "fd", "curl",
+    "wget", "make",
+];
+
+/// 목록에 없어도 하나라도 있으면 알려주는 패키지 매니저 (앞에 있는 것이 우선)
+const PACKAGE_MANAGERS: [&str; 9] = ["apt", "dnf", "yum", "pacman", "zypper", "apk", "brew", "winget", "choco"];
+
+const VERSION_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_VERSION_LEN: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolInfo {
+    pub name: String,
+    pub path: Option<PathBuf>,   // None: 설치 안 됨
+    pub version: Option<String>, // 알아내지 못하면 None
+    #[serde(default)]
+    mtime: u64,
+}
+
+/// 조사 결과
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    pub tools: Vec<ToolInfo>,
+    pub package_manager: Option<String>,
+}
+
+impl Inventory {
+    /// 프롬프트용 몇 줄
+    pub fn prompt_lines(&self) -> Vec<String> {
+        let found: Vec<String> = self
+            .tools
+            .iter()
+            .filter(|t| t.path.is_some())
+            .map(|t| match &t.version {
+                Some(v) => format!("{} {}", t.name, v),
+                None => t.name.clone(),
+            })
+            .collect();
+        let missing: Vec<&str> = self.tools.iter().filter(|t| t.path.is_none()).map(|t| t.name.as_str()).collect();
+
+        let mut lines = Vec::new();
+        if !found.is_empty() {
+            lines.push(format!("- Installed tools: {}", found.join(", ")));
+        }
+        if !missing.is_empty() {
+            lines.push(format!("- NOT installed: {}", missing.join(", ")));
+        }
+        if let Some(pm) = &self.package_manager {
+            lines.push(format!("- Package manager: {}", pm));
+        }
+        lines
+    }
+}
+
+/// names 를 조사. 캐시에 있고 실행 파일이 그대로면 버전은 캐시 값을 씀.
+pub fn inventory(names: &[String]) -> Inventory {
+    if names.is_empty() {
+        return Inventory::default();
+    }
+    let cache_path = cache_file();
+    let mut cache: BTreeMap<String, ToolInfo> = cache_path
+        .as_ref()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|t| serde_json::from_str(&t).ok())
+        .unwrap_or_default();
+
+    let path_dirs: Vec<PathBuf> = env::var_os("PATH").map(|p| env::split_paths(&p).collect()).unwrap_or_default();
+    let located: Vec<(String, Option<(PathBuf, u64)>)> =
+        names.iter().map(|n| (n.clone(), find_executable(n, &path_dirs))).collect();
+
+    // 캐시와 다른 것만 버전 조사 (병렬)
+    let stale: Vec<(String, PathBuf, u64)> = located
+        .iter()
+        .filter_map(|(name, loc)| {
+            let (path, mtime) = loc.as_ref()?;
+            let cached = cache.get(name);
+            let fresh = cached.is_some_and(|c| c.path.as_ref() == Some(path) && c.mtime == *mtime);
+            (!fresh).then(|| (name.clone(), path.clone(), *mtime))
+        })
+        .collect();
+    let probed: Vec<ToolInfo> = thread::scope(|s| {
+        let handles: Vec<_> = stale
+            .iter()
+            .map(|(name, path, mtime)| {
+                s.spawn(move || ToolInfo { name: name.clone(), path: Some(path.clone()), version: probe_version(name, path), mtime: *mtime })
+            })
+            .collect();
+        handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+    let changed = !probed.is_empty();
+    for t in probed {
+        cache.insert(t.name.clone(), t);
+    }
+    if changed {
+        if let Some(p) = &cache_path {
+            if let Some(dir) = p.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Ok(text) = serde_json::to_string_pretty(&cache) {
+                let _ = fs::write(p, text);
+            }
+        }
+    }
+
+    let tools = located
+        .into_iter()
+        .map(|(name, loc)| match loc {
+            Some(_) => cache.get(&name).cloned().unwrap_or(ToolInfo { name, path: None, version: None, mtime: 0 }),
+            None => ToolInfo { name, path: None, version: None, mtime: 0 },
+        })
+        .collect();
+    let package_manager = PACKAGE_MANAGERS
+        .iter()
+        .find(|pm| find_executable(pm, &path_dirs).is_some())
+        .map(|pm| pm.to_string());
+    Inventory { tools, package_manager }
+}
+
+/// PATH 에서 실행 파일 찾기. (경로, 수정 시각)
+fn find_executable(name: &str, dirs: &[PathBuf]) -> Option<(PathBuf, u64)> {
+    if name.contains(['/', '\\']) {
+        return None;
+    }
+    let exts: Vec<String> = if cfg!(windows) {
+        env::var("PATHEXT").unwrap_or_else(|_| ".EXE;.CMD;.BAT".to_string()).split(';').map(|e| e.to_string()).collect()
+    } else {
+        vec![String::new()]
+    };
+    for dir in dirs {
+        for ext in &exts {
+            let p = dir.join(format!("{}{}", name, ext));
+            if let Ok(meta) = fs::metadata(&p) {
+                if meta.is_file() && is_executable(&meta) {
+                    let mtime = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()).unwrap_or(0);
+                    return Some((p, mtime));
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &fs::Metadata) -> bool {
+    true
+}
+
+/// 버전 출력이 --version 이 아닌 도구
+fn version_args(name: &str) -> &'static [&'static str] {
+    match name {
+        "zip" => &["-v"],
+        "unzip" => &["-v"],
+        "7z" | "7za" | "7zz" => &[],
+        "java" => &["-version"],
+        _ => &["--version"],
+    }
+}
+
+fn probe_version(name: &str, path: &Path) -> Option<String> {
+    let mut child = Command::new(path)
+        .args(version_args(name))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < VERSION_TIMEOUT => thread::sleep(Duration::from_millis(20)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+    let mut text = String::new();
+    if let Some(mut out) = child.stdout.take() {
+        let _ = out.read_to_string(&mut text);
+    }
+    if let Some(mut err) = child.stderr.take() {
+        let _ = err.read_to_string(&mut text);
+    }
+    parse_version(&text)
+}
+
+/// 출력 앞쪽에서 처음 나오는 버전처럼 생긴 토큰 ("git version 2.43.0" → "2.43.0")
+fn parse_version(text: &str) -> Option<String> {
+    text.lines().take(5).flat_map(|l| l.split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')).find_map(|tok| {
+        // "jq-1.6" 처럼 이름-버전
+        let tok = match tok.split_once('-') {
+            Some((name, v)) if name.chars().all(|c| c.is_ascii_alphabetic()) => v,
+            _ => tok,
+        };
+        let tok = tok.trim_start_matches(['v', 'V']).trim_end_matches(['.', ':', ';']);
+        let starts_digit = tok.chars().next().is_some_and(|c| c.is_ascii_digit());
+        (starts_digit && tok.contains('.') && tok.len() <= MAX_VERSION_LEN).then(|| tok.to_string())
+    })
+}
+
+fn cache_file() -> Option<PathBuf> {
+    let host: String = hostname().chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect();
+    user_cache_dir().map(|d| d.join("sconny").join(format!("tools-{}.json", host)))
+}
+
+fn hostname() -> String {
+    let from_env = |k: &str| env::var(k).ok().filter(|v| !v.trim().is_empty());
+    from_env("HOSTNAME")
+        .or_else(|| from_env("COMPUTERNAME"))
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_version_outputs() {
+        let v = |s: &str| parse_version(s);
+        assert_eq!(v("git version 2.43.0").as_deref(), Some("2.43.0"));
+        assert_eq!(v("jq-1.6").as_deref(), Some("1.6"));
+        assert_eq!(v("v20.11.0\n").as_deref(), Some("20.11.0"));
+        assert_eq!(v("tar (GNU tar) 1.35\nCopyright (C) 2023").as_deref(), Some("1.35"));
+        assert_eq!(v("curl 8.5.0 (x86_64-pc-linux-gnu) libcurl/8.5.0").as_deref(), Some("8.5.0"));
+        assert_eq!(v("rsync  version 3.2.7  protocol version 31").as_deref(), Some("3.2.7"));
+        // 점이 없는 숫자, 버전 없는 출력
+        assert_eq!(v("protocol 31"), None);
+        assert_eq!(v("usage: foo [options]"), None);
+        assert_eq!(v(""), None);
+    }
+
+    #[test]
+    fn prompt_lines_split_installed_and_missing() {
+        let tool = |name: &str, installed: bool, version: Option<&str>| ToolInfo {
+            name: name.to_string(),
+            path: installed.then(|| PathBuf::from(format!("/usr/bin/{}", name))),
+            version: version.map(str::to_string),
+            mtime: 0,
+        };
+        let inv = Inventory {
+            tools: vec![tool("git", true, Some("2.43.0")), tool("jq", false, None), tool("rg", true, None)],
+            package_manager: Some("apt".to_string()),
+        };
+        assert_eq!(inv.prompt_lines(), ["- Installed tools: git 2.43.0, rg", "- NOT installed: jq", "- Package manager: apt"]);
+        assert!(Inventory::default().prompt_lines().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn find_executable_needs_exec_bit() {
+        use std::os::unix::fs::PermissionsExt;
+        let nanos = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+        let dir = env::temp_dir().join(format!("sconny-tools-test-{}-{}", std::process::id(), nanos));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, mode) in [("runme", 0o755), ("plain", 0o644)] {
+            fs::write(dir.join(name), "#!/bin/sh\n").unwrap();
+            fs::set_permissions(dir.join(name), fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let dirs = [dir.join("missing"), dir.clone()];
+        assert_eq!(find_executable("runme", &dirs).map(|(p, _)| p), Some(dir.join("runme")));
+        assert!(find_executable("plain", &dirs).is_none());
+        assert!(find_executable("../runme", &dirs).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}